@@ -1,19 +1,66 @@
 use std::io::Seek;
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::{
-    fs::{File, OpenOptions},
-    io::{Read, Write},
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
 };
 
 use crate::content::{Content, Line};
-use crate::cursor::{Cursor, EditLocation, EditorPosition};
+use crate::cursor::{Cursor, EditLocation, EditorPosition, Selection};
+use crate::diff::{diff_lines, LineDiff};
+use crate::text::{WordBoundaries, WrappedText};
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+pub const DEFAULT_FILE_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
 
 pub struct Buffer {
     content: Content,
     edit_location: EditLocation,
     file: Option<File>,
+    path: Option<PathBuf>,
+    disk_state: Option<DiskState>,
     is_saved: bool,
+    // The text as it was when the file was last opened or saved, to show what has changed since
+    saved_text: String,
+    // The file could only be opened for reading, so it has to be saved somewhere else
+    is_read_only: bool,
+    // Locked for reviewing, so every edit is ignored
+    is_locked: bool,
+    trim_trailing_whitespace: bool,
+    word_boundaries: WordBoundaries,
+    // How many spaces a tab is replaced with
+    tab_width: usize,
+    wrap: bool,
+}
+
+// The text and where the cursor or selection is, for checking the editor after actions ran
+#[derive(Debug, Clone)]
+pub struct EditorSnapshot {
+    pub text: String,
+    // Whole lines of the text, so a soft-wrapped paragraph is a single line
+    pub lines: Vec<Line>,
+    // The line and the byte column in it, which counts headline markers like the text does
+    // Both are the same when there's a cursor rather than a selection
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+// What the file looked like on disk the last time we read or wrote it
+#[derive(Debug, Clone, PartialEq)]
+struct DiskState {
+    modified: Option<SystemTime>,
+    length: u64,
+}
+
+impl DiskState {
+    fn read(path: &Path) -> Option<DiskState> {
+        return fs::metadata(path).ok().map(|metadata| DiskState {
+            modified: metadata.modified().ok(),
+            length: metadata.len(),
+        });
+    }
 }
 
 impl Buffer {
@@ -21,39 +68,94 @@ impl Buffer {
         return Buffer {
             content: Content::empty(),
             file: None,
+            path: None,
+            disk_state: None,
             is_saved: true,
+            saved_text: String::new(),
+            is_read_only: false,
+            is_locked: false,
+            trim_trailing_whitespace: false,
+            word_boundaries: WordBoundaries::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            wrap: true,
             edit_location: EditLocation::Cursor(Cursor::new(0, 0, 0)),
         };
     }
 
-    pub fn from_path(path: PathBuf) -> Buffer {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(path)
-            .unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-
+    // A buffer without a file, seeded with the text
+    // Text that isn't empty counts as unsaved, so it isn't lost without asking
+    pub fn from_string(text: String) -> Buffer {
         return Buffer {
+            is_saved: text.is_empty(),
+            content: Content::new(text.into()),
+            ..Buffer::empty()
+        };
+    }
+
+    pub fn from_path(path: PathBuf) -> Result<Buffer, OpenError> {
+        return Buffer::open(path, false);
+    }
+
+    // Opens the file even if it isn't valid UTF-8, replacing the invalid bytes
+    pub fn from_path_lossy(path: PathBuf) -> Result<Buffer, OpenError> {
+        return Buffer::open(path, true);
+    }
+
+    fn open(path: PathBuf, lossy: bool) -> Result<Buffer, OpenError> {
+        let (mut file, is_read_only) = open_file(&path)?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+
+        let contents = match String::from_utf8(bytes) {
+            Ok(contents) => contents,
+            Err(error) if lossy => String::from_utf8_lossy(error.as_bytes()).into_owned(),
+            Err(_) => return Err(OpenError::InvalidUtf8),
+        };
+
+        return Ok(Buffer {
             file: Some(file),
+            disk_state: DiskState::read(&path),
+            path: Some(path),
+            saved_text: contents.clone(),
             content: Content::new(contents.into()),
             is_saved: true,
+            is_read_only,
+            is_locked: false,
+            trim_trailing_whitespace: false,
+            word_boundaries: WordBoundaries::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            wrap: true,
             edit_location: EditLocation::Cursor(Cursor::new(0, 0, 0)),
-        };
+        });
     }
 
     pub fn save(&mut self) -> Result<(), SaveError> {
+        if self.is_read_only {
+            return Err(SaveError::ReadOnly);
+        }
+
+        // Saving a locked document writes it as it is
+        if self.file.is_some() && !self.is_locked {
+            if self.trim_trailing_whitespace {
+                self.content.trim_trailing_whitespace();
+            }
+
+            self.content.ensure_single_trailing_newline();
+            self.clamp_edit_location();
+        }
+
         let content = self.content.to_string();
 
         match &mut self.file {
             Some(file) => {
-                file.set_len(0).unwrap(); // Truncate the file to 0 bytes
-                file.seek(std::io::SeekFrom::Start(0)).unwrap(); // Go to the beginning
+                file.set_len(0)?; // Truncate the file to 0 bytes
+                file.seek(std::io::SeekFrom::Start(0))?; // Go to the beginning
 
-                file.write(content.as_bytes()).unwrap();
+                file.write_all(content.as_bytes())?;
 
                 self.is_saved = true;
+                self.saved_text = content;
+                self.disk_state = self.path.as_deref().and_then(DiskState::read);
                 Ok(())
             }
             None => Err(SaveError::NoFileAssociated),
@@ -69,7 +171,7 @@ impl Buffer {
     }
 
     pub fn is_empty(&self) -> bool {
-        return self.content().text().to_string().is_empty();
+        return self.content.is_empty();
     }
 
     pub fn pristine(&self) -> bool {
@@ -84,22 +186,235 @@ impl Buffer {
         return self.file.is_some();
     }
 
-    pub fn set_file(&mut self, path: PathBuf) -> Result<(), SaveError> {
+    pub fn is_read_only(&self) -> bool {
+        return self.is_read_only;
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        return self.path.clone();
+    }
+
+    pub fn set_file(&mut self, path: PathBuf, extensions: &[String]) -> Result<(), SaveError> {
+        check_save_path(&path, extensions)?;
+
         // Open or create the file
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
             .truncate(true)
-            .open(path)?;
+            .open(path.clone())?;
 
         self.file = Some(file);
+        self.path = Some(path);
+        self.is_read_only = false;
 
         // Save the content to the new file
         self.save()?;
 
         Ok(())
     }
+
+    // Whether another program has touched the file since we last read or wrote it
+    pub fn has_changed_on_disk(&self) -> bool {
+        return match (&self.path, &self.disk_state) {
+            (Some(path), Some(disk_state)) => DiskState::read(path).as_ref() != Some(disk_state),
+            _ => false,
+        };
+    }
+
+    // Accept the file as it is on disk without reloading it
+    pub fn acknowledge_disk_changes(&mut self) {
+        self.disk_state = self.path.as_deref().and_then(DiskState::read);
+    }
+
+    pub fn reload(&mut self) -> Result<(), std::io::Error> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+
+        // Reopen the file since it might have been replaced rather than written to
+        let (mut file, is_read_only) = open_file(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        self.file = Some(file);
+        self.is_read_only = is_read_only;
+        self.saved_text = contents.clone();
+        self.content = Content::new(contents.into());
+        self.content.set_wrap(self.wrap);
+        self.disk_state = DiskState::read(&path);
+        self.is_saved = true;
+        self.replace_tabs();
+        self.clamp_edit_location();
+
+        Ok(())
+    }
+
+    // How the text differs from the file as it is on disk right now
+    // Without a file, or when it has been deleted, every line counts as added
+    pub fn diff_against_disk(&self) -> Vec<LineDiff> {
+        // Nothing touched the file since we last read or wrote it, so what we saved is still on disk
+        if self.disk_state.is_some() && !self.has_changed_on_disk() {
+            return self.diff_against_saved();
+        }
+
+        let on_disk = self
+            .path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default();
+
+        return diff_lines(&on_disk, &self.content.to_string());
+    }
+
+    // How the text differs from when the file was last opened or saved
+    // Unlike `diff_against_disk` this doesn't touch the file, so it's cheap enough to do on every edit
+    pub fn diff_against_saved(&self) -> Vec<LineDiff> {
+        return diff_lines(&self.saved_text, &self.content.to_string());
+    }
+
+    // Takes over the text of another buffer for the same file, like the one in the other pane
+    // The cursor stays where it was, moved back inside the text if it got shorter
+    // Returns whether anything changed
+    pub fn mirror(&mut self, content: Content, is_saved: bool) -> bool {
+        let text_changed = self.content.to_string() != content.to_string();
+
+        if !text_changed && self.is_saved == is_saved {
+            return false;
+        }
+
+        if text_changed {
+            self.content = content;
+            self.content.set_wrap(self.wrap);
+            self.clamp_edit_location();
+        }
+
+        self.is_saved = is_saved;
+
+        // The other buffer wrote the file, so it matches what's on disk now
+        if is_saved {
+            self.saved_text = self.content.to_string();
+            self.acknowledge_disk_changes();
+        }
+
+        return true;
+    }
+
+    // Returns whether the formatting changed anything
+    pub fn format(&mut self) -> bool {
+        if self.is_locked || !self.content.format() {
+            return false;
+        }
+
+        self.is_saved = false;
+        self.clamp_edit_location();
+
+        return true;
+    }
+
+    pub fn set_locked(&mut self, is_locked: bool) {
+        self.is_locked = is_locked;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        return self.is_locked;
+    }
+
+    pub fn set_trim_trailing_whitespace(&mut self, trim_trailing_whitespace: bool) {
+        self.trim_trailing_whitespace = trim_trailing_whitespace;
+    }
+
+    pub fn set_word_boundaries(&mut self, word_boundaries: WordBoundaries) {
+        self.word_boundaries = word_boundaries;
+    }
+
+    // The cursor or selection stays at the same place in the text, even though it moves on screen
+    pub fn set_wrap(&mut self, wrap: bool) {
+        if self.wrap == wrap {
+            return;
+        }
+
+        let original_offset = |buffer: &Buffer, position: EditorPosition| -> usize {
+            buffer
+                .content
+                .original_offset(buffer.position_to_offset(position))
+        };
+        let location = match self.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                let offset = original_offset(self, cursor.position);
+
+                offset..offset
+            }
+            EditLocation::Selection(selection) => {
+                original_offset(self, selection.start)..original_offset(self, selection.end)
+            }
+        };
+
+        self.wrap = wrap;
+        self.content.set_wrap(wrap);
+
+        let start = self.offset_to_position(self.content.wrapped_offset(location.start));
+        let end = self.offset_to_position(self.content.wrapped_offset(location.end));
+
+        self.edit_location = if location.start == location.end {
+            EditLocation::Cursor(Cursor::new(start.y, start.x, start.x))
+        } else {
+            EditLocation::Selection(Selection::new(start, end))
+        };
+    }
+
+    // Also replaces the tabs already in the text, like the ones in a file that was just opened
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+        self.replace_tabs();
+    }
+
+    // Wrapping and positioning assume every character is as wide, which tabs are not
+    pub fn expand_tabs(&self, text: &str) -> String {
+        return text.replace('\t', &" ".repeat(self.tab_width));
+    }
+
+    fn replace_tabs(&mut self) {
+        let text = self.content.to_string();
+
+        if !text.contains('\t') {
+            return;
+        }
+
+        self.content = Content::new(self.expand_tabs(&text).into());
+        self.content.set_wrap(self.wrap);
+        // Otherwise every line with a tab would show up as changed
+        self.saved_text = self.expand_tabs(&self.saved_text);
+        self.clamp_edit_location();
+    }
+
+    // Moves the cursor or selection back inside the content after it has changed underneath it
+    pub fn clamp_edit_location(&mut self) {
+        let lines = self.content.lines();
+        let clamp = |position: &EditorPosition| {
+            let y = std::cmp::min(position.y, lines.len().saturating_sub(1));
+            let x = lines
+                .get(y)
+                .map(|line| position.x.clamp(line.beginning(), line.end()))
+                .unwrap_or(0);
+
+            EditorPosition::new(y, x)
+        };
+
+        self.edit_location = match &self.edit_location {
+            EditLocation::Cursor(cursor) => {
+                let position = clamp(&cursor.position);
+
+                EditLocation::Cursor(Cursor::new(position.y, position.x, cursor.preferred_x))
+            }
+            EditLocation::Selection(selection) => EditLocation::Selection(Selection::new(
+                clamp(&selection.start),
+                clamp(&selection.end),
+            )),
+        };
+    }
 }
 
 impl Buffer {
@@ -124,20 +439,1307 @@ impl Buffer {
     }
 
     pub fn replace(&mut self, range: Range<usize>, replacement: String) {
+        if self.is_locked {
+            return;
+        }
+
         self.is_saved = false;
 
         return self.content.replace(range, replacement);
     }
 }
 
+impl Buffer {
+    pub fn left_position(&self, point: EditorPosition) -> EditorPosition {
+        let line = self.line(point.y);
+
+        if point.y == 0 && point.x == line.beginning() {
+            return point;
+        }
+
+        if point.x == line.beginning() {
+            let new_line_index = point.y - 1;
+            let line = self.line(new_line_index);
+
+            return EditorPosition::new(new_line_index, line.end());
+        }
+
+        let length = line.previous_character_length(point.x) as isize;
+
+        return EditorPosition::new(point.y, point.x - length);
+    }
+
+    pub fn right_position(&self, point: EditorPosition) -> EditorPosition {
+        let line_length = self.lines().len();
+        let line = self.line(point.y);
+
+        if point.y == line_length - 1 && point.x == line.end() {
+            return point;
+        }
+
+        if point.x == line.end() {
+            return EditorPosition::new(point.y + 1, 0);
+        }
+
+        let length = line.next_character_length(point.x) as isize;
+
+        return EditorPosition::new(point.y, point.x + length);
+    }
+
+    pub fn up_position(&self, point: EditorPosition, preferred_x: isize) -> EditorPosition {
+        if point.y == 0 {
+            let line = self.line(0);
+            return EditorPosition::new(0, line.beginning());
+        }
+
+        let previous_line = self.line(point.y - 1);
+
+        let x = previous_line.vertical_x(preferred_x);
+
+        return EditorPosition::new(point.y - 1, x);
+    }
+
+    pub fn down_position(&self, point: EditorPosition, preferred_x: isize) -> EditorPosition {
+        let line = self.line(point.y);
+
+        if point.y == self.lines().len() - 1 {
+            return EditorPosition::new(point.y, line.end());
+        }
+
+        let next_line = self.line(point.y + 1);
+
+        let x = next_line.vertical_x(preferred_x);
+
+        return EditorPosition::new(point.y + 1, x);
+    }
+
+    pub fn beginning_of_file_position(&self) -> EditorPosition {
+        let line = self.line(0);
+
+        return EditorPosition::new(0, line.beginning());
+    }
+
+    pub fn end_of_file_position(&self) -> EditorPosition {
+        let y = self.lines().len() - 1;
+        let line = self.line(y);
+
+        return EditorPosition::new(y, line.end());
+    }
+
+    pub fn beginning_of_line_position(&self, point: EditorPosition) -> EditorPosition {
+        let line = self.line(point.y);
+
+        return EditorPosition::new(point.y, line.beginning());
+    }
+
+    pub fn end_of_line_position(&self, point: EditorPosition) -> EditorPosition {
+        let line = self.line(point.y);
+
+        return EditorPosition::new(point.y, line.end());
+    }
+
+    // The word the position is in or right next to
+    // Words are separated by whitespace, like when moving by word
+    pub fn word_range(&self, point: EditorPosition) -> Option<Range<EditorPosition>> {
+        let line = self.line(point.y);
+        let text = line.text.as_str();
+        // Headline markers are never part of a word
+        let minimum = (-line.beginning()) as usize;
+        let index = std::cmp::min(
+            text.len(),
+            std::cmp::max(0, point.x - line.beginning()) as usize,
+        );
+        let mut start = std::cmp::max(index, minimum);
+        let mut end = start;
+
+        while let Some(character) = text[minimum..start].chars().next_back() {
+            if character.is_whitespace() {
+                break;
+            }
+
+            start -= character.len_utf8();
+        }
+
+        while let Some(character) = text[end..].chars().next() {
+            if character.is_whitespace() {
+                break;
+            }
+
+            end += character.len_utf8();
+        }
+
+        if start == end {
+            return None;
+        }
+
+        return Some(
+            EditorPosition::new(point.y, line.beginning() + start as isize)
+                ..EditorPosition::new(point.y, line.beginning() + end as isize),
+        );
+    }
+
+    pub fn beginning_of_word_position(&self, point: EditorPosition) -> EditorPosition {
+        let line = self.line(point.y);
+        let line_offset = (point.x - line.beginning()) as usize;
+
+        // Handle edge case: at beginning of file
+        if point.y == 0 && point.x <= line.beginning() {
+            return self.beginning_of_file_position();
+        }
+
+        // First attempt: find previous word boundary in current line
+        if point.x > line.beginning() && line_offset <= line.text.len() {
+            let wrapped_text = WrappedText::new(line.text.clone());
+            let word_boundary =
+                wrapped_text.previous_word_boundary(line_offset, self.word_boundaries);
+            let new_x = line.beginning() + (word_boundary as isize);
+
+            // Use this position if it's actually before the current position
+            if new_x < point.x {
+                return EditorPosition::new(point.y, new_x);
+            }
+        }
+
+        // If we're here, we need to look at previous line
+
+        // Handle edge case: already at first line
+        if point.y == 0 {
+            return self.beginning_of_file_position();
+        }
+
+        // Get previous line
+        let previous_line = self.line(point.y - 1);
+
+        // Handle edge case: previous line is empty
+        if previous_line.text.trim().is_empty() {
+            return EditorPosition::new(point.y - 1, previous_line.beginning());
+        }
+
+        // Find last word in previous line
+        let wrapped_text = WrappedText::new(previous_line.text.clone());
+        let last_valid_offset = previous_line.text.len();
+        let word_boundary =
+            wrapped_text.previous_word_boundary(last_valid_offset, self.word_boundaries);
+        let new_x = previous_line.beginning() + (word_boundary as isize);
+
+        return EditorPosition::new(point.y - 1, new_x);
+    }
+
+    pub fn end_of_word_position(&self, point: EditorPosition) -> EditorPosition {
+        let line = self.line(point.y);
+        let line_offset = (point.x - line.beginning()) as usize;
+
+        // First attempt: find next word boundary in current line
+        if line_offset < line.text.len() {
+            let wrapped_text = WrappedText::new(line.text.clone());
+
+            if let Some(word_boundary) =
+                wrapped_text.next_word_boundary(line_offset, self.word_boundaries)
+            {
+                let new_x = line.beginning() + (word_boundary as isize);
+
+                // Use this position if it doesn't exceed the end of the line
+                if new_x <= line.end() {
+                    return EditorPosition::new(point.y, new_x);
+                }
+            }
+        }
+
+        // If we're here, we need to look at the next line
+
+        // Handle edge case: already at last line
+        if point.y >= self.lines().len() - 1 {
+            return EditorPosition::new(point.y, line.end());
+        }
+
+        // Get next line
+        let next_line = self.line(point.y + 1);
+
+        // Handle edge case: next line is empty
+        if next_line.text.trim().is_empty() {
+            return EditorPosition::new(point.y + 1, next_line.beginning());
+        }
+
+        // Find first non-whitespace character in next line, in bytes like the columns
+        let start_offset = next_line.text.len() - next_line.text.trim_start().len();
+
+        // Go to the first word in the next line
+        return EditorPosition::new(point.y + 1, next_line.beginning() + start_offset as isize);
+    }
+
+    // The markers stay with the first part and the rest becomes a paragraph of its own
+    // In front of the text the headline moves down as a whole instead of being split
+    // Returns where the cursor goes
+    pub fn split_headline(&mut self, range: Range<EditorPosition>) -> EditorPosition {
+        if range.start == range.end && range.start.x <= 0 {
+            let line = self.line(range.start.y);
+            let start =
+                self.position_to_offset(EditorPosition::new(range.start.y, line.beginning()));
+
+            self.replace(start..start, "\n".into());
+
+            return EditorPosition::new(range.start.y + 1, 0);
+        }
+
+        // The space between the words would otherwise start the paragraph
+        let line_end = self.end_of_line_position(range.end.clone());
+        let after = self.read_range(
+            self.position_to_offset(range.end.clone())..self.position_to_offset(line_end),
+        );
+        let whitespace = after.len() - after.trim_start_matches([' ', '\t']).len();
+        let end = self.position_after(range.end, whitespace);
+        let start_offset = self.position_to_offset(range.start.clone());
+        let end_offset = self.position_to_offset(end);
+
+        self.replace(start_offset..end_offset, "\n".into());
+
+        return self.position_after(range.start, 1);
+    }
+
+    pub fn characters_around(&self, position: &EditorPosition) -> (Option<char>, Option<char>) {
+        let line = self.line(position.y);
+        let index = (position.x - line.beginning()) as usize;
+
+        let previous = line.text.get(..index).and_then(|text| text.chars().last());
+        let next = line.text.get(index..).and_then(|text| text.chars().next());
+
+        return (previous, next);
+    }
+
+    // The position `length` bytes of original text after the given position
+    // Accounts for any soft-wraps in between
+    pub fn position_after(&self, position: EditorPosition, length: usize) -> EditorPosition {
+        return self.original_position(self.original_offset(position) + length);
+    }
+
+    // Offsets in the original text stay put when an edit changes the wrapping around them
+    // These borrow the content rather than cloning it, which matters for large documents
+    pub fn original_offset(&self, position: EditorPosition) -> usize {
+        return self
+            .content
+            .original_offset(self.content.position_to_offset(position));
+    }
+
+    pub fn original_position(&self, offset: usize) -> EditorPosition {
+        return self
+            .content
+            .offset_to_position(self.content.wrapped_offset(offset));
+    }
+
+    pub fn snapshot(&self) -> EditorSnapshot {
+        let text = self.content.to_string();
+        let wrap_points = self.content.wrap_points();
+        let mut offset = 0;
+        let mut first_fragments = vec![];
+
+        // The soft-wrapped rest of a line has the same type as its first fragment
+        for line in self.content.lines() {
+            let length = line.length();
+
+            if wrap_points.binary_search(&offset).is_err() {
+                first_fragments.push(line);
+            }
+
+            offset += length + 1; // Newline
+        }
+
+        // The virtual line after the last newline isn't part of the text, so zipping leaves it out
+        let lines = text
+            .split_terminator('\n')
+            .zip(first_fragments)
+            .map(|(text, line)| Line {
+                text: text.strip_suffix('\r').unwrap_or(text).to_string(),
+                max_width: None,
+                ..line
+            })
+            .collect();
+        let line_and_column = |position: EditorPosition| {
+            let before = &text[..self.original_offset(position)];
+            let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+            return (before.matches('\n').count(), before.len() - line_start);
+        };
+        let (start, end) = match self.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                let position = line_and_column(cursor.position);
+
+                (position, position)
+            }
+            EditLocation::Selection(selection) => (
+                line_and_column(selection.start),
+                line_and_column(selection.end),
+            ),
+        };
+
+        return EditorSnapshot {
+            text,
+            lines,
+            start,
+            end,
+        };
+    }
+
+    // Where the bracket or marker next to the position is matched
+    // The character after the position is tried first, then the one before it
+    pub fn matching_bracket_positions(
+        &self,
+        position: EditorPosition,
+    ) -> Option<(EditorPosition, EditorPosition)> {
+        let content = self.content();
+        let text = content.to_string();
+        let offset = content.original_offset(self.position_to_offset(position));
+
+        let (bracket, matching) = [Some(offset), offset.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .find_map(|offset| Some((offset, matching_bracket(&text, offset)?)))?;
+
+        let to_position = |offset: usize| self.offset_to_position(content.wrapped_offset(offset));
+
+        return Some((to_position(bracket), to_position(matching)));
+    }
+
+    // Every place the text occurs in the document, in order
+    pub fn find_all(&self, query: &str) -> Vec<Range<EditorPosition>> {
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let content = self.content();
+        let text = content.to_string();
+        let to_position = |offset: usize| self.offset_to_position(content.wrapped_offset(offset));
+
+        return text
+            .match_indices(query)
+            .map(|(offset, _)| to_position(offset)..to_position(offset + query.len()))
+            .collect();
+    }
+
+    // The sentence around the given position, which can span several soft-wrapped lines
+    pub fn sentence_range(&self, position: EditorPosition) -> Range<EditorPosition> {
+        let content = self.content();
+        let text = content.to_string();
+        let offset = content.original_offset(self.position_to_offset(position));
+        let range = sentence_range(&text, offset);
+
+        let to_position = |offset: usize| self.offset_to_position(content.wrapped_offset(offset));
+
+        return to_position(range.start)..to_position(range.end);
+    }
+
+    pub fn starts_logical_line(&self, y: usize, wrap_points: &Vec<usize>) -> bool {
+        let line = self.line(y);
+        let offset = self.position_to_offset(EditorPosition::new(y, line.beginning()));
+
+        return !wrap_points.contains(&offset);
+    }
+
+    // The lines of the paragraph around the given line
+    // Paragraphs are separated by blank lines
+    pub fn paragraph_lines(&self, y: usize) -> Range<usize> {
+        let lines = self.lines();
+        let is_blank = |y: usize| lines[y].text.trim().is_empty();
+
+        if y >= lines.len() || is_blank(y) {
+            return y..y + 1;
+        }
+
+        let mut first = y;
+        let mut last = y;
+
+        while first > 0 && !is_blank(first - 1) {
+            first -= 1;
+        }
+
+        while last + 1 < lines.len() && !is_blank(last + 1) {
+            last += 1;
+        }
+
+        return first..last + 1;
+    }
+
+    // The first and last visual line of the logical line that contains the given line
+    pub fn logical_line_bounds(&self, y: usize) -> (usize, usize) {
+        let wrap_points = self.content().wrap_points();
+        let line_count = self.lines().len();
+        let mut first = y;
+        let mut last = y;
+
+        while first > 0 && !self.starts_logical_line(first, &wrap_points) {
+            first -= 1;
+        }
+
+        while last + 1 < line_count && !self.starts_logical_line(last + 1, &wrap_points) {
+            last += 1;
+        }
+
+        return (first, last);
+    }
+}
+
+const SENTENCE_ENDINGS: [u8; 3] = [b'.', b'?', b'!'];
+
+// Sentences end at a `.`, `?` or `!` followed by whitespace, or at the end of a line
+// A cursor right after the punctuation still belongs to the sentence it ended
+// while one in the whitespace after it belongs to the next sentence
+fn sentence_range(text: &str, offset: usize) -> Range<usize> {
+    let bytes = text.as_bytes();
+    let offset = std::cmp::min(offset, bytes.len());
+    // Whether a sentence ends right before the index
+    let ends_before = |index: usize| {
+        index == bytes.len()
+            || bytes[index] == b'\n'
+            || (index > 0
+                && SENTENCE_ENDINGS.contains(&bytes[index - 1])
+                && bytes[index].is_ascii_whitespace())
+    };
+
+    let mut start = (0..offset)
+        .rev()
+        .find(|index| ends_before(*index))
+        .unwrap_or(0);
+
+    // Skipping the whitespace after the previous sentence moves past the cursor
+    // when it's between two sentences, so the next one is the current one
+    // Line breaks after the cursor are kept, so empty lines don't belong to the next paragraph
+    while start < bytes.len()
+        && bytes[start].is_ascii_whitespace()
+        && !(bytes[start] == b'\n' && start >= offset)
+    {
+        start += 1;
+    }
+
+    let end = (std::cmp::max(start, offset)..=bytes.len())
+        .find(|index| ends_before(*index))
+        .unwrap_or(bytes.len());
+
+    return start..end;
+}
+
+const BRACKETS: [(u8, u8); 3] = [(b'(', b')'), (b'[', b']'), (b'{', b'}')];
+// Opened and closed by the same character, so they're only matched within a line
+const MARKERS: [u8; 3] = [b'`', b'*', b'_'];
+
+// The offset of the character that matches the bracket or marker at the offset
+fn matching_bracket(text: &str, offset: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let character = *bytes.get(offset)?;
+
+    if MARKERS.contains(&character) {
+        let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = text[offset..]
+            .find('\n')
+            .map_or(text.len(), |index| offset + index);
+        let markers_before = bytes[line_start..offset]
+            .iter()
+            .filter(|byte| **byte == character)
+            .count();
+
+        // An even number of markers before it means this one opens a pair
+        return if markers_before % 2 == 0 {
+            bytes[offset + 1..line_end]
+                .iter()
+                .position(|byte| *byte == character)
+                .map(|index| offset + 1 + index)
+        } else {
+            bytes[line_start..offset]
+                .iter()
+                .rposition(|byte| *byte == character)
+                .map(|index| line_start + index)
+        };
+    }
+
+    let mut depth = 0;
+
+    if let Some((open, close)) = BRACKETS.iter().find(|(open, _)| *open == character) {
+        for (index, byte) in bytes.iter().enumerate().skip(offset) {
+            if byte == open {
+                depth += 1;
+            } else if byte == close {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+        }
+    }
+
+    if let Some((open, close)) = BRACKETS.iter().find(|(_, close)| *close == character) {
+        for index in (0..=offset).rev() {
+            if bytes[index] == *close {
+                depth += 1;
+            } else if bytes[index] == *open {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+        }
+    }
+
+    return None;
+}
+
+// Opens the file for reading and writing
+// Falls back to only reading when writing isn't allowed, which is returned as well
+fn open_file(path: &Path) -> std::io::Result<(File, bool)> {
+    return match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => Ok((file, false)),
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => {
+            let file = OpenOptions::new().read(true).open(path)?;
+
+            Ok((file, true))
+        }
+        Err(error) => Err(error),
+    };
+}
+
 #[derive(Debug)]
 pub enum SaveError {
     NoFileAssociated,
+    ReadOnly,
+    // Only files with one of the allowed extensions can be opened again
+    InvalidExtension,
+    PermissionDenied,
+    // The folder to save in doesn't exist (anymore)
+    NotFound,
     IoError(std::io::Error),
 }
 
 impl From<std::io::Error> for SaveError {
     fn from(error: std::io::Error) -> Self {
-        SaveError::IoError(error)
+        match error.kind() {
+            ErrorKind::PermissionDenied => SaveError::PermissionDenied,
+            ErrorKind::NotFound => SaveError::NotFound,
+            _ => SaveError::IoError(error),
+        }
+    }
+}
+
+// Checked before the file is created, so nothing is written to a path that can't be used
+pub fn check_save_path(path: &Path, extensions: &[String]) -> Result<(), SaveError> {
+    if !has_allowed_extension(path, extensions) {
+        return Err(SaveError::InvalidExtension);
+    }
+
+    Ok(())
+}
+
+// The extensions are written without the dot and an empty one allows files without an extension
+pub fn has_allowed_extension(path: &Path, extensions: &[String]) -> bool {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    return extensions
+        .iter()
+        .any(|allowed| allowed.to_lowercase() == extension);
+}
+
+// Like ".md, .markdown or .txt" for telling which files can be opened
+pub fn describe_extensions(extensions: &[String]) -> String {
+    let mut names: Vec<String> = extensions
+        .iter()
+        .map(|extension| match extension.as_str() {
+            "" => "files without an extension".to_string(),
+            extension => format!(".{}", extension),
+        })
+        .collect();
+    let Some(last) = names.pop() else {
+        return String::new();
+    };
+
+    if names.is_empty() {
+        return last;
+    }
+
+    return format!("{} or {}", names.join(", "), last);
+}
+
+#[derive(Debug)]
+pub enum OpenError {
+    InvalidUtf8,
+    IoError(std::io::Error),
+}
+
+impl From<std::io::Error> for OpenError {
+    fn from(error: std::io::Error) -> Self {
+        OpenError::IoError(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::LineType;
+    use crate::diff;
+
+    #[test]
+    fn typing_multi_byte_characters_at_the_wrap_width() {
+        let mut buffer = Buffer::from_string(format!("{}\n", "a".repeat(59)));
+        let end = buffer.end_of_line_position(EditorPosition::new(0, 0));
+        let offset = buffer.position_to_offset(end.clone());
+
+        buffer.replace(offset..offset, "——".into());
+
+        let position = buffer.position_after(end, "——".len());
+
+        assert_eq!(
+            buffer.content().to_string(),
+            format!("{}——\n", "a".repeat(59))
+        );
+        assert_eq!(buffer.line(0).text, format!("{}—", "a".repeat(59)));
+        assert_eq!(position, EditorPosition::new(1, "—".len() as isize));
+    }
+
+    fn fixture() -> Buffer {
+        return Buffer::from_string("Hello brave world\nsecond line here\n\nlast\n".into());
+    }
+
+    #[test]
+    fn moving_by_word_through_several_lines() {
+        let buffer = fixture();
+        let mut position = EditorPosition::new(0, 0);
+        let mut ends = vec![];
+
+        for _ in 0..9 {
+            position = buffer.end_of_word_position(position);
+            ends.push((position.y, position.x));
+        }
+
+        assert_eq!(
+            ends,
+            vec![
+                (0, 5),
+                (0, 11),
+                (0, 17),
+                (1, 0),
+                (1, 6),
+                (1, 11),
+                (1, 16),
+                (2, 0),
+                (3, 0)
+            ]
+        );
+
+        let mut position = EditorPosition::new(3, 4);
+        let mut beginnings = vec![];
+
+        for _ in 0..9 {
+            position = buffer.beginning_of_word_position(position);
+            beginnings.push((position.y, position.x));
+        }
+
+        assert_eq!(
+            beginnings,
+            vec![
+                (3, 0),
+                (2, 0),
+                (1, 12),
+                (1, 7),
+                (1, 0),
+                (0, 12),
+                (0, 6),
+                (0, 0),
+                (0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_by_character_wraps_to_the_neighbouring_lines() {
+        let buffer = fixture();
+
+        assert_eq!(
+            buffer.left_position(EditorPosition::new(1, 0)),
+            EditorPosition::new(0, 17)
+        );
+        assert_eq!(
+            buffer.right_position(EditorPosition::new(0, 17)),
+            EditorPosition::new(1, 0)
+        );
+        assert_eq!(
+            buffer.left_position(EditorPosition::new(0, 0)),
+            EditorPosition::new(0, 0)
+        );
+        assert_eq!(
+            buffer.right_position(buffer.end_of_file_position()),
+            buffer.end_of_file_position()
+        );
+    }
+
+    #[test]
+    fn moving_vertically_stops_at_the_ends_of_the_file() {
+        let buffer = fixture();
+
+        assert_eq!(
+            buffer.up_position(EditorPosition::new(0, 5), 5),
+            EditorPosition::new(0, 0)
+        );
+        assert_eq!(
+            buffer.down_position(EditorPosition::new(0, 5), 5),
+            EditorPosition::new(1, 5)
+        );
+        // The empty line can't hold the column, but the next line gets it back
+        assert_eq!(
+            buffer.down_position(EditorPosition::new(1, 5), 5),
+            EditorPosition::new(2, 0)
+        );
+        assert_eq!(
+            buffer.down_position(EditorPosition::new(2, 0), 5),
+            EditorPosition::new(3, 4)
+        );
+        assert_eq!(
+            buffer.down_position(buffer.end_of_file_position(), 5),
+            buffer.end_of_file_position()
+        );
+    }
+
+    #[test]
+    fn moving_over_a_headline_keeps_the_column() {
+        let buffer = Buffer::from_string(
+            "Some paragraph text here\n### Heading\nMore paragraph text\n".into(),
+        );
+
+        // The column counts from the start of the headline text, not from its markers
+        let up = buffer.up_position(EditorPosition::new(2, 10), 10);
+        let down = buffer.down_position(up.clone(), 10);
+
+        assert_eq!(up, EditorPosition::new(1, 7));
+        assert_eq!(down, EditorPosition::new(2, 10));
+        assert_eq!(
+            buffer.down_position(EditorPosition::new(0, 3), 3),
+            EditorPosition::new(1, 3)
+        );
+        assert_eq!(
+            buffer.up_position(EditorPosition::new(1, 3), 3),
+            EditorPosition::new(0, 3)
+        );
+        // Coming from the markers of a headline, the cursor lands before the text
+        assert_eq!(
+            buffer.down_position(EditorPosition::new(0, 0), -4),
+            EditorPosition::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn moving_through_a_wrapped_paragraph_keeps_the_column() {
+        let buffer = Buffer::from_string(
+            "The quick brown fox jumps over the lazy dog and keeps running far away. The quick brown fox jumps over the lazy dog again. Then a short bit.\n"
+                .into(),
+        );
+
+        assert_eq!(buffer.lines().len(), 4);
+
+        let second = buffer.down_position(EditorPosition::new(0, 40), 40);
+        let third = buffer.down_position(second.clone(), 40);
+        let back = buffer.up_position(third.clone(), 40);
+
+        assert_eq!(second, EditorPosition::new(1, 40));
+        // The last fragment is shorter, so the cursor stops at its end
+        assert_eq!(third, EditorPosition::new(2, 28));
+        assert_eq!(back, EditorPosition::new(1, 40));
+        assert_eq!(buffer.up_position(back, 40), EditorPosition::new(0, 40));
+    }
+
+    #[test]
+    fn a_collapsed_selection_keeps_aiming_for_its_column() {
+        let buffer = Buffer::from_string("A long first line\nab\nAnother long line\n".into());
+
+        // Extending a selection down from column 15 clamps it to the short line
+        let start = EditorPosition::new(0, 15);
+        let mut selection = Selection::new(start.clone(), buffer.down_position(start, 15));
+        selection.preferred_x = 15;
+
+        assert_eq!(selection.end, EditorPosition::new(1, 2));
+
+        // Escape leaves the cursor at the end that moved, which still aims for column 15
+        let preferred_x = selection.preferred_x_at(&selection.end);
+
+        assert_eq!(preferred_x, 15);
+        assert_eq!(
+            buffer.down_position(selection.end.clone(), preferred_x),
+            EditorPosition::new(2, 15)
+        );
+        // Anywhere but the end that moved, the column is just where the position is
+        assert_eq!(selection.preferred_x_at(&EditorPosition::new(0, 3)), 3);
+    }
+
+    #[test]
+    fn typing_over_a_selection_across_a_soft_wrap() {
+        let paragraph =
+            "The quick brown fox jumps over the lazy dog and keeps running far away from here.";
+        let mut buffer = Buffer::from_string(format!("{}\n", paragraph));
+        let start = EditorPosition::new(0, 44);
+        let end = EditorPosition::new(1, 8);
+
+        assert_eq!(buffer.lines().len(), 3);
+
+        let start_offset = buffer.position_to_offset(start.clone());
+        let end_offset = buffer.position_to_offset(end);
+        let original_start = buffer.original_offset(start);
+
+        buffer.replace(start_offset..end_offset, "x".into());
+
+        let position = buffer.original_position(original_start + 1);
+
+        assert_eq!(
+            buffer.content().to_string(),
+            "The quick brown fox jumps over the lazy dog xfar away from here.\n"
+        );
+        assert_eq!(buffer.characters_around(&position), (Some('x'), Some('f')));
+    }
+
+    #[test]
+    fn replacing_everything_up_to_the_virtual_last_line() {
+        let mut buffer = Buffer::from_string("# Title\n\nSome text\n".into());
+        let start = buffer.position_to_offset(buffer.beginning_of_file_position());
+        let end = buffer.position_to_offset(buffer.end_of_file_position());
+
+        assert_eq!(end, buffer.content().to_string().len());
+
+        // Select All, Backspace and then typing
+        buffer.replace(start..end, String::new());
+        buffer.replace(0..0, "typed".into());
+
+        assert_eq!(buffer.content().to_string(), "typed");
+        assert_eq!(
+            buffer.position_to_offset(buffer.end_of_file_position()),
+            "typed".len()
+        );
+    }
+
+    #[test]
+    fn splitting_a_headline_between_words() {
+        let mut buffer = Buffer::from_string("## Hello World\n".into());
+        let position = EditorPosition::new(0, 5);
+
+        let cursor = buffer.split_headline(position.clone()..position);
+
+        assert_eq!(buffer.content().to_string(), "## Hello\nWorld\n");
+        assert_eq!(cursor, EditorPosition::new(1, 0));
+        assert!(matches!(buffer.line(0).kind, LineType::HeadlineStart(2)));
+        assert!(matches!(buffer.line(1).kind, LineType::Normal));
+    }
+
+    #[test]
+    fn splitting_a_headline_in_front_of_its_text_moves_it_down() {
+        let mut buffer = Buffer::from_string("## Hello World\n".into());
+        let position = EditorPosition::new(0, 0);
+
+        let cursor = buffer.split_headline(position.clone()..position);
+
+        assert_eq!(buffer.content().to_string(), "\n## Hello World\n");
+        assert_eq!(cursor, EditorPosition::new(1, 0));
+        assert!(matches!(buffer.line(1).kind, LineType::HeadlineStart(2)));
+    }
+
+    #[test]
+    fn moving_in_an_empty_buffer_stays_at_the_start() {
+        let origin = EditorPosition::new(0, 0);
+
+        for word_boundaries in [WordBoundaries::Whitespace, WordBoundaries::Punctuation] {
+            let mut buffer = Buffer::empty();
+            buffer.set_word_boundaries(word_boundaries);
+
+            assert_eq!(buffer.left_position(origin.clone()), origin);
+            assert_eq!(buffer.right_position(origin.clone()), origin);
+            assert_eq!(buffer.up_position(origin.clone(), 0), origin);
+            assert_eq!(buffer.down_position(origin.clone(), 0), origin);
+            assert_eq!(buffer.beginning_of_word_position(origin.clone()), origin);
+            assert_eq!(buffer.end_of_word_position(origin.clone()), origin);
+            assert_eq!(buffer.beginning_of_line_position(origin.clone()), origin);
+            assert_eq!(buffer.end_of_line_position(origin.clone()), origin);
+            assert_eq!(buffer.beginning_of_file_position(), origin);
+            assert_eq!(buffer.end_of_file_position(), origin);
+        }
+    }
+
+    #[test]
+    fn moving_through_headline_markers() {
+        let buffer = Buffer::from_string("Intro\n### Title\n".into());
+        let mut position = EditorPosition::new(1, 0);
+        let mut columns = vec![];
+
+        for _ in 0..5 {
+            position = buffer.left_position(position);
+            columns.push((position.y, position.x));
+        }
+
+        assert_eq!(columns, vec![(1, -1), (1, -2), (1, -3), (1, -4), (0, 5)]);
+
+        let mut position = EditorPosition::new(1, -4);
+        let mut columns = vec![];
+
+        for _ in 0..5 {
+            position = buffer.right_position(position);
+            columns.push((position.y, position.x));
+        }
+
+        assert_eq!(columns, vec![(1, -3), (1, -2), (1, -1), (1, 0), (1, 1)]);
+        // Moving right from the line above lands at the start of the text
+        assert_eq!(
+            buffer.right_position(EditorPosition::new(0, 5)),
+            EditorPosition::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn offsets_inside_headline_markers() {
+        let mut buffer = Buffer::from_string("Intro\n### Title\n".into());
+
+        for offset in 6..=15 {
+            let position = buffer.offset_to_position(offset);
+
+            assert_eq!(position.x, offset as isize - 10);
+            assert_eq!(buffer.position_to_offset(position), offset);
+        }
+
+        // Deleting one of the markers, like Backspace does with the cursor at -2
+        let end = buffer.original_offset(EditorPosition::new(1, -2));
+        buffer.replace(end - 1..end, String::new());
+
+        assert_eq!(buffer.content().to_string(), "Intro\n## Title\n");
+        assert_eq!(
+            buffer.original_position(end - 1),
+            EditorPosition::new(1, -2)
+        );
+    }
+
+    #[test]
+    fn the_cursor_goes_after_a_large_paste() {
+        let mut buffer = Buffer::from_string("Before the paste. After it.\n".into());
+        let pasted = "A pasted sentence — with “quotes” that wraps somewhere. ".repeat(20_000);
+        let start = EditorPosition::new(0, 18);
+        let offset = buffer.position_to_offset(start.clone());
+        let original_start = buffer.original_offset(start.clone());
+
+        buffer.replace(offset..offset, pasted.clone());
+
+        let position = buffer.position_after(start, pasted.len());
+
+        assert!(pasted.len() > 1_000_000);
+        assert_eq!(
+            buffer.original_offset(position.clone()),
+            original_start + pasted.len()
+        );
+        assert_eq!(buffer.characters_around(&position), (Some(' '), Some('A')));
+        assert_eq!(
+            buffer.read_range(
+                buffer.position_to_offset(position)
+                    ..buffer.position_to_offset(buffer.end_of_file_position())
+            ),
+            "After it.\n"
+        );
+    }
+
+    // Types at the position like the editor does, leaving the cursor after the text
+    fn type_text(buffer: &mut Buffer, position: EditorPosition, text: &str) -> EditorPosition {
+        let offset = buffer.position_to_offset(position.clone());
+
+        buffer.replace(offset..offset, text.into());
+
+        let position = buffer.position_after(position, text.len());
+
+        buffer.set_location(EditLocation::Cursor(Cursor::new(
+            position.y, position.x, position.x,
+        )));
+
+        return position;
+    }
+
+    #[test]
+    fn the_snapshot_after_typing_a_wrapped_paragraph() {
+        let mut buffer = Buffer::from_string("## Title\n\n".into());
+        let paragraph = "A paragraph that is long enough to be wrapped onto a second line. ";
+        let position = type_text(&mut buffer, EditorPosition::new(1, 0), paragraph);
+
+        type_text(&mut buffer, position, "Done");
+
+        let snapshot = buffer.snapshot();
+
+        assert_eq!(snapshot.text, format!("## Title\n{}Done\n", paragraph));
+        assert_eq!(buffer.lines().len(), 4);
+        assert_eq!(snapshot.lines.len(), 2);
+        assert_eq!(snapshot.lines[0].text, "## Title");
+        assert!(matches!(snapshot.lines[0].kind, LineType::HeadlineStart(2)));
+        assert_eq!(snapshot.lines[1].text, format!("{}Done", paragraph));
+        assert!(matches!(snapshot.lines[1].kind, LineType::Normal));
+        assert_eq!(snapshot.start, (1, paragraph.len() + 4));
+        assert_eq!(snapshot.end, snapshot.start);
+    }
+
+    #[test]
+    fn the_snapshot_after_moving_and_selecting() {
+        let mut buffer = Buffer::from_string(
+            "# Notes\nA paragraph that is long enough to be wrapped onto a second line.\n- [ ] last\n"
+                .into(),
+        );
+        let start = EditorPosition::new(0, 0);
+        let wrapped = buffer.down_position(buffer.down_position(start.clone(), 0), 0);
+        let end = buffer.end_of_word_position(buffer.down_position(wrapped.clone(), 0));
+
+        buffer.set_location(EditLocation::Selection(Selection::new(
+            start,
+            wrapped.clone(),
+        )));
+
+        let snapshot = buffer.snapshot();
+        let first_fragment = buffer.line(1).text.len();
+
+        assert_eq!(wrapped, EditorPosition::new(2, 0));
+        assert_eq!(snapshot.start, (0, 2));
+        assert_eq!(snapshot.end, (1, first_fragment));
+        assert!(matches!(snapshot.lines[2].task, Some(task) if !task.is_done));
+
+        buffer.set_location(EditLocation::Cursor(Cursor::new(end.y, end.x, end.x)));
+
+        let snapshot = buffer.snapshot();
+
+        assert_eq!(end, EditorPosition::new(3, 1));
+        assert_eq!(snapshot.start, (2, 1));
+        assert_eq!(snapshot.end, (2, 1));
+    }
+
+    #[test]
+    fn the_word_at_a_position() {
+        let buffer = fixture();
+
+        assert_eq!(
+            buffer.word_range(EditorPosition::new(0, 8)),
+            Some(EditorPosition::new(0, 6)..EditorPosition::new(0, 11))
+        );
+        assert_eq!(
+            buffer.word_range(EditorPosition::new(0, 11)),
+            Some(EditorPosition::new(0, 6)..EditorPosition::new(0, 11))
+        );
+        assert_eq!(buffer.word_range(EditorPosition::new(2, 0)), None);
+    }
+
+    // A buffer for a new file in the temporary folder, removed again when the test is done
+    fn temporary_buffer(name: &str, text: &str) -> (Buffer, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("wordsmith-{}-{}.md", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+
+        return (Buffer::from_path(path.clone()).unwrap(), path);
+    }
+
+    #[test]
+    fn saving_trims_trailing_whitespace() {
+        let (mut buffer, path) = temporary_buffer("trim", "one  \ntwo\t\n \t \nthree\n   \n");
+        buffer.set_trim_trailing_whitespace(true);
+        buffer.set_location(EditLocation::Cursor(Cursor::new(0, 5, 5)));
+
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n\nthree\n");
+        assert_eq!(buffer.content().to_string(), "one\ntwo\n\nthree\n");
+        assert!(matches!(
+            buffer.edit_location(),
+            EditLocation::Cursor(cursor) if cursor.position == EditorPosition::new(0, 3)
+        ));
+        assert!(buffer.pristine());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saving_keeps_whitespace_unless_asked_to_trim_it() {
+        let (mut buffer, path) = temporary_buffer("keep", "one  \ntwo\t\n");
+
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one  \ntwo\t\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_locked_buffer_ignores_edits() {
+        let text = "## Headline\nSome text  \n";
+        let (mut buffer, path) = temporary_buffer("locked", text);
+        let start = buffer.position_to_offset(EditorPosition::new(1, 0));
+        let end = buffer.position_to_offset(EditorPosition::new(1, 4));
+
+        buffer.set_locked(true);
+        buffer.set_trim_trailing_whitespace(true);
+
+        // Typing, backspace, enter, cut and paste
+        buffer.replace(end..end, "a".into());
+        buffer.replace(end - 1..end, String::new());
+        buffer.replace(end..end, "\n".into());
+        buffer.split_headline(EditorPosition::new(0, 3)..EditorPosition::new(0, 3));
+        buffer.replace(start..end, String::new());
+        buffer.replace(start..start, "pasted".into());
+
+        assert!(!buffer.format());
+        assert_eq!(buffer.content().to_string(), text);
+        assert!(buffer.pristine());
+
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+
+        buffer.set_locked(false);
+        buffer.replace(start..start, "pasted ".into());
+
+        assert_eq!(
+            buffer.content().to_string(),
+            "## Headline\npasted Some text  \n"
+        );
+        assert!(!buffer.pristine());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tabs_are_replaced_with_spaces_when_loading() {
+        let (mut buffer, path) = temporary_buffer("tabs", "\tindented\n- item\twith tab\n");
+
+        buffer.set_tab_width(2);
+
+        assert_eq!(
+            buffer.content().to_string(),
+            "  indented\n- item  with tab\n"
+        );
+        assert_eq!(
+            buffer.word_range(EditorPosition::new(0, 2)),
+            Some(EditorPosition::new(0, 2)..EditorPosition::new(0, 10))
+        );
+        assert_eq!(buffer.position_to_offset(EditorPosition::new(1, 8)), 19);
+        assert_eq!(buffer.expand_tabs("a\tb"), "a  b");
+        // Replacing the tabs isn't an edit of its own
+        assert!(buffer.pristine());
+        assert!(diff::changed_lines(&buffer.diff_against_saved()).is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saving_twice_ends_in_a_single_newline() {
+        let (mut buffer, path) = temporary_buffer("newline", "text\n\n\n");
+
+        buffer.save().unwrap();
+        let length = fs::metadata(&path).unwrap().len();
+        buffer.save().unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().len(), length);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "text\n");
+        assert_eq!(buffer.lines().len(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn every_line_of_an_untitled_buffer_is_added() {
+        let buffer = Buffer::from_string("one\ntwo\n".into());
+
+        assert_eq!(
+            buffer.diff_against_disk(),
+            vec![LineDiff::Added("one".into()), LineDiff::Added("two".into())]
+        );
+    }
+
+    #[test]
+    fn every_line_of_a_deleted_file_is_added() {
+        let path =
+            std::env::temp_dir().join(format!("wordsmith-deleted-{}.md", std::process::id()));
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let buffer = Buffer::from_path(path.clone()).unwrap();
+
+        assert_eq!(
+            buffer.diff_against_disk(),
+            vec![
+                LineDiff::Unchanged("one".into()),
+                LineDiff::Unchanged("two".into())
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            buffer.diff_against_disk(),
+            vec![LineDiff::Added("one".into()), LineDiff::Added("two".into())]
+        );
+    }
+
+    #[test]
+    fn saving_into_a_missing_folder_fails() {
+        let path = std::env::temp_dir()
+            .join(format!("wordsmith-missing-{}", std::process::id()))
+            .join("note.md");
+        let mut buffer = Buffer::from_string("text\n".into());
+
+        assert!(matches!(
+            buffer.set_file(path, &["md".to_string()]),
+            Err(SaveError::NotFound)
+        ));
+        assert!(!buffer.pristine());
+    }
+
+    #[test]
+    fn saving_reports_what_went_wrong() {
+        let extensions = ["md".to_string()];
+        let mut buffer = Buffer::from_string("text\n".into());
+
+        assert!(matches!(buffer.save(), Err(SaveError::NoFileAssociated)));
+        assert!(matches!(
+            buffer.set_file(std::env::temp_dir().join("note.txt"), &extensions),
+            Err(SaveError::InvalidExtension)
+        ));
+        assert!(matches!(
+            SaveError::from(std::io::Error::from(ErrorKind::PermissionDenied)),
+            SaveError::PermissionDenied
+        ));
+        assert!(matches!(
+            SaveError::from(std::io::Error::from(ErrorKind::NotFound)),
+            SaveError::NotFound
+        ));
+        assert!(matches!(
+            SaveError::from(std::io::Error::from(ErrorKind::Other)),
+            SaveError::IoError(_)
+        ));
+
+        buffer.is_read_only = true;
+
+        assert!(matches!(buffer.save(), Err(SaveError::ReadOnly)));
+    }
+
+    #[test]
+    fn failing_writes_are_returned_instead_of_panicking() {
+        let path = std::env::temp_dir().join(format!("wordsmith-write-{}.md", std::process::id()));
+        fs::write(&path, "one\n").unwrap();
+
+        let mut buffer = Buffer::from_path(path.clone()).unwrap();
+        buffer.replace(0..0, "zero\n".into());
+        // A handle that can't be written to, like after the permissions changed
+        buffer.file = Some(File::open(&path).unwrap());
+
+        assert!(buffer.save().is_err());
+        assert!(!buffer.pristine());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn moving_right_reaches_the_end_of_a_multi_byte_line() {
+        let buffer = Buffer::from_string("a — “b” …\n".into());
+        let line = buffer.line(0);
+        let mut position = EditorPosition::new(0, 0);
+        let mut steps = 0;
+
+        while position.x < line.end() {
+            position = buffer.right_position(position);
+            steps += 1;
+        }
+
+        assert_eq!(position.x, line.text.len() as isize);
+        assert_eq!(steps, line.text.chars().count());
     }
 }
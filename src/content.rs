@@ -2,19 +2,38 @@ use std::ops::{Index, Range};
 
 use gpui::SharedString;
 
-use crate::{cursor::EditorPosition, text::WrappedText};
+use crate::{
+    cursor::EditorPosition,
+    editor::CHARACTER_COUNT_PER_LINE,
+    text::{Rewrap, WrappedText},
+};
 
 #[derive(Debug, Clone)]
 pub enum LineType {
     HeadlineStart(usize),
     HeadlineNotStart,
     Normal,
+    // The YAML block between `---` lines at the very top of the file
+    Frontmatter,
+    // A line of `---`, `***` or `___` on its own, drawn as a line across the text
+    HorizontalRule,
 }
 
 #[derive(Debug, Clone)]
 pub struct Line {
     pub text: String,
     pub kind: LineType,
+    pub task: Option<Task>,
+    // The widest the line can be, when the text is wrapped
+    pub max_width: Option<usize>,
+}
+
+// A task list item, like `- [ ] Outline` or `- [x] Draft`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Task {
+    // The byte index of the `[` in the text of the line
+    pub checkbox: usize,
+    pub is_done: bool,
 }
 
 impl Line {
@@ -23,14 +42,34 @@ impl Line {
             LineType::HeadlineStart(level) => level as isize * -1 - 1,
             LineType::HeadlineNotStart => 0,
             LineType::Normal => 0,
+            LineType::Frontmatter => 0,
+            LineType::HorizontalRule => 0,
         };
     }
 
     pub fn end(&self) -> isize {
-        return match self.kind {
+        let end = match self.kind {
             LineType::HeadlineStart(level) => self.length() as isize - (level as isize) - 1,
             LineType::HeadlineNotStart => self.text.len() as isize,
             LineType::Normal => self.text.len() as isize,
+            LineType::Frontmatter => self.text.len() as isize,
+            LineType::HorizontalRule => self.text.len() as isize,
+        };
+
+        // Wrapping guarantees a line never exceeds the wrap width
+        // but the cursor should never be placed past the right edge regardless
+        // The width counts characters, so it's turned into a column for multi-byte text
+        return match self.max_width {
+            Some(max_width) => {
+                let width = self
+                    .text
+                    .char_indices()
+                    .nth(max_width)
+                    .map_or(self.text.len(), |(index, _)| index);
+
+                std::cmp::min(end, self.beginning() + width as isize)
+            }
+            None => end,
         };
     }
 
@@ -49,30 +88,182 @@ impl Line {
 
         return preferred_x;
     }
+
+    // Where the cursor lands when moving onto this line from the line above or below
+    // Column 0 is the start of the text on every line, since headline markers sit in the margin
+    // So the column is kept as is, except that it never lands on the markers
+    pub fn vertical_x(&self, preferred_x: isize) -> isize {
+        let x = self.clamp_x(std::cmp::max(preferred_x, 0));
+        let mut index = self.index(x);
+
+        // Columns count bytes, so the column can fall inside a character like `—`
+        while index > 0 && !self.text.is_char_boundary(index) {
+            index -= 1;
+        }
+
+        return self.beginning() + index as isize;
+    }
+
+    // How far moving left or right goes, so the cursor steps over whole characters
+    pub fn previous_character_length(&self, x: isize) -> usize {
+        return self
+            .text
+            .get(..self.index(x))
+            .and_then(|text| text.chars().next_back())
+            .map_or(1, char::len_utf8);
+    }
+
+    pub fn next_character_length(&self, x: isize) -> usize {
+        return self
+            .text
+            .get(self.index(x)..)
+            .and_then(|text| text.chars().next())
+            .map_or(1, char::len_utf8);
+    }
+
+    // The byte index into the text for a column, which counts from `beginning`
+    fn index(&self, x: isize) -> usize {
+        return std::cmp::max(0, x - self.beginning()) as usize;
+    }
+}
+
+const WORDS_PER_MINUTE: f32 = 200.;
+const FRONTMATTER_FENCE: &str = "---";
+pub const FORMATTING_MARKERS: [char; 3] = ['*', '_', '`'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    pub words: usize,
+    pub characters: usize,
+    pub reading_minutes: usize,
+    pub lines: usize,
+    pub tasks: usize,
+    pub completed_tasks: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct Content {
-    original: SharedString,
     wrapped: WrappedText,
+    // Both are kept up to date on every change, so converting between offsets and positions is a lookup
+    // An edit only rebuilds the lines it re-wrapped and shifts the starts of the lines after them
+    lines: Vec<Line>,
+    // The offset in the wrapped text where each line starts
+    line_starts: Vec<usize>,
+    wrapped_length: usize,
+    frontmatter_lines: usize,
 }
 
 impl Content {
     pub fn new(original: SharedString) -> Content {
-        let wrapped = WrappedText::new(original.clone().into());
-
-        return Content { original, wrapped };
+        return Content::with_wrapped(WrappedText::new(original.into()));
     }
 
     pub fn empty() -> Content {
-        return Content {
-            original: SharedString::new_static(""),
-            wrapped: WrappedText::empty(),
+        return Content::with_wrapped(WrappedText::empty());
+    }
+
+    fn with_wrapped(wrapped: WrappedText) -> Content {
+        let mut content = Content {
+            wrapped,
+            lines: vec![],
+            line_starts: vec![],
+            wrapped_length: 0,
+            frontmatter_lines: 0,
         };
+
+        content.index_lines();
+
+        return content;
+    }
+
+    fn index_lines(&mut self) {
+        let end = self.wrapped.as_str().len();
+
+        self.frontmatter_lines = frontmatter_line_count(self.wrapped.as_str().lines());
+        (self.lines, self.line_starts) = self.build_lines(0..end, 0);
+
+        // `str::lines` drops the final newline, so this line is where the cursor goes after it
+        // That way a file ending in a single newline doesn't show an extra blank line
+        self.lines.push(Line {
+            text: "".into(),
+            kind: LineType::Normal,
+            task: None,
+            max_width: self.max_width(),
+        });
+        self.line_starts.push(end);
+
+        // The wrapped text always ends in a newline, even when the original doesn't
+        self.wrapped_length = self.wrapped.wrapped_offset(self.len());
+    }
+
+    // Rebuilds the lines that were re-wrapped by an edit, returning where they are now
+    fn reindex_lines(&mut self, rewrap: Rewrap) -> Range<usize> {
+        // Opening or closing the frontmatter changes what every line after it is
+        if frontmatter_line_count(self.wrapped.as_str().lines()) != self.frontmatter_lines {
+            self.index_lines();
+
+            return 0..self.lines.len();
+        }
+
+        let old_end = rewrap.start + rewrap.old_length;
+        let first = self
+            .line_starts
+            .partition_point(|start| *start < rewrap.start);
+        let last = self.line_starts.partition_point(|start| *start < old_end);
+        let (lines, line_starts) =
+            self.build_lines(rewrap.start..rewrap.start + rewrap.new_length, first);
+        let rebuilt = first..first + lines.len();
+
+        for start in self.line_starts[last..].iter_mut() {
+            *start = *start + rewrap.new_length - rewrap.old_length;
+        }
+
+        self.lines.splice(first..last, lines);
+        self.line_starts.splice(first..last, line_starts);
+        self.wrapped_length = self.wrapped.wrapped_offset(self.len());
+
+        return rebuilt;
     }
 
     pub fn to_string(&self) -> String {
-        return self.original.clone().into();
+        return self.wrapped.original().to_string();
+    }
+
+    // The length of the source text, without the wrap points
+    pub fn len(&self) -> usize {
+        return self.wrapped.original().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    // The lines of the source text, unlike `lines` which has every soft-wrapped fragment
+    // A trailing newline ends the last line rather than starting another one
+    pub fn logical_line_count(&self) -> usize {
+        return self.wrapped.line_count();
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        let text = self.to_string();
+        // Frontmatter is metadata rather than part of the writing
+        let text: String = text
+            .split_inclusive('\n')
+            .skip(frontmatter_line_count(text.lines()))
+            .collect();
+        let words = word_count(&text);
+        let characters = character_count(&text);
+
+        let tasks: Vec<Task> = self.lines().iter().filter_map(|line| line.task).collect();
+
+        return Statistics {
+            words,
+            characters,
+            reading_minutes: (words as f32 / WORDS_PER_MINUTE).round() as usize,
+            lines: self.logical_line_count(),
+            tasks: tasks.len(),
+            completed_tasks: tasks.iter().filter(|task| task.is_done).count(),
+        };
     }
 
     pub fn text(&self) -> WrappedText {
@@ -80,24 +271,57 @@ impl Content {
     }
 
     pub fn lines(&self) -> Vec<Line> {
-        let raw_lines: Vec<_> = self
-            .text()
-            .to_string()
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
+        return self.lines.clone();
+    }
+
+    // Builds the lines in the range of the wrapped text, along with where each of them starts
+    // The range covers whole logical lines, and the index is where the first one goes
+    fn build_lines(&self, range: Range<usize>, first_index: usize) -> (Vec<Line>, Vec<usize>) {
         let mut lines: Vec<Line> = vec![];
+        let mut line_starts = vec![];
         let mut is_inside_headline = false;
+        let max_width = self.max_width();
+        let mut offset = range.start;
 
-        for raw in raw_lines {
-            let is_start_of_headline = is_headline(raw.clone());
+        for (index, raw) in self.wrapped.as_str()[range].lines().enumerate() {
+            let raw = raw.to_string();
+            let starts_logical_line = !self.wrapped.is_wrap_point(offset);
+
+            line_starts.push(offset);
+            offset += raw.len() + 1; // Newline
+
+            // Nothing in the frontmatter is markdown, even if it looks like it
+            if first_index + index < self.frontmatter_lines {
+                lines.push(Line {
+                    text: raw,
+                    kind: LineType::Frontmatter,
+                    task: None,
+                    max_width,
+                });
 
-            if is_start_of_headline {
-                is_inside_headline = true;
+                continue;
             }
 
-            if raw.is_empty() {
+            // The frontmatter fences above are the only `---` lines that aren't rules
+            // The end of a soft-wrapped line can look like one too, so only whole lines count
+            if starts_logical_line && is_horizontal_rule(&raw) {
                 is_inside_headline = false;
+
+                lines.push(Line {
+                    text: raw,
+                    kind: LineType::HorizontalRule,
+                    task: None,
+                    max_width,
+                });
+
+                continue;
+            }
+
+            let is_start_of_headline = is_headline(raw.clone());
+
+            // Only the soft-wrapped rest of a headline belongs to it, not the lines after it
+            if starts_logical_line {
+                is_inside_headline = is_start_of_headline;
             }
 
             let kind = if is_start_of_headline {
@@ -113,87 +337,265 @@ impl Content {
                 LineType::Normal
             };
 
-            lines.push(Line { text: raw, kind })
+            let task = match kind {
+                LineType::Normal => task(&raw),
+                _ => None,
+            };
+
+            lines.push(Line {
+                text: raw,
+                kind,
+                task,
+                max_width,
+            })
         }
 
-        lines.push(Line {
-            text: "".into(),
-            kind: LineType::Normal,
-        });
+        return (lines, line_starts);
+    }
 
-        return lines;
+    fn max_width(&self) -> Option<usize> {
+        if self.wrapped.wraps() {
+            return Some(CHARACTER_COUNT_PER_LINE);
+        }
+
+        return None;
     }
 
     pub fn wrap_points(&self) -> Vec<usize> {
         return self.wrapped.wrap_points();
     }
 
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrapped.set_wrap(wrap);
+        self.index_lines();
+    }
+
+    pub fn original_offset(&self, offset: usize) -> usize {
+        return self.wrapped.original_offset(offset);
+    }
+
+    pub fn wrapped_offset(&self, offset: usize) -> usize {
+        return self.wrapped.wrapped_offset(offset);
+    }
+
     pub fn replace(&mut self, range: Range<usize>, replacement: String) {
-        self.wrapped.replace(range, replacement);
-        self.original = self.wrapped.original().to_string().into();
+        let rewrap = self.wrapped.replace(range, replacement);
+
+        self.reindex_lines(rewrap);
     }
 
     pub fn read_range(&self, range: Range<usize>) -> String {
-        return self.text().read_range(range);
+        return self.wrapped.read_range(range);
     }
 
-    pub fn line(&self, index: usize) -> Line {
-        let lines = self.lines();
-
-        return lines.index(index).clone();
+    pub fn read_wrapped_range(&self, range: Range<usize>) -> String {
+        return self.wrapped.read_wrapped_range(range);
     }
 
-    pub fn position_to_offset(&self, position: EditorPosition) -> usize {
-        let lines = self.lines();
+    // Makes the text end with exactly one newline
+    // Empty documents are left empty, and ones with nothing but newlines become empty
+    pub fn ensure_single_trailing_newline(&mut self) {
+        let original = self.to_string();
+        let trimmed = original.trim_end_matches(['\n', '\r']);
 
-        if lines.is_empty() {
-            return 0;
+        if trimmed.is_empty() {
+            if !original.is_empty() {
+                let end = self.wrapped_offset(original.len());
+
+                self.replace(0..end, String::new());
+            }
+
+            return;
         }
 
-        let previous_lines = lines.iter().take(position.y);
-        let mut offset: isize = 0;
+        let line_ending = if original.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
 
-        for line in previous_lines {
-            offset += line.length() as isize;
-            offset += 1; // Newline
+        if &original[trimmed.len()..] == line_ending {
+            return;
         }
 
-        let line = lines.index(position.y);
+        let start = self.wrapped_offset(trimmed.len());
+        let end = self.wrapped_offset(original.len());
 
-        if let LineType::HeadlineStart(level) = line.kind {
-            offset += level as isize + 1;
+        self.replace(start..end, line_ending.to_string());
+    }
+
+    // Removes the spaces and tabs at the end of every line
+    // The whole text is replaced at once, since every replacement re-indexes the lines
+    pub fn trim_trailing_whitespace(&mut self) {
+        let original = self.to_string();
+        let trimmed: String = original
+            .split_inclusive('\n')
+            .map(|line| {
+                let text = line.strip_suffix('\n').unwrap_or(line);
+                let text = text.strip_suffix('\r').unwrap_or(text);
+
+                return format!(
+                    "{}{}",
+                    text.trim_end_matches([' ', '\t']),
+                    &line[text.len()..]
+                );
+            })
+            .collect();
+
+        if trimmed == original {
+            return;
         }
 
-        offset += position.x;
+        let end = self.wrapped_offset(original.len());
 
-        return std::cmp::max(0, offset) as usize;
+        self.replace(0..end, trimmed);
     }
 
-    pub fn offset_to_position(&self, offset: usize) -> EditorPosition {
-        let lines = self.lines();
-        let mut x = offset;
-        let mut y = 0;
+    // Tidies the spacing of the markdown, returning whether anything changed
+    pub fn format(&mut self) -> bool {
+        let original = self.to_string();
+        let formatted = format_markdown(&original);
 
-        for line in lines {
-            if x <= line.length() {
-                break;
-            }
+        if formatted == original {
+            return false;
+        }
+
+        let end = self.wrapped_offset(original.len());
+
+        self.replace(0..end, formatted);
+
+        return true;
+    }
 
-            y += 1;
-            x -= line.length();
-            x = x.saturating_sub(1);
+    pub fn line(&self, index: usize) -> Line {
+        return self.lines.index(index).clone();
+    }
+
+    // The text in the range without the markdown, for pasting where it wouldn't be rendered
+    pub fn plain_text(&self, range: Range<usize>) -> String {
+        return self
+            .read_range(range)
+            .split('\n')
+            .map(plain_line)
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+
+    pub fn position_to_offset(&self, position: EditorPosition) -> usize {
+        if self.lines.is_empty() {
+            return 0;
         }
 
+        let line = self.lines.index(position.y);
+        let mut offset = self.line_starts[position.y] as isize;
+
+        if let LineType::HeadlineStart(level) = line.kind {
+            offset += level as isize + 1;
+        }
+
+        // A column past the end of the line would point into the next line
+        offset += position.x.clamp(line.beginning(), line.end());
+
+        // The last line is virtual when the text doesn't end in a newline
+        // so it starts one past the end of the text rather than after a newline
+        return std::cmp::min(std::cmp::max(0, offset) as usize, self.wrapped_length);
+    }
+
+    pub fn offset_to_position(&self, offset: usize) -> EditorPosition {
+        // The offset belongs to the first line that ends at or after it
+        // Every line ends right before the next one starts, after the newline
+        let y = self
+            .line_starts
+            .partition_point(|start| *start <= offset)
+            .saturating_sub(1);
         let line = self.line(y);
+        let mut x = (offset - self.line_starts[y]) as isize;
 
+        // Offsets in the headline markers are before the start of the text, so negative
         if let LineType::HeadlineStart(level) = line.kind {
-            x -= level + 1;
+            x -= level as isize + 1;
         }
 
-        return EditorPosition::new(y, x as isize);
+        return EditorPosition::new(y, x);
     }
 }
 
+// Three or more of the same marker, optionally with spaces between them
+pub fn is_horizontal_rule(text: &str) -> bool {
+    let characters: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    return characters.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|marker| characters.iter().all(|character| character == marker));
+}
+
+// The number of lines in the frontmatter, including the opening and closing `---`
+// Zero when the file doesn't start with frontmatter or it's never closed
+fn frontmatter_line_count<'a>(mut lines: impl Iterator<Item = &'a str>) -> usize {
+    if lines.next().map(|line| line.trim_end()) != Some(FRONTMATTER_FENCE) {
+        return 0;
+    }
+
+    return lines
+        .position(|line| line.trim_end() == FRONTMATTER_FENCE)
+        .map_or(0, |index| index + 2);
+}
+
+// Markdown markers on their own, like a headline `#` or a list `-`, aren't words
+pub fn word_count(text: &str) -> usize {
+    return text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+}
+
+// Line breaks don't count as characters
+pub fn character_count(text: &str) -> usize {
+    return text
+        .chars()
+        .filter(|character| *character != '\n' && *character != '\r')
+        .count();
+}
+
+fn task(text: &str) -> Option<Task> {
+    let indentation = text.len() - text.trim_start().len();
+    let item = &text[indentation..];
+    let digits = item
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+    let marker_length = if ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| item.starts_with(marker))
+    {
+        2
+    } else if digits > 0 && item[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        return None;
+    };
+
+    let checkbox = &item[marker_length..];
+    let is_done = if checkbox.starts_with("[ ]") {
+        false
+    } else if checkbox.starts_with("[x]") || checkbox.starts_with("[X]") {
+        true
+    } else {
+        return None;
+    };
+
+    // `- [ ]` on its own is a task that hasn't been written yet
+    if checkbox.len() > 3 && !checkbox[3..].starts_with(' ') {
+        return None;
+    }
+
+    return Some(Task {
+        checkbox: indentation + marker_length,
+        is_done,
+    });
+}
+
 fn is_headline(line: String) -> bool {
     let trimmed = line.trim_start();
 
@@ -207,3 +609,507 @@ fn is_headline(line: String) -> bool {
         && hash_count < trimmed.len()
         && trimmed.chars().nth(hash_count) == Some(' ');
 }
+
+// Removes the markers that form pairs, like the `**` around bold text
+// Markers without a partner are kept, since they might belong to formatting outside of the text
+pub fn remove_formatting(text: &str) -> String {
+    let characters: Vec<char> = text.chars().collect();
+    // Runs of the same marker, as the character, where they start, and how long they are
+    let mut runs: Vec<(char, usize, usize)> = vec![];
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let length = characters[index..]
+            .iter()
+            .take_while(|other| **other == character)
+            .count();
+
+        // Underscores inside words, like in snake_case, aren't formatting
+        let is_inside_word = character == '_'
+            && index > 0
+            && characters[index - 1].is_alphanumeric()
+            && characters
+                .get(index + length)
+                .map_or(false, |next| next.is_alphanumeric());
+
+        if FORMATTING_MARKERS.contains(&character) && !is_inside_word {
+            runs.push((character, index, length));
+        }
+
+        index += length;
+    }
+
+    let mut removed = vec![false; characters.len()];
+    let mut open: Vec<(char, usize, usize)> = vec![];
+
+    for run in runs {
+        match open
+            .iter()
+            .rposition(|(character, _, length)| *character == run.0 && *length == run.2)
+        {
+            Some(position) => {
+                let (_, start, length) = open.remove(position);
+
+                removed[start..start + length].fill(true);
+                removed[run.1..run.1 + run.2].fill(true);
+            }
+            None => open.push(run),
+        }
+    }
+
+    return characters
+        .into_iter()
+        .zip(removed)
+        .filter(|(_, removed)| !removed)
+        .map(|(character, _)| character)
+        .collect();
+}
+
+// A line as it reads once the markdown is rendered
+// Bullets are kept as `- ` so lists still look like lists in plain text
+fn plain_line(line: &str) -> String {
+    let indentation_length = line.len() - line.trim_start().len();
+    let (indentation, mut rest) = line.split_at(indentation_length);
+    let mut prefix = String::new();
+
+    if is_headline(rest.to_string()) {
+        rest = rest.trim_start_matches('#').trim_start();
+    }
+
+    if let Some(quoted) = rest.strip_prefix("> ") {
+        rest = quoted;
+    }
+
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| rest.strip_prefix(marker))
+    {
+        prefix.push_str("- ");
+        rest = item;
+    } else {
+        let digits = rest
+            .chars()
+            .take_while(|character| character.is_ascii_digit())
+            .count();
+
+        if digits > 0 && rest[digits..].starts_with(". ") {
+            prefix.push_str(&rest[..digits + 2]);
+            rest = &rest[digits + 2..];
+        }
+    }
+
+    if let Some(item) = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|checkbox| rest.strip_prefix(checkbox))
+    {
+        rest = item;
+    }
+
+    return format!(
+        "{}{}{}",
+        indentation,
+        prefix,
+        remove_formatting(&remove_links(rest))
+    );
+}
+
+// Reduces links to their text and images to their description, like `[text](url)` to `text`
+fn remove_links(text: &str) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|index| open + index) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|index| close + index) else {
+            break;
+        };
+        let label = &rest[open + 1..close];
+
+        // The bracket belongs to something else, like `[1]`, when another link starts inside it
+        if label.contains('[') {
+            output.push_str(&rest[..open + 1]);
+            rest = &rest[open + 1..];
+
+            continue;
+        }
+
+        let before = &rest[..open];
+
+        output.push_str(before.strip_suffix('!').unwrap_or(before));
+        output.push_str(label);
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+
+    return output;
+}
+
+// Tidies the spacing without changing what the markdown means
+// Frontmatter and code blocks are left as they are, since their whitespace matters
+fn format_markdown(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let frontmatter_lines = frontmatter_line_count(lines.iter().copied());
+    let mut formatted: Vec<String> = vec![];
+    let mut is_inside_code_block = false;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let is_fence = ["```", "~~~"]
+            .iter()
+            .any(|fence| line.trim_start().starts_with(fence));
+
+        if index < frontmatter_lines || is_inside_code_block || is_fence {
+            if is_fence {
+                is_inside_code_block = !is_inside_code_block;
+            }
+
+            formatted.push(line.to_string());
+            index += 1;
+
+            continue;
+        }
+
+        // Three or more blank lines in a row become one
+        if line.trim().is_empty() {
+            let blank_lines = lines[index..]
+                .iter()
+                .take_while(|line| line.trim().is_empty())
+                .count();
+            let kept = if blank_lines >= 3 { 1 } else { blank_lines };
+
+            for blank_line in &lines[index..index + kept] {
+                // Keep the carriage return of Windows line endings
+                formatted.push(if blank_line.ends_with('\r') {
+                    "\r".to_string()
+                } else {
+                    String::new()
+                });
+            }
+
+            index += blank_lines;
+
+            continue;
+        }
+
+        formatted.push(format_line(line));
+        index += 1;
+    }
+
+    return formatted.join("\n");
+}
+
+fn format_line(line: &str) -> String {
+    let (line, line_ending) = match line.strip_suffix('\r') {
+        Some(line) => (line, "\r"),
+        None => (line, ""),
+    };
+    let text = line.trim_end_matches([' ', '\t']);
+    let indentation_length = text.len() - text.trim_start().len();
+    let (indentation, rest) = text.split_at(indentation_length);
+
+    let is_headline = is_headline(rest.to_string());
+    // Two spaces at the end of a paragraph line are a line break, so they're kept
+    let trailing = if line.len() - line.trim_end_matches(' ').len() >= 2 && !is_headline {
+        "  "
+    } else {
+        ""
+    };
+
+    let marker_length = if is_headline {
+        rest.chars()
+            .take_while(|&character| character == '#')
+            .count()
+    } else if ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| rest.starts_with(marker))
+    {
+        1
+    } else {
+        let digits = rest
+            .chars()
+            .take_while(|character| character.is_ascii_digit())
+            .count();
+
+        if digits > 0 && rest[digits..].starts_with(". ") {
+            digits + 1
+        } else {
+            0
+        }
+    };
+
+    // A single space after headline and list markers
+    let rest = if marker_length > 0 {
+        format!(
+            "{} {}",
+            &rest[..marker_length],
+            rest[marker_length..].trim_start_matches(' ')
+        )
+    } else {
+        rest.to_string()
+    };
+
+    return format!("{}{}{}{}", indentation, rest, trailing, line_ending);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cursor_can_reach_the_end_of_multi_byte_lines() {
+        let text = "—".repeat(30);
+        let content = Content::new(format!("{}\n", text).into());
+        let line = content.line(0);
+
+        assert_eq!(line.end(), text.len() as isize);
+        assert_eq!(
+            content.position_to_offset(EditorPosition::new(0, line.end())),
+            text.len()
+        );
+    }
+
+    #[test]
+    fn wrapped_multi_byte_lines_end_at_the_wrap_width_in_characters() {
+        let content = Content::new(format!("{}\n", "…".repeat(70)).into());
+        let first = content.line(0);
+        let second = content.line(1);
+
+        assert_eq!(first.text.chars().count(), CHARACTER_COUNT_PER_LINE);
+        assert_eq!(first.end(), first.text.len() as isize);
+        assert_eq!(second.end(), "…".len() as isize * 10);
+    }
+
+    #[test]
+    fn headlines_with_multi_byte_text_end_after_their_last_character() {
+        let content = Content::new("## Café “menu”\n".into());
+        let line = content.line(0);
+
+        assert_eq!(line.end(), "Café “menu”".len() as isize);
+    }
+
+    #[test]
+    fn columns_past_the_end_of_a_line_stay_on_that_line() {
+        let content = Content::new("first\nsecond\n## Third\n".into());
+
+        assert_eq!(content.position_to_offset(EditorPosition::new(0, 40)), 5);
+        assert_eq!(content.position_to_offset(EditorPosition::new(1, 40)), 12);
+        assert_eq!(content.position_to_offset(EditorPosition::new(2, 40)), 21);
+        // Columns before the headline markers start at the line instead
+        assert_eq!(content.position_to_offset(EditorPosition::new(2, -10)), 13);
+    }
+
+    #[test]
+    fn logical_lines_leave_out_soft_wraps() {
+        let paragraph =
+            "A paragraph that goes on for long enough to be wrapped more than once. ".repeat(3);
+        let content = Content::new(format!("# Title\n\n{}\nLast line\n", paragraph).into());
+
+        // The wrapped paragraph and the line after the final newline count for the rendering
+        assert_eq!(content.lines().len(), 8);
+        assert_eq!(content.logical_line_count(), 4);
+        assert_eq!(content.statistics().lines, 4);
+        assert_eq!(Content::new("no newline".into()).logical_line_count(), 1);
+        assert_eq!(Content::new("".into()).logical_line_count(), 0);
+    }
+
+    #[test]
+    fn format_tidies_a_messy_document() {
+        let mut content = Content::new(
+            "---\ntitle:   Notes  \n---\n#   Title   \n\n\n\n\n-   one \n*  two\n12.   twelve\nA line break  \n```\n  code   \n\n\n\n```\n\n\nEnd\t\n"
+                .into(),
+        );
+
+        assert!(content.format());
+        assert_eq!(
+            content.to_string(),
+            "---\ntitle:   Notes  \n---\n# Title\n\n- one\n* two\n12. twelve\nA line break  \n```\n  code   \n\n\n\n```\n\n\nEnd\n"
+        );
+    }
+
+    #[test]
+    fn format_keeps_windows_line_endings() {
+        let mut content = Content::new("#  Title \r\n\r\n\r\n\r\ntext\r\n".into());
+
+        assert!(content.format());
+        assert_eq!(content.to_string(), "# Title\r\n\r\ntext\r\n");
+    }
+
+    #[test]
+    fn format_leaves_a_tidy_document_alone() {
+        let text = "# Title\n\nSome text.\n\n- one\n- two\n";
+        let mut content = Content::new(text.into());
+
+        assert!(!content.format());
+        assert_eq!(content.to_string(), text);
+    }
+
+    #[test]
+    fn documents_end_with_a_single_newline() {
+        for (text, expected) in [
+            ("text", "text\n"),
+            ("text\n", "text\n"),
+            ("text\n\n\n", "text\n"),
+            ("one\r\ntwo\r\n\r\n\n", "one\r\ntwo\r\n"),
+            ("text\r", "text\n"),
+            ("\n\n", ""),
+            ("", ""),
+        ] {
+            let mut content = Content::new(text.into());
+
+            content.ensure_single_trailing_newline();
+
+            assert_eq!(content.to_string(), expected, "{:?}", text);
+        }
+    }
+
+    // A small deterministic generator, so failures can be reproduced
+    fn random(seed: &mut u64, below: usize) -> usize {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+
+        return (*seed >> 33) as usize % below;
+    }
+
+    // Adds up the line lengths instead of looking the line up in the index
+    fn naive_position_to_offset(content: &Content, position: EditorPosition) -> usize {
+        let lines = content.lines();
+        let line = &lines[position.y];
+        let mut offset: isize = lines[..position.y]
+            .iter()
+            .map(|line| line.length() as isize + 1)
+            .sum();
+
+        if let LineType::HeadlineStart(level) = line.kind {
+            offset += level as isize + 1;
+        }
+
+        offset += position.x.clamp(line.beginning(), line.end());
+
+        return std::cmp::min(offset.max(0) as usize, content.wrapped_length);
+    }
+
+    #[test]
+    fn the_line_index_matches_a_fresh_content() {
+        let insertions = [
+            "a",
+            "\n",
+            "—",
+            "“quoted” ",
+            "# ",
+            "---\n",
+            "- [ ] task\n",
+            "A sentence that is long enough to be wrapped on its own, with “curly” quotes — and café.",
+        ];
+        let mut seed = 3;
+        let mut text = "# Title\n\nSome “text” — that is long enough to wrap around the width of the editor.\n".repeat(5);
+        let mut content = Content::new(text.clone().into());
+
+        for _ in 0..300 {
+            let boundaries: Vec<usize> = text
+                .char_indices()
+                .map(|(index, _)| index)
+                .chain([text.len()])
+                .collect();
+            let start = random(&mut seed, boundaries.len());
+            let end = start + random(&mut seed, std::cmp::min(boundaries.len() - start, 20));
+            let range = boundaries[start]..boundaries[end];
+            let replacement = insertions[random(&mut seed, insertions.len())];
+
+            content.replace(
+                content.wrapped_offset(range.start)..content.wrapped_offset(range.end),
+                replacement.to_string(),
+            );
+            text.replace_range(range, replacement);
+
+            let fresh = Content::new(text.clone().into());
+
+            assert_eq!(content.to_string(), text);
+            assert_eq!(
+                format!("{:?}", content.lines()),
+                format!("{:?}", fresh.lines())
+            );
+            assert_eq!(content.line_starts, fresh.line_starts);
+            assert_eq!(content.wrapped_length, fresh.wrapped_length);
+
+            for offset in 0..=fresh.wrapped_length {
+                assert_eq!(
+                    content.offset_to_position(offset),
+                    fresh.offset_to_position(offset)
+                );
+            }
+
+            for (y, line) in fresh.lines().iter().enumerate() {
+                for x in line.beginning() - 1..=line.end() + 1 {
+                    let position = EditorPosition::new(y, x);
+
+                    assert_eq!(
+                        content.position_to_offset(position.clone()),
+                        naive_position_to_offset(&fresh, position)
+                    );
+                }
+            }
+        }
+    }
+
+    // Benchmark-style, counting the lines an edit wraps and rebuilds rather than timing it
+    #[test]
+    fn an_edit_near_the_end_of_a_large_document_only_rebuilds_its_own_lines() {
+        let paragraph =
+            "A paragraph that is long enough to be wrapped onto a second line of the editor.\n";
+        let text = paragraph.repeat(10_000);
+        let mut content = Content::new(text.clone().into());
+        let line_count = content.lines.len();
+
+        // Wrapping from scratch builds every line of the document
+        assert_eq!(line_count, 20_001);
+
+        let offset = content.wrapped_offset(text.len() - 10);
+        let rewrap = content.wrapped.replace(offset..offset, "word ".into());
+        let rebuilt = content.reindex_lines(rewrap);
+
+        // The edit only wraps and rebuilds the two lines of the last paragraph
+        assert_eq!(rewrap.start, content.line_starts[line_count - 3]);
+        assert_eq!(rewrap.old_length, paragraph.len() + 1);
+        assert_eq!(rewrap.new_length, paragraph.len() + 6);
+        assert_eq!(rebuilt, line_count - 3..line_count - 1);
+
+        let fresh = Content::new(content.to_string().into());
+
+        assert_eq!(
+            format!("{:?}", content.lines()),
+            format!("{:?}", fresh.lines())
+        );
+        assert_eq!(content.line_starts, fresh.line_starts);
+        assert_eq!(content.wrap_points(), fresh.wrap_points());
+    }
+
+    #[test]
+    fn opening_and_closing_the_frontmatter_rebuilds_the_lines_after_it() {
+        let mut content = Content::new("title: Notes\n# Headline\nText\n".into());
+
+        content.replace(0..0, "---\n".into());
+
+        assert!(matches!(content.line(1).kind, LineType::Normal));
+        assert!(matches!(content.line(2).kind, LineType::HeadlineStart(1)));
+
+        let end = content.wrapped_offset("---\ntitle: Notes\n".len());
+
+        content.replace(end..end, "---\n".into());
+
+        assert!(matches!(content.line(1).kind, LineType::Frontmatter));
+        assert!(matches!(content.line(2).kind, LineType::Frontmatter));
+        assert!(matches!(content.line(3).kind, LineType::HeadlineStart(1)));
+
+        content.replace(0..4, String::new());
+
+        assert!(matches!(content.line(0).kind, LineType::Normal));
+        assert!(matches!(content.line(1).kind, LineType::HorizontalRule));
+        assert!(matches!(content.line(2).kind, LineType::HeadlineStart(1)));
+    }
+}
@@ -1,12 +1,14 @@
 use std::cmp::Ordering;
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone)]
 pub enum EditLocation {
     Cursor(Cursor),
     Selection(Selection),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditorPosition {
     pub x: isize,
     pub y: usize,
@@ -77,11 +79,20 @@ impl Cursor {
 pub struct Selection {
     pub start: EditorPosition,
     pub end: EditorPosition,
+    // The column the end of the selection aims for when extending it up or down
+    // Short wrapped lines clamp the end, but shouldn't make later lines drift to the left
+    pub preferred_x: isize,
 }
 
 impl Selection {
     pub fn new(start: EditorPosition, end: EditorPosition) -> Selection {
-        return Selection { start, end };
+        let preferred_x = end.x;
+
+        return Selection {
+            start,
+            end,
+            preferred_x,
+        };
     }
 }
 
@@ -102,6 +113,16 @@ impl Selection {
         }
     }
 
+    // The column to aim for when moving on from one of the ends of the selection
+    // Only the end that moved has a preferred column
+    pub fn preferred_x_at(&self, position: &EditorPosition) -> isize {
+        if *position == self.end {
+            return self.preferred_x;
+        }
+
+        return position.x;
+    }
+
     pub fn largest(&self) -> EditorPosition {
         if self.start > self.end {
             return self.start.clone();
@@ -0,0 +1,314 @@
+// Past this many added and removed lines the texts are too different to be worth comparing
+// and every line in between counts as replaced, which keeps the work bounded for huge edits
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+// A line of the new text compared to the old one
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineDiff {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+// Compares the texts line by line, keeping as many lines unchanged as possible
+// Removed lines come before the lines added in their place
+pub fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Edits tend to be small, so the lines around them are skipped before comparing
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    let mut diff: Vec<LineDiff> = old[..prefix]
+        .iter()
+        .map(|line| LineDiff::Unchanged(line.to_string()))
+        .collect();
+
+    diff.extend(diff_changed_lines(old_changed, new_changed));
+    diff.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| LineDiff::Unchanged(line.to_string())),
+    );
+
+    return diff;
+}
+
+// Myers' algorithm, which only does work for each line that differs
+// So an edit in a long document costs about as much as the document is long
+fn diff_changed_lines(old: &[&str], new: &[&str]) -> Vec<LineDiff> {
+    let Some(trace) = shortest_edit(old, new) else {
+        return old
+            .iter()
+            .map(|line| LineDiff::Removed(line.to_string()))
+            .chain(new.iter().map(|line| LineDiff::Added(line.to_string())))
+            .collect();
+    };
+
+    // Walks back from the end through the furthest points reached after each edit
+    let mut diff = vec![];
+    let (mut x, mut y) = (old.len() as isize, new.len() as isize);
+
+    for d in (1..trace.len() as isize).rev() {
+        let previous = &trace[d as usize - 1];
+        let furthest = |k: isize| previous[(k + d - 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            diff.push(LineDiff::Unchanged(old[x as usize - 1].to_string()));
+            x -= 1;
+            y -= 1;
+        }
+
+        if x == previous_x {
+            diff.push(LineDiff::Added(new[y as usize - 1].to_string()));
+            y -= 1;
+        } else {
+            diff.push(LineDiff::Removed(old[x as usize - 1].to_string()));
+            x -= 1;
+        }
+    }
+
+    while x > 0 {
+        diff.push(LineDiff::Unchanged(old[x as usize - 1].to_string()));
+        x -= 1;
+    }
+
+    diff.reverse();
+
+    // Removed lines come before the lines added in their place
+    for change in diff.split_mut(|line| matches!(line, LineDiff::Unchanged(_))) {
+        change.sort_by_key(|line| matches!(line, LineDiff::Added(_)));
+    }
+
+    return diff;
+}
+
+// The furthest point reached in the old text for each diagonal `k` after each number of edits
+// Diagonal `k` of step `d` is stored at index `k + d`
+// Nothing when the texts differ by more than `MAX_EDIT_DISTANCE` lines
+fn shortest_edit(old: &[&str], new: &[&str]) -> Option<Vec<Vec<isize>>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = std::cmp::min(n + m, MAX_EDIT_DISTANCE as isize);
+    let offset = max + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = vec![];
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && furthest[index - 1] < furthest[index + 1]) {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            furthest[index] = x;
+        }
+
+        trace.push(furthest[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        let end = (n - m + offset) as usize;
+
+        if (n - m).abs() <= d && furthest[end] >= n {
+            return Some(trace);
+        }
+    }
+
+    return None;
+}
+
+// How a line of the new text came about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineChange {
+    Added,
+    Modified,
+}
+
+// The lines of the new text that aren't unchanged, by their index in the new text
+// Added lines that take the place of removed ones count as modified
+pub fn changed_lines(diff: &[LineDiff]) -> Vec<(usize, LineChange)> {
+    let mut index = 0;
+    let mut removed = 0;
+    let mut changes = vec![];
+
+    for line in diff {
+        match line {
+            LineDiff::Unchanged(_) => {
+                removed = 0;
+                index += 1;
+            }
+            LineDiff::Removed(_) => removed += 1,
+            LineDiff::Added(_) => {
+                let change = if removed > 0 {
+                    removed -= 1;
+                    LineChange::Modified
+                } else {
+                    LineChange::Added
+                };
+
+                changes.push((index, change));
+                index += 1;
+            }
+        }
+    }
+
+    return changes;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> String {
+        return (0..count)
+            .map(|index| format!("line {}\n", index))
+            .collect();
+    }
+
+    #[test]
+    fn identical_texts_are_unchanged() {
+        let text = "one\ntwo\nthree\n";
+
+        assert_eq!(
+            diff_lines(text, text),
+            vec![
+                LineDiff::Unchanged("one".into()),
+                LineDiff::Unchanged("two".into()),
+                LineDiff::Unchanged("three".into()),
+            ]
+        );
+        assert!(changed_lines(&diff_lines(text, text)).is_empty());
+    }
+
+    #[test]
+    fn edits_at_the_first_and_last_line() {
+        let diff = diff_lines("one\ntwo\nthree\n", "One\ntwo\nthree\nfour\n");
+
+        assert_eq!(
+            diff,
+            vec![
+                LineDiff::Removed("one".into()),
+                LineDiff::Added("One".into()),
+                LineDiff::Unchanged("two".into()),
+                LineDiff::Unchanged("three".into()),
+                LineDiff::Added("four".into()),
+            ]
+        );
+        assert_eq!(
+            changed_lines(&diff),
+            vec![(0, LineChange::Modified), (3, LineChange::Added)]
+        );
+    }
+
+    #[test]
+    fn removed_lines_come_before_added_ones() {
+        let diff = diff_lines("a\nb\nc\nd\n", "a\nx\ny\nz\nd\n");
+
+        assert_eq!(
+            diff,
+            vec![
+                LineDiff::Unchanged("a".into()),
+                LineDiff::Removed("b".into()),
+                LineDiff::Removed("c".into()),
+                LineDiff::Added("x".into()),
+                LineDiff::Added("y".into()),
+                LineDiff::Added("z".into()),
+                LineDiff::Unchanged("d".into()),
+            ]
+        );
+        assert_eq!(
+            changed_lines(&diff),
+            vec![
+                (1, LineChange::Modified),
+                (2, LineChange::Modified),
+                (3, LineChange::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_lines_between_scattered_edits() {
+        let diff = diff_lines("a\nb\nc\nd\ne\n", "a\nc\nd\nx\ne\n");
+
+        assert_eq!(
+            diff,
+            vec![
+                LineDiff::Unchanged("a".into()),
+                LineDiff::Removed("b".into()),
+                LineDiff::Unchanged("c".into()),
+                LineDiff::Unchanged("d".into()),
+                LineDiff::Added("x".into()),
+                LineDiff::Unchanged("e".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn everything_is_added_to_an_empty_text() {
+        assert_eq!(
+            changed_lines(&diff_lines("", "one\ntwo\n")),
+            vec![(0, LineChange::Added), (1, LineChange::Added)]
+        );
+        assert_eq!(
+            diff_lines("one\n", ""),
+            vec![LineDiff::Removed("one".into())]
+        );
+    }
+
+    #[test]
+    fn small_edits_in_large_documents() {
+        let old = lines(100_000);
+        let new = old
+            .replace("line 10\n", "changed\n")
+            .replace("line 50000\n", "");
+        let changes = changed_lines(&diff_lines(&old, &new));
+
+        assert_eq!(changes, vec![(10, LineChange::Modified)]);
+    }
+
+    #[test]
+    fn texts_too_different_to_compare_are_replaced() {
+        let old = lines(MAX_EDIT_DISTANCE);
+        let new = old.replace("line", "other");
+        let diff = diff_lines(
+            &format!("start\n{}end\n", old),
+            &format!("start\n{}end\n", new),
+        );
+
+        assert_eq!(diff.len(), 2 * MAX_EDIT_DISTANCE + 2);
+        assert_eq!(diff[0], LineDiff::Unchanged("start".into()));
+        assert!(diff[1..=MAX_EDIT_DISTANCE]
+            .iter()
+            .all(|line| matches!(line, LineDiff::Removed(_))));
+        assert!(diff[MAX_EDIT_DISTANCE + 1..=2 * MAX_EDIT_DISTANCE]
+            .iter()
+            .all(|line| matches!(line, LineDiff::Added(_))));
+        assert_eq!(diff.last(), Some(&LineDiff::Unchanged("end".into())));
+    }
+}
@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+use crate::content::{Line, LineType};
+use crate::cursor::EditorPosition;
+use crate::editor::CHARACTER_COUNT_PER_LINE;
+
+const MARKERS: [char; 2] = ['*', '`'];
+
+// Keeps track of the parts of each line that are hidden when rendering
+// The ranges are byte ranges into the text of the line
+#[derive(Debug, Clone)]
+pub struct DisplayMap {
+    hidden: Vec<Vec<Range<usize>>>,
+}
+
+impl DisplayMap {
+    pub fn new(lines: &Vec<Line>, revealed: Range<usize>) -> DisplayMap {
+        let hidden = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                if revealed.contains(&index) {
+                    vec![]
+                } else {
+                    hidden_ranges(line)
+                }
+            })
+            .collect();
+
+        return DisplayMap { hidden };
+    }
+
+    pub fn text(&self, y: usize, line: &Line) -> String {
+        let hidden = self.hidden(y);
+
+        return line
+            .text
+            .char_indices()
+            .filter(|(index, _)| !hidden.iter().any(|range| range.contains(index)))
+            .map(|(_, character)| character)
+            .collect();
+    }
+
+    // The column where the rendered text of the line starts
+    // Negative when the headline markers are shown in the margin
+    pub fn beginning(&self, y: usize, line: &Line) -> isize {
+        let marker_length = (-line.beginning()) as usize;
+
+        return line.beginning() + self.hidden_before(y, marker_length) as isize;
+    }
+
+    // Maps a column in the buffer to the column it is rendered at
+    // Buffer columns count bytes while rendered ones count characters, which differ for `—` or `…`
+    pub fn display_x(&self, y: usize, line: &Line, x: isize) -> isize {
+        let index = std::cmp::max(0, x - line.beginning()) as usize;
+        let hidden = self.hidden(y);
+        let visible_characters = line
+            .text
+            .char_indices()
+            .take_while(|(offset, _)| *offset < index)
+            .filter(|(offset, _)| !hidden.iter().any(|range| range.contains(offset)))
+            .count();
+
+        return self.beginning(y, line) + visible_characters as isize;
+    }
+
+    // The line and the rendered columns the selection covers on it, for every line it touches
+    pub fn selection_columns(
+        &self,
+        lines: &[Line],
+        smallest: &EditorPosition,
+        largest: &EditorPosition,
+    ) -> Vec<(usize, isize, isize)> {
+        return (smallest.y..largest.y + 1)
+            .map(|y| {
+                let line = &lines[y];
+                let start = if y == smallest.y {
+                    self.display_x(y, line, smallest.x)
+                } else {
+                    // Headline markers shown in the margin are selected as well
+                    self.beginning(y, line)
+                };
+                let end = if y == largest.y {
+                    self.display_x(y, line, largest.x)
+                } else {
+                    // Lines that aren't wrapped can be longer than the wrap width
+                    std::cmp::max(
+                        CHARACTER_COUNT_PER_LINE as isize,
+                        self.display_x(y, line, line.end()),
+                    )
+                };
+
+                (y, start, end)
+            })
+            .collect();
+    }
+
+    // Maps a byte index into the text of the line to the index in the rendered text
+    pub fn display_offset(&self, y: usize, index: usize) -> usize {
+        return index - self.hidden_before(y, index);
+    }
+
+    fn hidden(&self, y: usize) -> &[Range<usize>] {
+        return self
+            .hidden
+            .get(y)
+            .map(|ranges| ranges.as_slice())
+            .unwrap_or(&[]);
+    }
+
+    fn hidden_before(&self, y: usize, index: usize) -> usize {
+        return self
+            .hidden(y)
+            .iter()
+            .map(|range| std::cmp::min(range.start, index)..std::cmp::min(range.end, index))
+            .map(|range| range.len())
+            .sum();
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MarkerRun {
+    character: char,
+    range: Range<usize>,
+    can_open: bool,
+    can_close: bool,
+}
+
+fn hidden_ranges(line: &Line) -> Vec<Range<usize>> {
+    // The markers are replaced by a drawn line
+    if matches!(line.kind, LineType::HorizontalRule) {
+        return vec![0..line.text.len()];
+    }
+
+    let mut ranges = vec![];
+
+    if let LineType::HeadlineStart(level) = line.kind {
+        ranges.push(0..level + 1);
+    }
+
+    // Only hide markers that form a pair so lone characters are left alone
+    let runs = marker_runs(&line.text);
+    let mut used = vec![false; runs.len()];
+
+    for (index, opening) in runs.iter().enumerate() {
+        if used[index] || !opening.can_open {
+            continue;
+        }
+
+        let closing = runs
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .find(|(other, run)| {
+                !used[*other]
+                    && run.can_close
+                    && run.character == opening.character
+                    && run.range.len() == opening.range.len()
+            });
+
+        if let Some((other, closing)) = closing {
+            used[index] = true;
+            used[other] = true;
+
+            ranges.push(opening.range.clone());
+            ranges.push(closing.range.clone());
+        }
+    }
+
+    return ranges;
+}
+
+fn marker_runs(text: &str) -> Vec<MarkerRun> {
+    let characters: Vec<(usize, char)> = text.char_indices().collect();
+    let mut runs = vec![];
+    let mut index = 0;
+
+    while index < characters.len() {
+        let (start, character) = characters[index];
+
+        if !MARKERS.contains(&character) {
+            index += 1;
+            continue;
+        }
+
+        let mut end = index;
+
+        while end < characters.len() && characters[end].1 == character {
+            end += 1;
+        }
+
+        let previous = if index > 0 {
+            Some(characters[index - 1].1)
+        } else {
+            None
+        };
+        let next = characters.get(end).map(|(_, character)| *character);
+        let end_offset = characters
+            .get(end)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(text.len());
+
+        runs.push(MarkerRun {
+            character,
+            range: start..end_offset,
+            can_open: next.map_or(false, |character| !character.is_whitespace()),
+            can_close: previous.map_or(false, |character| !character.is_whitespace()),
+        });
+
+        index = end;
+    }
+
+    return runs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::Content;
+
+    #[test]
+    fn selections_ending_inside_a_headline() {
+        let lines = Content::new("Some paragraph text\nmore\n## Heading here\n".into()).lines();
+        let smallest = EditorPosition::new(0, 5);
+        let largest = EditorPosition::new(2, 4);
+
+        // With the markers hidden the headline text starts at the left edge
+        assert_eq!(
+            DisplayMap::new(&lines, 0..0).selection_columns(&lines, &smallest, &largest),
+            vec![(0, 5, 60), (1, 0, 60), (2, 0, 4)]
+        );
+        // The cursor on the headline reveals its markers in the margin
+        assert_eq!(
+            DisplayMap::new(&lines, 2..3).selection_columns(&lines, &smallest, &largest),
+            vec![(0, 5, 60), (1, 0, 60), (2, -3, 4)]
+        );
+    }
+
+    #[test]
+    fn headlines_in_the_middle_of_a_selection_are_selected_from_their_markers() {
+        let lines = Content::new("Some paragraph\n## Heading\nend\n".into()).lines();
+        let smallest = EditorPosition::new(0, 5);
+        let largest = EditorPosition::new(2, 2);
+
+        assert_eq!(
+            DisplayMap::new(&lines, 0..3).selection_columns(&lines, &smallest, &largest),
+            vec![(0, 5, 60), (1, -3, 60), (2, 0, 2)]
+        );
+        assert_eq!(
+            DisplayMap::new(&lines, 0..0).selection_columns(&lines, &smallest, &largest)[1],
+            (1, 0, 60)
+        );
+    }
+}
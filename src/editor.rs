@@ -1,862 +1,3287 @@
-use std::{env, ops::Range, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use gpui::{
-    div, fill, point, prelude::*, px, rgb, size, AppContext, Bounds, ClipboardItem, Corner,
-    Corners, ElementInputHandler, FocusHandle, FocusableView, Font, FontWeight, Hsla, PaintQuad,
-    PathPromptOptions, Pixels, Point, PromptLevel, ScrollHandle, ShapedLine, SharedString, Style,
-    TextRun, View, ViewContext, ViewInputHandler,
+    div, fill, point, prelude::*, px, rgb, size, transparent_black, AppContext, Bounds,
+    ClipboardItem, ContentMask, Corner, Corners, ElementInputHandler, EventEmitter, FocusHandle,
+    FocusableView, Font, FontWeight, Hsla, Image, ImageFormat, MouseButton, MouseDownEvent,
+    MouseMoveEvent, MouseUpEvent, PaintQuad, PathPromptOptions, Pixels, Point, PromptLevel,
+    ScrollHandle, ScrollWheelEvent, ShapedLine, SharedString, Style, Task, TextRun, UnderlineStyle,
+    View, ViewContext, ViewInputHandler,
 };
 
 use crate::{
-    buffer::Buffer,
-    content::{Content, Line, LineType},
+    buffer::{
+        describe_extensions, has_allowed_extension, Buffer, EditorSnapshot, OpenError, SaveError,
+        DEFAULT_FILE_EXTENSIONS, DEFAULT_TAB_WIDTH,
+    },
+    content::{self, remove_formatting, Content, Line, LineType, Statistics, FORMATTING_MARKERS},
     cursor::{self, Cursor, EditLocation, EditorPosition, Selection, SelectionDirection},
-    text::WrappedText,
-    Backspace, Copy, Cut, Enter, MoveBeginningOfFile, MoveBeginningOfLine, MoveBeginningOfWord,
-    MoveDown, MoveEndOfFile, MoveEndOfLine, MoveEndOfWord, MoveLeft, MoveRight, MoveUp, NewFile,
-    OpenFile, Paste, RemoveSelection, Save, SaveAs, SelectAll, SelectBeginningOfFile,
-    SelectBeginningOfLine, SelectBeginningOfWord, SelectDown, SelectEndOfFile, SelectEndOfLine,
-    SelectEndOfWord, SelectLeft, SelectRight, SelectUp, SetBuffer, COLOR_BLUE_DARK,
-    COLOR_BLUE_LIGHT, COLOR_BLUE_MEDIUM, COLOR_GRAY_300, COLOR_GRAY_400, COLOR_GRAY_700,
-    COLOR_GRAY_800, COLOR_PINK,
+    diff::{self, LineChange},
+    display_map::DisplayMap,
+    links,
+    settings::{Metrics, Palette, Settings},
+    spellcheck::Spellchecker,
+    storage,
+    templates::{self, Template},
+    text::WordBoundaries,
+    theme::Theme,
+    vim::{self, VimCommand, VimMode},
+    Backspace, ClearFormatting, CloseTab, Copy, CopyAsPlainText, CopyWithHardWraps, Cut,
+    DecreaseHeadlineLevel, Enter, FormatDocument, IncreaseHeadlineLevel, Indent, InsertCharacter,
+    InsertCodeBlock, InsertHorizontalRule, InsertLink, InsertSpecialCharacter, JumpBack,
+    JumpForward, JumpToMatchingBracket, LowerCase, Mode, MoveBeginningOfFile, MoveBeginningOfLine,
+    MoveBeginningOfWord, MoveDown, MoveEndOfFile, MoveEndOfLine, MoveEndOfWord, MoveLeft,
+    MoveRight, MoveUp, NewFile, NewFromTemplate, NextMisspelling, NextTab, OpenFile, OpenPath,
+    Outdent, Paste, PreviousMisspelling, PreviousTab, RemoveSelection, Revert, Save, SaveAs,
+    SelectAll, SelectBeginningOfFile, SelectBeginningOfLine, SelectBeginningOfWord, SelectDown,
+    SelectEndOfFile, SelectEndOfLine, SelectEndOfWord, SelectLeft, SelectParagraph, SelectRight,
+    SelectToMatchingBracket, SelectUp, SelectWord, SetBuffer, SetWordGoal, ShowRuler, TitleCase,
+    ToggleAutoCapitalize, ToggleLineNumbers, ToggleList, ToggleOrderedList, ToggleReadOnly,
+    ToggleSentenceFocus, ToggleSmartPunctuation, ToggleTask, ToggleTrimTrailingWhitespace,
+    ToggleVimMode, ToggleWordWrap, UpperCase,
 };
 
-const CHARACTER_WIDTH: Pixels = px(10.24);
-const LINE_HEIGHT: Pixels = px(28.);
 pub const CHARACTER_COUNT_PER_LINE: usize = 60;
-const EDITOR_HORIZONTAL_MARGIN: Pixels = px(71.68); // 7 (6 headline markers + 1 space) * CHARACTERWIDTH;
 const EDITOR_VERTICAL_MARGIN: Pixels = px(32.);
-const EDITOR_BASE_WIDTH: Pixels = px(512.);
-pub const CONTAINER_WIDTH: Pixels = px(757.76); // Base width + Margin * 2
+const INDENTATION: &str = "  ";
+const CURSOR_POSITIONS_FILE: &str = "cursor_positions.json";
+const WORD_GOALS_FILE: &str = "word_goals.json";
+const SAVE_DIRECTORY_FILE: &str = "save_directory.json";
+// The goals offered when setting one, in words
+const WORD_GOALS: [usize; 5] = [250, 500, 1000, 2000, 5000];
+// The characters offered by Insert Special Character, since they're hard to type
+const SPECIAL_CHARACTERS: [(&str, char); 8] = [
+    ("Non-Breaking Space", '\u{a0}'),
+    ("Em Dash —", '—'),
+    ("En Dash –", '–'),
+    ("Ellipsis …", '…'),
+    ("Left Arrow ←", '←'),
+    ("Right Arrow →", '→'),
+    ("Up Arrow ↑", '↑'),
+    ("Down Arrow ↓", '↓'),
+];
+const MAX_HEADLINE_LEVEL: isize = 6;
+// Room for four digits and a space before the margin
+const LINE_NUMBER_COLUMNS: usize = 5;
+// How many jumps are remembered before the oldest ones are forgotten
+const MAX_JUMPS: usize = 100;
+const SCROLL_ANIMATION_DURATION: Duration = Duration::from_millis(150);
+const SCROLL_FRAME: Duration = Duration::from_millis(8);
+const SCROLLBAR_WIDTH: Pixels = px(6.);
+const SCROLLBAR_MIN_THUMB_HEIGHT: Pixels = px(24.);
+// How long the cursor has to rest before other occurrences of the word are highlighted
+const OCCURRENCES_DELAY: Duration = Duration::from_millis(150);
+const CHANGE_BAR_WIDTH: Pixels = px(3.);
+// How long typing has to pause before the change bars are brought up to date
+const CHANGED_LINES_DELAY: Duration = Duration::from_millis(300);
+// How many characters are kept visible beside the cursor when scrolling sideways
+const HORIZONTAL_SCROLL_MARGIN: usize = 4;
+const STATUS_DURATION: Duration = Duration::from_secs(2);
+const SAVED_STATUS_DURATION: Duration = Duration::from_millis(1500);
+// The status fades out in steps at the end of its duration
+const STATUS_FADE_STEPS: u32 = 10;
+const STATUS_FADE_STEP: Duration = Duration::from_millis(30);
+// Pastes of at least this many bytes show that they're being inserted
+const LARGE_PASTE_LENGTH: usize = 256 * 1024;
+const ASSETS_DIRECTORY: &str = "assets";
+
+// Emitted after every change to the text, including switching to another file
+pub struct BufferChanged {
+    pub content: Content,
+}
 
 pub struct Editor {
     buffer: Buffer,
+    // Every open document in the order of the tab bar
+    // The active tab's buffer is moved into `buffer` while it's being edited
+    tabs: Vec<Tab>,
+    active_tab: usize,
     focus_handle: FocusHandle,
     scroll_handle: ScrollHandle,
+    smart_punctuation: bool,
+    auto_capitalize: bool,
+    trim_trailing_whitespace: bool,
+    word_boundaries: WordBoundaries,
+    tab_width: usize,
+    save_directory: Option<PathBuf>,
+    file_extensions: Vec<String>,
+    reduce_motion: bool,
+    read_only: bool,
+    mode: Mode,
+    zen: bool,
+    show_ruler: bool,
+    show_line_numbers: bool,
+    sentence_focus: bool,
+    // Off unless turned on, since most people expect typing to insert text
+    vim_mode: Option<VimMode>,
+    // The first key of a two key command, like the first `d` of `dd`
+    vim_pending: Option<char>,
+    wrap: bool,
+    // How far the text is scrolled to the side, which only happens when it isn't wrapped
+    horizontal_offset: Pixels,
+    scroll_animation: Option<ScrollAnimation>,
+    scrollbar_drag: Option<ScrollbarDrag>,
+    // Where the cursor was before big moves, navigated like a browser's back and forward
+    jumps: Vec<EditorPosition>,
+    jump_index: usize,
+    word_goal: Option<usize>,
+    // Other places the word at the cursor or the selected word occurs
+    occurrences: Vec<Range<EditorPosition>>,
+    occurrences_task: Option<Task<()>>,
+    // Logical lines that are new or different since the file was last saved
+    changed_lines: Vec<(usize, LineChange)>,
+    changed_lines_task: Option<Task<()>>,
+    spellchecker: Spellchecker,
+    status: Option<SharedString>,
+    status_opacity: f32,
+    status_task: Option<Task<()>>,
+    metrics: Metrics,
+    theme: Theme,
+}
+
+struct Tab {
+    buffer: Buffer,
+    scroll_offset: Point<Pixels>,
+    word_goal: Option<usize>,
+}
+
+impl Tab {
+    fn new() -> Tab {
+        return Tab {
+            buffer: Buffer::empty(),
+            scroll_offset: Point::default(),
+            word_goal: None,
+        };
+    }
+}
+
+struct ScrollAnimation {
+    target: Pixels,
+    _task: Task<()>,
+}
+
+// Where the mouse and the editor were when the scrollbar thumb was grabbed
+struct ScrollbarDrag {
+    mouse_y: Pixels,
+    scroll_offset: Pixels,
 }
 
 impl Editor {
-    pub fn new(buffer: Buffer, focus_handle: FocusHandle) -> Editor {
+    pub fn new(buffer: Buffer, focus_handle: FocusHandle, spellchecker: Spellchecker) -> Editor {
         return Editor {
             buffer,
+            tabs: vec![Tab::new()],
+            active_tab: 0,
             focus_handle,
             scroll_handle: ScrollHandle::new(),
+            smart_punctuation: false,
+            auto_capitalize: false,
+            trim_trailing_whitespace: false,
+            word_boundaries: WordBoundaries::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            save_directory: None,
+            file_extensions: DEFAULT_FILE_EXTENSIONS.map(String::from).to_vec(),
+            reduce_motion: false,
+            read_only: false,
+            mode: Mode::default(),
+            zen: false,
+            show_ruler: false,
+            show_line_numbers: false,
+            sentence_focus: false,
+            vim_mode: None,
+            vim_pending: None,
+            wrap: true,
+            horizontal_offset: Pixels::ZERO,
+            scroll_animation: None,
+            scrollbar_drag: None,
+            jumps: Vec::new(),
+            jump_index: 0,
+            word_goal: None,
+            occurrences: vec![],
+            occurrences_task: None,
+            changed_lines: vec![],
+            changed_lines_task: None,
+            spellchecker,
+            status: None,
+            status_opacity: 1.,
+            status_task: None,
+            metrics: Metrics::default(),
+            theme: Theme::light(&Palette::default()),
         };
     }
 
-    fn set_buffer(&mut self, action: &SetBuffer, context: &mut ViewContext<Self>) {
-        let buffer = Buffer::from_path(action.path.clone());
+    pub fn apply_settings(&mut self, settings: &Settings, context: &mut ViewContext<Self>) {
+        self.metrics = settings.metrics();
+        self.word_boundaries = settings.word_boundaries;
+        self.buffer.set_word_boundaries(self.word_boundaries);
+        self.tab_width = settings.tab_width;
+        self.buffer.set_tab_width(self.tab_width);
+        self.save_directory = settings.save_directory.clone();
+        self.file_extensions = settings.file_extensions.clone();
+        self.reduce_motion = settings.reduce_motion();
 
-        self.buffer = buffer;
+        context.notify();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme, context: &mut ViewContext<Self>) {
+        self.theme = theme;
 
         context.notify();
     }
 
-    fn new_file(&mut self, _: &NewFile, context: &mut ViewContext<Self>) {
-        let buffer = Buffer::empty();
+    pub fn set_mode(&mut self, mode: Mode, context: &mut ViewContext<Self>) {
+        self.mode = mode;
 
-        self.buffer = buffer;
+        context.notify();
+    }
+
+    pub fn set_zen(&mut self, zen: bool, context: &mut ViewContext<Self>) {
+        self.zen = zen;
 
         context.notify();
     }
 
-    fn open_file(&mut self, _: &OpenFile, context: &mut ViewContext<Self>) {
-        if !self.buffer.pristine() {
-            self.prompt_to_save_before_open(context);
-        } else {
-            self.prompt_to_open_file(context);
-        }
+    pub fn read_only(&self) -> bool {
+        return self.read_only;
     }
 
-    fn prompt_to_save_before_open(&self, context: &mut ViewContext<Self>) {
-        let prompt = context.prompt(
-            PromptLevel::Warning,
-            "Do you want to save the file?",
-            None,
-            &["Save", "Don't save", "Cancel"],
-        );
+    // How many words and characters are selected, or nothing without a selection
+    pub fn selection_counts(&self) -> Option<(usize, usize)> {
+        let EditLocation::Selection(selection) = self.buffer.edit_location() else {
+            return None;
+        };
+        let text = self.read_range(selection.smallest()..selection.largest());
 
-        // Create a clone of self that we can move into the closure
-        let editor = context.view().clone();
+        return Some((content::word_count(&text), content::character_count(&text)));
+    }
 
-        context
-            .spawn(move |_, mut context| async move {
-                let answer = prompt.await.ok();
+    // Whether the open file can only be read, as opposed to the editor being locked
+    pub fn file_read_only(&self) -> bool {
+        return self.buffer.is_read_only();
+    }
 
-                match answer {
-                    // Save and then open another file
-                    Some(0) => {
-                        // First save the file
-                        context
-                            .update_view(&editor, |editor, cx| {
-                                // If the buffer has no file, prompt for a save location first
-                                if !editor.buffer.has_file() {
-                                    // Use our callback-based method to save first, then open a new file
-                                    editor.prompt_to_save_file_with_callback(
-                                        cx,
-                                        Some(|editor: &mut Editor, cx: &mut ViewContext<Self>| {
-                                            editor.prompt_to_open_file(cx)
-                                        }),
-                                    );
-                                } else {
-                                    // Otherwise save directly to the existing file
-                                    match editor.buffer.save() {
-                                        Ok(_) => {
-                                            cx.notify();
-                                            // Then open a new file
-                                            editor.prompt_to_open_file(cx);
-                                        }
-                                        Err(err) => {
-                                            // This shouldn't happen since we checked has_file(), but handle it anyway
-                                            let error_message =
-                                                format!("Failed to save file: {:?}", err);
-                                            let error_prompt = cx.prompt(
-                                                PromptLevel::Critical,
-                                                &error_message,
-                                                None,
-                                                &["OK"],
-                                            );
-
-                                            cx.foreground_executor()
-                                                .spawn(async move {
-                                                    error_prompt.await.ok();
-                                                })
-                                                .detach();
-                                        }
-                                    }
-                                }
-                            })
-                            .ok();
-                    }
-                    // Don't save but open another file
-                    Some(1) => {
-                        context
-                            .update_view(&editor, |editor, cx| {
-                                editor.prompt_to_open_file(cx);
-                            })
-                            .ok();
-                    }
-                    // Cancel
-                    _ => {}
-                };
-            })
-            .detach();
+    fn toggle_read_only(&mut self, _: &ToggleReadOnly, context: &mut ViewContext<Self>) {
+        self.read_only = !self.read_only;
+        self.buffer.set_locked(self.read_only);
+
+        context.notify();
     }
 
-    fn prompt_to_save_file(&self, context: &mut ViewContext<Self>) {
-        self.prompt_to_save_file_with_callback::<fn(&mut Editor, &mut ViewContext<Self>)>(
-            context, None,
-        );
+    fn toggle_smart_punctuation(
+        &mut self,
+        _: &ToggleSmartPunctuation,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.smart_punctuation = !self.smart_punctuation;
+
+        context.notify();
     }
 
-    fn prompt_to_save_file_with_callback<F>(
-        &self,
+    fn toggle_auto_capitalize(
+        &mut self,
+        _: &ToggleAutoCapitalize,
         context: &mut ViewContext<Self>,
-        callback: Option<F>,
-    ) where
-        F: FnOnce(&mut Editor, &mut ViewContext<Self>) + Send + 'static,
-    {
-        let path = get_documents_folder_path();
-        let result = context.prompt_for_new_path(path.as_path());
+    ) {
+        self.auto_capitalize = !self.auto_capitalize;
 
-        let editor = context.view().clone();
+        context.notify();
+    }
 
-        context
-            .spawn(move |_, mut context| async move {
-                let result = result.await;
-                // Handle the result of the prompt
-                if let Ok(Ok(result)) = result {
-                    if let Some(path) = result {
-                        // Check if the file has a .md extension
-                        if path.extension().is_none() || path.extension().unwrap() != "md" {
-                            // If not, show an error
-                            let error_prompt = context.prompt(
-                                PromptLevel::Critical,
-                                "File must have a .md extension",
-                                None,
-                                &["OK"],
-                            );
-
-                            context
-                                .foreground_executor()
-                                .spawn(async move {
-                                    error_prompt.await.ok();
-                                })
-                                .detach();
-                        } else {
-                            context
-                                .update_view(&editor, |editor, cx| {
-                                    // Use the set_file method to associate a file with the buffer and save
-                                    match editor.buffer.set_file(path.clone()) {
-                                        Ok(_) => {
-                                            // File was created and saved successfully
-                                            cx.notify();
+    fn toggle_trim_trailing_whitespace(
+        &mut self,
+        _: &ToggleTrimTrailingWhitespace,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.trim_trailing_whitespace = !self.trim_trailing_whitespace;
+        self.buffer
+            .set_trim_trailing_whitespace(self.trim_trailing_whitespace);
 
-                                            // Execute callback if provided
-                                            if let Some(callback) = callback {
-                                                callback(editor, cx);
-                                            }
-                                        }
-                                        Err(error) => {
-                                            // Show an error if file creation failed
-                                            let error_message =
-                                                format!("Failed to save file: {:?}", error);
-                                            let error_prompt = cx.prompt(
-                                                PromptLevel::Critical,
-                                                &error_message,
-                                                None,
-                                                &["OK"],
-                                            );
-
-                                            cx.foreground_executor()
-                                                .spawn(async move {
-                                                    error_prompt.await.ok();
-                                                })
-                                                .detach();
-                                        }
-                                    }
-                                })
-                                .ok();
-                        }
-                    }
-                }
-            })
-            .detach();
+        context.notify();
     }
-    fn prompt_to_open_file(&self, context: &mut ViewContext<Self>) {
-        let paths = context.prompt_for_paths(PathPromptOptions {
-            files: true,
-            directories: false,
-            multiple: false,
-        });
 
-        context
-            .spawn(|_, mut context| async move {
-                let result = paths.await.unwrap().unwrap();
+    fn toggle_ruler(&mut self, _: &ShowRuler, context: &mut ViewContext<Self>) {
+        self.show_ruler = !self.show_ruler;
 
-                if let Some(paths) = result {
-                    let path = paths.first().unwrap();
+        context.notify();
+    }
 
-                    if path.extension().is_none() || path.extension().unwrap() != "md" {
-                        let prompt = context.prompt(
-                            PromptLevel::Critical,
-                            "Can only open .md files",
-                            None,
-                            &["OK"],
-                        );
+    fn toggle_line_numbers(&mut self, _: &ToggleLineNumbers, context: &mut ViewContext<Self>) {
+        self.show_line_numbers = !self.show_line_numbers;
 
-                        context
-                            .foreground_executor()
-                            .spawn(async {
-                                prompt.await.ok();
-                            })
-                            .detach();
-                    } else {
-                        // Only dispatch the SetBuffer action if the file has a .md extension
-                        context
-                            .update(|context| {
-                                context.dispatch_action(Box::new(SetBuffer::new(path.clone())));
-                            })
-                            .unwrap();
-                    }
-                }
-            })
-            .detach();
+        context.notify();
     }
 
-    fn move_left(&mut self, _: &MoveLeft, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
-        }
+    fn toggle_sentence_focus(&mut self, _: &ToggleSentenceFocus, context: &mut ViewContext<Self>) {
+        self.sentence_focus = !self.sentence_focus;
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.smallest(),
-        };
+        context.notify();
+    }
 
-        let position = self.left_position(starting_point.clone());
+    fn toggle_vim_mode(&mut self, _: &ToggleVimMode, context: &mut ViewContext<Self>) {
+        self.vim_mode = match self.vim_mode {
+            Some(_) => None,
+            None => Some(VimMode::Normal),
+        };
+        self.vim_pending = None;
 
-        self.move_to(position.clone(), position.x, context);
+        context.notify();
     }
 
-    fn move_right(&mut self, _: &MoveRight, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    // Keys typed in Normal mode run commands built from the regular movement and editing
+    fn vim_key(&mut self, key: char, context: &mut ViewContext<Self>) {
+        let (command, pending) = vim::command(self.vim_pending, key);
+
+        self.vim_pending = pending;
+
+        let Some(command) = command else {
             return;
+        };
+
+        match command {
+            VimCommand::Left => self.move_left(&MoveLeft, context),
+            VimCommand::Down => self.move_down(&MoveDown, context),
+            VimCommand::Up => self.move_up(&MoveUp, context),
+            VimCommand::Right => self.move_right(&MoveRight, context),
+            VimCommand::Insert => self.vim_mode = Some(VimMode::Insert),
+            VimCommand::Append => {
+                self.move_right(&MoveRight, context);
+                self.vim_mode = Some(VimMode::Insert);
+            }
+            VimCommand::DeleteCharacter => {
+                if let EditLocation::Cursor(cursor) = self.buffer.edit_location() {
+                    let line = self.buffer.line(cursor.position.y);
+
+                    if cursor.position.x < line.end() {
+                        let end = self.buffer.right_position(cursor.position.clone());
+
+                        self.replace_range(cursor.position.clone()..end, "".into(), context);
+                        self.move_to(cursor.position.clone(), cursor.position.x, context);
+                    }
+                }
+            }
+            VimCommand::DeleteLine => {
+                if let EditLocation::Selection(selection) = self.buffer.edit_location() {
+                    self.move_to(selection.end, selection.preferred_x, context);
+                }
+
+                // Like in vim, the deleted line can be pasted again
+                self.cut(&Cut, context);
+            }
         }
 
-        let starting_point = match self.buffer.edit_location() {
+        context.notify();
+    }
+
+    fn toggle_word_wrap(&mut self, _: &ToggleWordWrap, context: &mut ViewContext<Self>) {
+        self.wrap = !self.wrap;
+        self.buffer.set_wrap(self.wrap);
+        self.horizontal_offset = Pixels::ZERO;
+
+        let position = match self.buffer.edit_location() {
             EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.largest(),
+            EditLocation::Selection(selection) => selection.end,
         };
 
-        let position = self.right_position(starting_point.clone());
+        self.ensure_in_viewport(position, context);
+        self.buffer_changed(context);
+    }
 
-        self.move_to(position.clone(), position.x, context);
+    // Where column 0 of the text is, after the line numbers and the margin
+    fn text_left(&self, bounds: Bounds<Pixels>) -> Pixels {
+        return bounds.left() + self.gutter_width() + self.metrics.horizontal_margin
+            - self.horizontal_offset;
     }
 
-    fn move_up(&mut self, _: &MoveUp, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    // The line numbers sit left of the margin, so the text moves over to make room
+    fn gutter_width(&self) -> Pixels {
+        if !self.show_line_numbers {
+            return Pixels::ZERO;
+        }
+
+        return self.metrics.character_width * LINE_NUMBER_COLUMNS as f32;
+    }
+
+    fn set_buffer(&mut self, action: &SetBuffer, context: &mut ViewContext<Self>) {
+        if let Some(index) = self.tab_with_path(&action.path) {
+            self.activate_tab(index, context);
+
             return;
         }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.smallest(),
-        };
-        let preferred_x = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.preferred_x,
-            EditLocation::Selection(selection) => selection.smallest().x,
-        };
+        match Buffer::from_path(action.path.clone()) {
+            Ok(buffer) => self.open_buffer(buffer, context),
+            Err(OpenError::InvalidUtf8) => self.prompt_to_open_lossy(action.path.clone(), context),
+            Err(OpenError::IoError(error)) => {
+                let error_message = format!("Failed to open file: {:?}", error);
+                let error_prompt =
+                    context.prompt(PromptLevel::Critical, &error_message, None, &["OK"]);
+
+                context
+                    .foreground_executor()
+                    .spawn(async move {
+                        error_prompt.await.ok();
+                    })
+                    .detach();
+            }
+        }
+    }
 
-        let position = self.up_position(starting_point.clone(), preferred_x);
+    // Files in another encoding, like latin-1, can still be opened with the invalid bytes replaced
+    fn prompt_to_open_lossy(&self, path: PathBuf, context: &mut ViewContext<Self>) {
+        let prompt = context.prompt(
+            PromptLevel::Critical,
+            "File is not valid UTF-8",
+            Some("Opening it anyway replaces the characters that can't be read."),
+            &["Open Anyway", "Cancel"],
+        );
 
-        let preferred_x = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.preferred_x,
-            EditLocation::Selection(_selection) => position.x,
-        };
+        let editor = context.view().clone();
 
-        self.move_to(position, preferred_x, context);
+        context
+            .spawn(move |_, mut context| async move {
+                if prompt.await.ok() != Some(0) {
+                    return;
+                }
+
+                context
+                    .update_view(&editor, |editor, cx| match Buffer::from_path_lossy(path) {
+                        Ok(buffer) => editor.open_buffer(buffer, cx),
+                        Err(error) => {
+                            let error_message = format!("Failed to open file: {:?}", error);
+                            let error_prompt =
+                                cx.prompt(PromptLevel::Critical, &error_message, None, &["OK"]);
+
+                            cx.foreground_executor()
+                                .spawn(async move {
+                                    error_prompt.await.ok();
+                                })
+                                .detach();
+                        }
+                    })
+                    .ok();
+            })
+            .detach();
     }
 
-    fn move_down(&mut self, _: &MoveDown, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn open_buffer(&mut self, mut buffer: Buffer, context: &mut ViewContext<Self>) {
+        self.configure_buffer(&mut buffer);
+        self.remember_cursor_position();
+        self.open_tab();
+        self.buffer = buffer;
+        self.clear_jumps();
+        self.restore_cursor_position(context);
+        self.restore_word_goal();
+
+        self.buffer_changed(context);
+    }
+
+    fn configure_buffer(&self, buffer: &mut Buffer) {
+        buffer.set_locked(self.read_only);
+        buffer.set_trim_trailing_whitespace(self.trim_trailing_whitespace);
+        buffer.set_word_boundaries(self.word_boundaries);
+        buffer.set_tab_width(self.tab_width);
+        buffer.set_wrap(self.wrap);
+    }
+
+    // Makes room for another document next to the open ones
+    // An untouched untitled document is replaced instead, so starting the app and opening a file
+    // doesn't leave an empty tab behind
+    fn open_tab(&mut self) {
+        if !self.buffer.has_file() && self.buffer.is_empty() && self.buffer.pristine() {
             return;
         }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.largest(),
-        };
-        let preferred_x = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.preferred_x,
-            EditLocation::Selection(selection) => selection.largest().x,
-        };
+        self.stash_active_tab();
+        self.tabs.push(Tab::new());
+        self.active_tab = self.tabs.len() - 1;
+        self.scroll_animation = None;
+        self.scroll_handle.set_offset(Point::default());
+    }
 
-        let position = self.down_position(starting_point.clone(), preferred_x);
-        let preferred_x = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.preferred_x,
-            EditLocation::Selection(_selection) => position.x,
-        };
+    // Moves the document being edited back into its tab
+    fn stash_active_tab(&mut self) {
+        let buffer = std::mem::replace(&mut self.buffer, Buffer::empty());
+        let tab = &mut self.tabs[self.active_tab];
 
-        self.move_to(position, preferred_x, context);
+        tab.buffer = buffer;
+        tab.scroll_offset = self.scroll_handle.offset();
+        tab.word_goal = self.word_goal;
     }
 
-    fn move_beginning_of_file(&mut self, _: &MoveBeginningOfFile, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
-        }
+    // Makes the document in the tab the one being edited, where it was left
+    fn load_tab(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        let tab = &mut self.tabs[index];
+        let mut buffer = std::mem::replace(&mut tab.buffer, Buffer::empty());
+        let scroll_offset = tab.scroll_offset;
 
-        let position = self.beginning_of_file_position();
+        self.word_goal = tab.word_goal;
+        self.configure_buffer(&mut buffer);
+        self.buffer = buffer;
+        self.active_tab = index;
+        self.scroll_animation = None;
+        self.scroll_handle.set_offset(scroll_offset);
+        self.clear_jumps();
 
-        self.move_to(position.clone(), position.x, context);
+        self.buffer_changed(context);
     }
 
-    fn move_end_of_file(&mut self, _: &MoveEndOfFile, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn activate_tab(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        if index == self.active_tab || index >= self.tabs.len() {
             return;
         }
 
-        let position = self.end_of_file_position();
+        self.stash_active_tab();
+        self.load_tab(index, context);
+    }
 
-        let line = self.buffer.line(position.y);
+    fn next_tab(&mut self, _: &NextTab, context: &mut ViewContext<Self>) {
+        self.activate_tab((self.active_tab + 1) % self.tabs.len(), context);
+    }
 
-        self.move_to(position, line.end(), context);
+    fn previous_tab(&mut self, _: &PreviousTab, context: &mut ViewContext<Self>) {
+        let index = (self.active_tab + self.tabs.len() - 1) % self.tabs.len();
+
+        self.activate_tab(index, context);
     }
 
-    fn move_beginning_of_line(&mut self, _: &MoveBeginningOfLine, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
+    fn tab_with_path(&self, path: &PathBuf) -> Option<usize> {
+        if self.buffer.path().as_ref() == Some(path) {
+            return Some(self.active_tab);
+        }
+
+        return self
+            .tabs
+            .iter()
+            .position(|tab| tab.buffer.path().as_ref() == Some(path));
+    }
+
+    fn close_tab(&mut self, _: &CloseTab, context: &mut ViewContext<Self>) {
+        if self.buffer.pristine() {
+            self.remove_active_tab(context);
+        } else {
+            self.prompt_to_save_before(context, |editor, context| {
+                editor.remove_active_tab(context)
+            });
+        }
+    }
+
+    fn close_tab_at(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        self.activate_tab(index, context);
+        self.close_tab(&CloseTab, context);
+    }
+
+    // Closing the last tab leaves an empty document, like starting the app does
+    fn remove_active_tab(&mut self, context: &mut ViewContext<Self>) {
+        self.remember_cursor_position();
+
+        if self.tabs.len() == 1 {
+            self.tabs[0] = Tab::new();
+            self.load_tab(0, context);
+
+            return;
+        }
+
+        self.tabs.remove(self.active_tab);
+        self.load_tab(std::cmp::min(self.active_tab, self.tabs.len() - 1), context);
+    }
+
+    // The names of the open documents, and whether each has unsaved changes
+    fn tab_titles(&self) -> Vec<(String, bool)> {
+        return self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let buffer = if index == self.active_tab {
+                    &self.buffer
+                } else {
+                    &tab.buffer
+                };
+                let name = buffer
+                    .path()
+                    .and_then(|path| {
+                        path.file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                    })
+                    .unwrap_or_else(|| "Untitled".to_string());
+
+                (name, !buffer.pristine())
+            })
+            .collect();
+    }
+
+    fn tab_bar(&self, context: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = self.theme;
+
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .right_0()
+            .flex()
+            .flex_row()
+            .gap_1()
+            .px_2()
+            .py_1()
+            .text_xs()
+            .bg(rgb(theme.background))
+            .border_b_1()
+            .border_color(rgb(theme.border))
+            .children(self.tab_titles().into_iter().enumerate().map(
+                |(index, (name, is_unsaved))| {
+                    let is_active = index == self.active_tab;
+
+                    div()
+                        .flex()
+                        .flex_row()
+                        .gap_2()
+                        .px_2()
+                        .py(px(2.))
+                        .rounded(px(3.))
+                        .text_color(rgb(theme.text_muted))
+                        .when(is_active, |this| {
+                            this.bg(rgb(theme.surface)).text_color(rgb(theme.text))
+                        })
+                        .when(!is_active, |this| {
+                            this.hover(|this| this.bg(rgb(theme.surface_hover)))
+                        })
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            context.listener(move |editor, _: &MouseDownEvent, context| {
+                                editor.activate_tab(index, context)
+                            }),
+                        )
+                        .child(name)
+                        .child(
+                            div()
+                                .text_color(rgb(theme.text_muted))
+                                .hover(|this| this.text_color(rgb(theme.text)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    context.listener(move |editor, _: &MouseDownEvent, context| {
+                                        // Otherwise the tab underneath would be activated too
+                                        context.stop_propagation();
+                                        editor.close_tab_at(index, context);
+                                    }),
+                                )
+                                .child(if is_unsaved { "•" } else { "×" }),
+                        )
+                },
+            ))
+    }
+
+    fn new_file(&mut self, _: &NewFile, context: &mut ViewContext<Self>) {
+        self.replace_with_new_buffer(Buffer::empty(), context);
+    }
+
+    fn new_from_template(&mut self, _: &NewFromTemplate, context: &mut ViewContext<Self>) {
+        let templates = templates::list(context.asset_source().as_ref());
+
+        if templates.is_empty() {
+            self.show_status("No templates", context);
+
+            return;
+        }
+
+        let mut answers: Vec<&str> = templates
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect();
+        answers.push("Cancel");
+
+        let prompt = context.prompt(PromptLevel::Info, "New from Template", None, &answers);
+
+        context
+            .spawn(|editor, mut context| async move {
+                let Some(template) = prompt
+                    .await
+                    .ok()
+                    .and_then(|answer| templates.get(answer).cloned())
+                else {
+                    return;
+                };
+
+                editor
+                    .update(&mut context, |editor, context| {
+                        editor.open_template(&template, context)
+                    })
+                    .ok();
+            })
+            .detach();
+    }
+
+    fn open_template(&mut self, template: &Template, context: &mut ViewContext<Self>) {
+        let (text, cursor_offset) = templates::expand(&template.text);
+        let mut buffer = Buffer::from_string(text);
+
+        if let Some(offset) = cursor_offset {
+            let position = buffer.offset_to_position(buffer.content().wrapped_offset(offset));
+
+            buffer.set_location(EditLocation::Cursor(Cursor::new(
+                position.y, position.x, position.x,
+            )));
+        }
+
+        self.replace_with_new_buffer(buffer, context);
+
+        if let EditLocation::Cursor(cursor) = self.buffer.edit_location() {
+            self.ensure_in_viewport(cursor.position, context);
+        }
+    }
+
+    // Opens a buffer that isn't associated with a file yet in a tab of its own
+    fn replace_with_new_buffer(&mut self, mut buffer: Buffer, context: &mut ViewContext<Self>) {
+        self.configure_buffer(&mut buffer);
+        self.remember_cursor_position();
+        self.open_tab();
+        self.buffer = buffer;
+        self.clear_jumps();
+        self.word_goal = None;
+
+        self.buffer_changed(context);
+    }
+
+    // Stores where the cursor is so it can be put back when the file is opened again
+    pub fn remember_cursor_position(&self) {
+        let Some(path) = self.buffer.path() else {
+            return;
+        };
+
+        let position = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+        let mut positions: HashMap<PathBuf, EditorPosition> =
+            storage::read(CURSOR_POSITIONS_FILE).unwrap_or_default();
+
+        positions.insert(path, position);
+
+        storage::write(CURSOR_POSITIONS_FILE, &positions).ok();
+    }
+
+    fn restore_cursor_position(&mut self, context: &mut ViewContext<Self>) {
+        let Some(path) = self.buffer.path() else {
+            return;
+        };
+
+        let positions: HashMap<PathBuf, EditorPosition> =
+            storage::read(CURSOR_POSITIONS_FILE).unwrap_or_default();
+
+        let Some(position) = positions.get(&path) else {
+            return;
+        };
+
+        self.buffer.set_location(EditLocation::Cursor(Cursor::new(
+            position.y, position.x, position.x,
+        )));
+        // The file might have gotten shorter since
+        self.buffer.clamp_edit_location();
+
+        if let EditLocation::Cursor(cursor) = self.buffer.edit_location() {
+            self.ensure_in_viewport(cursor.position, context);
+        }
+    }
+
+    pub fn word_goal(&self) -> Option<usize> {
+        return self.word_goal;
+    }
+
+    fn set_word_goal(&mut self, _: &SetWordGoal, context: &mut ViewContext<Self>) {
+        let labels: Vec<String> = WORD_GOALS
+            .iter()
+            .map(|goal| format!("{} Words", goal))
+            .collect();
+        let mut answers: Vec<&str> = labels.iter().map(String::as_str).collect();
+        answers.extend(["No Goal", "Cancel"]);
+
+        let prompt = context.prompt(
+            PromptLevel::Info,
+            "Set a word goal for this document",
+            Some("The progress towards it is shown in the sidebar."),
+            &answers,
+        );
+
+        context
+            .spawn(|editor, mut context| async move {
+                let goal = match prompt.await.ok() {
+                    Some(answer) if answer < WORD_GOALS.len() => Some(WORD_GOALS[answer]),
+                    Some(answer) if answer == WORD_GOALS.len() => None,
+                    _ => return,
+                };
+
+                editor
+                    .update(&mut context, |editor, context| {
+                        editor.word_goal = goal;
+                        editor.remember_word_goal();
+
+                        context.notify();
+                    })
+                    .ok();
+            })
+            .detach();
+    }
+
+    // Goals are kept per file so they're still there when the file is opened again
+    fn remember_word_goal(&self) {
+        let Some(path) = self.buffer.path() else {
+            return;
+        };
+
+        let mut goals: HashMap<PathBuf, usize> = storage::read(WORD_GOALS_FILE).unwrap_or_default();
+
+        match self.word_goal {
+            Some(goal) => goals.insert(path, goal),
+            None => goals.remove(&path),
+        };
+
+        storage::write(WORD_GOALS_FILE, &goals).ok();
+    }
+
+    fn restore_word_goal(&mut self) {
+        let goals: HashMap<PathBuf, usize> = storage::read(WORD_GOALS_FILE).unwrap_or_default();
+
+        self.word_goal = self
+            .buffer
+            .path()
+            .and_then(|path| goals.get(&path).copied());
+    }
+
+    // Files open in a tab of their own, so the open document doesn't have to be saved first
+    fn open_file(&mut self, _: &OpenFile, context: &mut ViewContext<Self>) {
+        self.prompt_to_open_file(context);
+    }
+
+    fn open_path(&mut self, action: &OpenPath, context: &mut ViewContext<Self>) {
+        self.set_buffer(&SetBuffer::new(action.path.clone()), context);
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        return self.buffer.path();
+    }
+
+    pub fn open(&mut self, path: PathBuf, context: &mut ViewContext<Self>) {
+        self.set_buffer(&SetBuffer::new(path), context);
+    }
+
+    // Unlike `pristine` this only looks at the document being edited, not the other tabs
+    pub fn is_saved(&self) -> bool {
+        return self.buffer.pristine();
+    }
+
+    // Keeps the document in step with the same file being edited somewhere else
+    pub fn mirror(&mut self, content: Content, is_saved: bool, context: &mut ViewContext<Self>) {
+        if self.buffer.mirror(content, is_saved) {
+            self.buffer_changed(context);
+        }
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        return self.buffer.content().statistics();
+    }
+
+    pub fn content(&self) -> Content {
+        return self.buffer.content();
+    }
+
+    pub fn snapshot(&self) -> EditorSnapshot {
+        return self.buffer.snapshot();
+    }
+
+    // The first line that can be seen, ignoring the margin above the text
+    pub fn top_visible_line(&self) -> usize {
+        let offset = self.scroll_offset().abs() - EDITOR_VERTICAL_MARGIN;
+
+        if offset <= Pixels::ZERO {
+            return 0;
+        }
+
+        return (offset / self.metrics.line_height).floor() as usize;
+    }
+
+    pub fn jump_to_line(&mut self, y: usize, context: &mut ViewContext<Self>) {
+        let y = std::cmp::min(y, self.buffer.lines().len() - 1);
+
+        self.record_jump();
+        self.move_to(EditorPosition::new(y, 0), 0, context);
+    }
+
+    // Called when the window gains or loses focus
+    pub fn window_activation_changed(&mut self, context: &mut ViewContext<Self>) {
+        if !context.is_window_active() || !self.buffer.has_changed_on_disk() {
+            return;
+        }
+
+        // Only ask once per change, even if the window is activated again before answering
+        self.buffer.acknowledge_disk_changes();
+
+        let prompt = context.prompt(
+            PromptLevel::Warning,
+            "The file has been changed by another program.",
+            Some("Do you want to reload it? Reloading discards your unsaved changes."),
+            &["Reload", "Keep"],
+        );
+
+        let editor = context.view().clone();
+
+        context
+            .spawn(move |_, mut context| async move {
+                if prompt.await.ok() != Some(0) {
+                    return;
+                }
+
+                context
+                    .update_view(&editor, |editor, cx| editor.reload(cx))
+                    .ok();
+            })
+            .detach();
+    }
+
+    pub fn pristine(&self) -> bool {
+        return self.unsaved_tab().is_none();
+    }
+
+    fn unsaved_tab(&self) -> Option<usize> {
+        if !self.buffer.pristine() {
+            return Some(self.active_tab);
+        }
+
+        return self.tabs.iter().position(|tab| !tab.buffer.pristine());
+    }
+
+    // Asks about each document with unsaved changes in turn, closing it once it's been dealt with
+    // Continues right away when there's nothing to save
+    pub fn prompt_to_save_before_closing<F>(
+        &mut self,
+        context: &mut ViewContext<Self>,
+        continuation: F,
+    ) where
+        F: FnOnce(&mut Editor, &mut ViewContext<Self>) + Send + 'static,
+    {
+        let Some(index) = self.unsaved_tab() else {
+            continuation(self, context);
+
+            return;
+        };
+
+        self.activate_tab(index, context);
+        self.prompt_to_save_before(context, move |editor, context| {
+            editor.remove_active_tab(context);
+            editor.prompt_to_save_before_closing(context, continuation);
+        });
+    }
+
+    // Asks whether to save unsaved changes before continuing
+    // The continuation runs after saving or when choosing not to save
+    fn prompt_to_save_before<F>(&self, context: &mut ViewContext<Self>, continuation: F)
+    where
+        F: FnOnce(&mut Editor, &mut ViewContext<Self>) + Send + 'static,
+    {
+        let prompt = context.prompt(
+            PromptLevel::Warning,
+            "Do you want to save the file?",
+            None,
+            &["Save", "Don't save", "Cancel"],
+        );
+
+        // Create a clone of self that we can move into the closure
+        let editor = context.view().clone();
+
+        context
+            .spawn(move |_, mut context| async move {
+                let answer = prompt.await.ok();
+
+                match answer {
+                    // Save and then continue
+                    Some(0) => {
+                        // First save the file
+                        context
+                            .update_view(&editor, |editor, cx| {
+                                // If the buffer has no file to write to, prompt for a save location first
+                                if !editor.buffer.has_file() || editor.buffer.is_read_only() {
+                                    // Use our callback-based method to save first, then continue
+                                    editor
+                                        .prompt_to_save_file_with_callback(cx, Some(continuation));
+                                } else {
+                                    // Otherwise save directly to the existing file
+                                    match editor.buffer.save() {
+                                        Ok(_) => {
+                                            editor.update_changed_lines();
+                                            cx.notify();
+                                            // Then continue
+                                            continuation(editor, cx);
+                                        }
+                                        Err(error) => show_save_error(error, cx),
+                                    }
+                                }
+                            })
+                            .ok();
+                    }
+                    // Don't save but continue
+                    Some(1) => {
+                        context
+                            .update_view(&editor, |editor, cx| {
+                                continuation(editor, cx);
+                            })
+                            .ok();
+                    }
+                    // Cancel
+                    _ => {}
+                };
+            })
+            .detach();
+    }
+
+    // Save As starts next to the file being saved, otherwise where the last file was saved,
+    // then in the folder from the settings and finally in the documents folder
+    fn save_directory(&self) -> PathBuf {
+        let file_directory = self
+            .buffer
+            .path()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        let last_directory: Option<PathBuf> = storage::read(SAVE_DIRECTORY_FILE).ok();
+
+        return [file_directory, last_directory, self.save_directory.clone()]
+            .into_iter()
+            .flatten()
+            .find(|directory| directory.is_dir())
+            .unwrap_or_else(storage::documents_directory);
+    }
+
+    fn prompt_to_save_file(&self, context: &mut ViewContext<Self>) {
+        self.prompt_to_save_file_with_callback::<fn(&mut Editor, &mut ViewContext<Self>)>(
+            context, None,
+        );
+    }
+
+    fn prompt_to_save_file_with_callback<F>(
+        &self,
+        context: &mut ViewContext<Self>,
+        callback: Option<F>,
+    ) where
+        F: FnOnce(&mut Editor, &mut ViewContext<Self>) + Send + 'static,
+    {
+        let directory = self.save_directory();
+        let result = context.prompt_for_new_path(&directory);
+
+        let editor = context.view().clone();
+        let extensions = self.file_extensions.clone();
+
+        context
+            .spawn(move |_, mut context| async move {
+                let result = result.await;
+                // Handle the result of the prompt
+                if let Ok(Ok(result)) = result {
+                    if let Some(path) = result {
+                        context
+                            .update_view(&editor, |editor, cx| {
+                                // Use the set_file method to associate a file with the buffer and save
+                                match editor.buffer.set_file(path.clone(), &extensions) {
+                                    Ok(_) => {
+                                        // File was created and saved successfully
+                                        remember_save_directory(&path);
+                                        editor.remember_word_goal();
+                                        editor.update_changed_lines();
+                                        editor.show_status_for("Saved", SAVED_STATUS_DURATION, cx);
+
+                                        // Execute callback if provided
+                                        if let Some(callback) = callback {
+                                            callback(editor, cx);
+                                        }
+                                    }
+                                    Err(error) => show_save_error(error, cx),
+                                }
+                            })
+                            .ok();
+                    }
+                }
+            })
+            .detach();
+    }
+    fn prompt_to_open_file(&self, context: &mut ViewContext<Self>) {
+        let paths = context.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+
+        let extensions = self.file_extensions.clone();
+
+        context
+            .spawn(|_, mut context| async move {
+                let result = paths.await.unwrap().unwrap();
+
+                if let Some(paths) = result {
+                    let path = paths.first().unwrap();
+
+                    if !has_allowed_extension(path, &extensions) {
+                        let message = format!("Can only open {}", describe_extensions(&extensions));
+                        let prompt = context.prompt(PromptLevel::Critical, &message, None, &["OK"]);
+
+                        context
+                            .foreground_executor()
+                            .spawn(async {
+                                prompt.await.ok();
+                            })
+                            .detach();
+                    } else {
+                        // Only dispatch the SetBuffer action if the file has an allowed extension
+                        context
+                            .update(|context| {
+                                context.dispatch_action(Box::new(SetBuffer::new(path.clone())));
+                            })
+                            .unwrap();
+                    }
+                }
+            })
+            .detach();
+    }
+
+    fn move_left(&mut self, _: &MoveLeft, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.smallest(),
+        };
+
+        let position = self.buffer.left_position(starting_point.clone());
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_right(&mut self, _: &MoveRight, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.largest(),
+        };
+
+        let position = self.buffer.right_position(starting_point.clone());
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_up(&mut self, _: &MoveUp, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.smallest(),
+        };
+        let preferred_x = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.preferred_x,
+            EditLocation::Selection(selection) => selection.preferred_x_at(&starting_point),
+        };
+
+        let position = self.buffer.up_position(starting_point.clone(), preferred_x);
+
+        self.move_to(position, preferred_x, context);
+    }
+
+    fn move_down(&mut self, _: &MoveDown, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.largest(),
+        };
+        let preferred_x = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.preferred_x,
+            EditLocation::Selection(selection) => selection.preferred_x_at(&starting_point),
+        };
+
+        let position = self
+            .buffer
+            .down_position(starting_point.clone(), preferred_x);
+
+        self.move_to(position, preferred_x, context);
+    }
+
+    fn move_beginning_of_file(&mut self, _: &MoveBeginningOfFile, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let position = self.buffer.beginning_of_file_position();
+
+        self.record_jump();
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_end_of_file(&mut self, _: &MoveEndOfFile, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let position = self.buffer.end_of_file_position();
+
+        let line = self.buffer.line(position.y);
+
+        self.record_jump();
+        self.move_to(position, line.end(), context);
+    }
+
+    fn move_beginning_of_line(&mut self, _: &MoveBeginningOfLine, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => match selection.direction() {
+                SelectionDirection::Backwards => selection.smallest(),
+                SelectionDirection::Forwards => selection.largest(),
+            },
+        };
+        let position = self.buffer.beginning_of_line_position(starting_point);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_end_of_line(&mut self, _: &MoveEndOfLine, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => match selection.direction() {
+                SelectionDirection::Backwards => selection.smallest(),
+                SelectionDirection::Forwards => selection.largest(),
+            },
+        };
+        let position = self.buffer.end_of_line_position(starting_point);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_beginning_of_word(&mut self, _: &MoveBeginningOfWord, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let starting_point = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.smallest(),
+        };
+        let position = self.buffer.beginning_of_word_position(starting_point);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_end_of_word(&mut self, _: &MoveEndOfWord, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
         }
 
         let starting_point = match self.buffer.edit_location() {
             EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => selection.smallest(),
-                SelectionDirection::Forwards => selection.largest(),
+            EditLocation::Selection(selection) => selection.largest(),
+        };
+        let position = self.buffer.end_of_word_position(starting_point);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn select_left(&mut self, _: &SelectLeft, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.left_position(end), context);
+    }
+
+    fn select_right(&mut self, _: &SelectRight, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.right_position(end), context);
+    }
+
+    fn select_up(&mut self, _: &SelectUp, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let (start, end, preferred_x) = self.vertical_selection();
+        let end = self.buffer.up_position(end, preferred_x);
+
+        self.select_vertically(start, end, preferred_x, context);
+    }
+
+    fn select_down(&mut self, _: &SelectDown, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let (start, end, preferred_x) = self.vertical_selection();
+        let end = self.buffer.down_position(end, preferred_x);
+
+        self.select_vertically(start, end, preferred_x, context);
+    }
+
+    // The anchor, the moving end and the column the moving end aims for
+    fn vertical_selection(&self) -> (EditorPosition, EditorPosition, isize) {
+        return match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                (cursor.position.clone(), cursor.position, cursor.preferred_x)
+            }
+            EditLocation::Selection(selection) => {
+                (selection.start, selection.end, selection.preferred_x)
+            }
+        };
+    }
+
+    // Like select, but keeps aiming for the same column
+    // so moving through wrapped lines of different lengths doesn't drift
+    fn select_vertically(
+        &mut self,
+        start: EditorPosition,
+        end: EditorPosition,
+        preferred_x: isize,
+        context: &mut ViewContext<Self>,
+    ) {
+        if start == end {
+            self.move_to(start, preferred_x, context);
+
+            return;
+        }
+
+        self.buffer.set_location(EditLocation::Selection(Selection {
+            start,
+            end: end.clone(),
+            preferred_x,
+        }));
+
+        self.ensure_in_viewport(end, context);
+
+        context.notify();
+    }
+
+    fn select_beginning_of_file(
+        &mut self,
+        _: &SelectBeginningOfFile,
+        context: &mut ViewContext<Self>,
+    ) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        self.select_to(self.buffer.beginning_of_file_position(), context);
+    }
+
+    fn select_end_of_file(&mut self, _: &SelectEndOfFile, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        self.select_to(self.buffer.end_of_file_position(), context);
+    }
+
+    fn select_beginning_of_line(
+        &mut self,
+        _: &SelectBeginningOfLine,
+        context: &mut ViewContext<Self>,
+    ) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.beginning_of_line_position(end), context);
+    }
+
+    fn select_end_of_line(&mut self, _: &SelectEndOfLine, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.end_of_line_position(end), context);
+    }
+
+    fn select_beginning_of_word(
+        &mut self,
+        _: &SelectBeginningOfWord,
+        context: &mut ViewContext<Self>,
+    ) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.beginning_of_word_position(end), context);
+    }
+
+    fn select_end_of_word(&mut self, _: &SelectEndOfWord, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let end = self.selection_end();
+
+        self.select_to(self.buffer.end_of_word_position(end), context);
+    }
+
+    // The end of the selection that moves when extending it, or the cursor
+    fn selection_end(&self) -> EditorPosition {
+        return match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+    }
+
+    fn select_all(&mut self, _: &SelectAll, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let start = self.buffer.beginning_of_file_position();
+        let end = self.buffer.end_of_file_position();
+
+        self.select(start, end, context);
+    }
+
+    fn select_paragraph(&mut self, _: &SelectParagraph, context: &mut ViewContext<Self>) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let lines = self.buffer.lines();
+        let is_blank = |y: usize| lines[y].text.trim().is_empty();
+
+        let (selected, mut first, mut last) = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                // There is no paragraph to select on a blank line
+                if is_blank(cursor.position.y) {
+                    return;
+                }
+
+                (None, cursor.position.y, cursor.position.y)
+            }
+            EditLocation::Selection(selection) => {
+                let smallest = selection.smallest();
+                let largest = selection.largest();
+
+                (
+                    Some(smallest.clone()..largest.clone()),
+                    smallest.y,
+                    largest.y,
+                )
+            }
+        };
+
+        while first > 0 && !is_blank(first - 1) {
+            first -= 1;
+        }
+
+        while last + 1 < lines.len() && !is_blank(last + 1) {
+            last += 1;
+        }
+
+        let start = EditorPosition::new(first, lines[first].beginning());
+        let mut end = EditorPosition::new(last, lines[last].end());
+
+        // When the paragraph is already selected, grow the selection to the next paragraph
+        if selected == Some(start.clone()..end.clone()) {
+            let mut next = last + 1;
+
+            while next < lines.len() && is_blank(next) {
+                next += 1;
+            }
+
+            if next < lines.len() {
+                last = next;
+
+                while last + 1 < lines.len() && !is_blank(last + 1) {
+                    last += 1;
+                }
+
+                end = EditorPosition::new(last, lines[last].end());
+            }
+        }
+
+        self.select(start, end.clone(), context);
+        self.ensure_in_viewport(end, context);
+    }
+
+    // Selects the word under the cursor, or the one right next to it on whitespace
+    // With a selection, the next place the selected text occurs is selected instead
+    fn select_word(&mut self, _: &SelectWord, context: &mut ViewContext<Self>) {
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => match self.word_at(cursor.position) {
+                Some(range) => range,
+                None => return,
+            },
+            EditLocation::Selection(selection) => {
+                let current = selection.smallest()..selection.largest();
+                let text = self.read_range(current.clone());
+                let matches = self.buffer.find_all(&text);
+
+                // Wraps around to the top after the last occurrence
+                let next = matches
+                    .iter()
+                    .find(|range| range.start >= current.end)
+                    .or(matches.first());
+
+                match next {
+                    Some(next) if *next != current => next.clone(),
+                    _ => return,
+                }
+            }
+        };
+
+        self.select(range.start, range.end.clone(), context);
+        self.ensure_in_viewport(range.end, context);
+    }
+
+    fn jump_to_matching_bracket(
+        &mut self,
+        _: &JumpToMatchingBracket,
+        context: &mut ViewContext<Self>,
+    ) {
+        let position = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+
+        let Some((_, matching)) = self.buffer.matching_bracket_positions(position) else {
+            return;
+        };
+
+        self.record_jump();
+        self.move_to(matching.clone(), matching.x, context);
+    }
+
+    // Selects both brackets and everything between them
+    fn select_to_matching_bracket(
+        &mut self,
+        _: &SelectToMatchingBracket,
+        context: &mut ViewContext<Self>,
+    ) {
+        let position = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+
+        let Some((bracket, matching)) = self.buffer.matching_bracket_positions(position) else {
+            return;
+        };
+
+        let (start, end) = if bracket < matching {
+            (bracket, matching)
+        } else {
+            (matching, bracket)
+        };
+        let end = self.buffer.right_position(end);
+
+        self.select(start, end.clone(), context);
+        self.ensure_in_viewport(end, context);
+    }
+
+    fn remove_selection(&mut self, _: &RemoveSelection, context: &mut ViewContext<Self>) {
+        // Like in other editors, the cursor stays where the selection was being extended
+        // and keeps aiming for the same column when moving up or down afterwards
+        // Leaving Insert mode comes first, so a selection made while typing stays until the next Escape
+        if self.vim_mode == Some(VimMode::Insert) {
+            self.vim_mode = Some(VimMode::Normal);
+
+            context.notify();
+        } else if self.vim_pending.take().is_some() {
+            context.notify();
+        } else if let EditLocation::Selection(selection) = self.buffer.edit_location() {
+            self.move_to(selection.end, selection.preferred_x, context);
+        } else {
+            // Without a selection there's nothing to do here, so a view further up can have it
+            context.propagate();
+        }
+    }
+
+    fn backspace(&mut self, _: &Backspace, context: &mut ViewContext<Self>) {
+        // Normal mode doesn't edit with the typing keys, it moves like vim does
+        if self.vim_mode == Some(VimMode::Normal) {
+            self.move_left(&MoveLeft, context);
+
+            return;
+        }
+
+        if self.read_only {
+            return;
+        }
+
+        match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                if cursor.position == self.buffer.beginning_of_file_position() {
+                    return;
+                }
+
+                // Remove an automatically inserted closing character along with its opening
+                if let (Some(previous), Some(next)) =
+                    self.buffer.characters_around(&cursor.position)
+                {
+                    if closing_character(previous) == Some(next) {
+                        let start = EditorPosition::new(cursor.position.y, cursor.position.x - 1);
+                        let end = EditorPosition::new(cursor.position.y, cursor.position.x + 1);
+
+                        self.replace_range(start.clone()..end, "".into(), context);
+                        self.move_to(start.clone(), start.x, context);
+
+                        return;
+                    }
+                }
+
+                let line = self.buffer.line(cursor.position.y);
+
+                if let LineType::Normal = line.kind {
+                    if self.remove_list_marker(cursor.position.clone(), context) {
+                        return;
+                    }
+                }
+
+                match (line.clone().kind, cursor.position.x) {
+                    (LineType::HeadlineStart(_level), 0) => {
+                        let position = EditorPosition::new(cursor.position.y, line.beginning());
+                        let range = position.clone()..cursor.position;
+
+                        self.replace_range(range, "".into(), context);
+                    }
+                    (LineType::Normal | LineType::Frontmatter, 0) => {
+                        let current_cursor_offset =
+                            self.buffer.position_to_offset(cursor.position.clone());
+                        let wrap_points = self.buffer.content().wrap_points();
+
+                        let mut position = self.buffer.left_position(cursor.position.clone());
+
+                        // Note: It's important we get the cursor offset here
+                        // Before subtracting the soft-wrap point
+                        let cursor_offset = self.buffer.position_to_offset(position.clone());
+
+                        if wrap_points.contains(&current_cursor_offset) {
+                            position = self.buffer.left_position(position);
+                        }
+
+                        let range = position.clone()..cursor.position;
+
+                        self.replace_range(range, "".into(), context);
+
+                        let new_cursor_position = self.buffer.offset_to_position(cursor_offset);
+
+                        self.move_to(new_cursor_position, position.x, context);
+                    }
+                    _ => {
+                        let position = self.buffer.left_position(cursor.position.clone());
+                        let offset = self
+                            .buffer
+                            .content()
+                            .original_offset(self.buffer.position_to_offset(position.clone()));
+                        let range = position.clone()..cursor.position;
+
+                        self.replace_range(range, "".into(), context);
+
+                        // Removing one of the headline markers moves where the text of the line starts
+                        // so the cursor is placed by offset rather than by column
+                        let position = self
+                            .buffer
+                            .offset_to_position(self.buffer.content().wrapped_offset(offset));
+
+                        self.move_to(position.clone(), position.x, context);
+                    }
+                };
+            }
+            EditLocation::Selection(selection) => {
+                let smallest = selection.smallest();
+                let range = smallest.clone()..selection.largest();
+
+                self.replace_range(range, "".into(), context);
+                let x = std::cmp::max(0, smallest.x);
+
+                self.move_to(EditorPosition::new(smallest.y, x), x, context);
+            }
+        }
+    }
+
+    fn enter(&mut self, _: &Enter, context: &mut ViewContext<Self>) {
+        if self.vim_mode == Some(VimMode::Normal) {
+            self.move_down(&MoveDown, context);
+
+            return;
+        }
+
+        if self.read_only {
+            return;
+        }
+
+        let location = self.buffer.edit_location();
+        let range = match location.clone() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+
+        let (first, _) = self.buffer.logical_line_bounds(range.start.y);
+        let line = self.buffer.line(first);
+        let (prefix, prefix_length) = match line.kind {
+            LineType::Normal => continuation(&line.text),
+            _ => (String::new(), 0),
+        };
+        let is_after_prefix = range.start.y > first || range.start.x >= prefix_length as isize;
+
+        // Enter on an empty list item or a blank indented line ends the list or indentation
+        if prefix_length > 0 && line.text[prefix_length..].trim().is_empty() {
+            if let EditLocation::Cursor(_) = location {
+                let start = EditorPosition::new(first, line.beginning());
+                let end = EditorPosition::new(first, line.end());
+
+                self.replace_range(start.clone()..end, "".into(), context);
+                self.move_to(start.clone(), start.x, context);
+
+                return;
+            }
+        }
+
+        if let LineType::HeadlineStart(_) = line.kind {
+            self.split_headline(range, context);
+
+            return;
+        }
+
+        if !is_after_prefix {
+            self.replace_range(range.clone(), "\n".into(), context);
+
+            let y = range.end.y + 1;
+            let line = self.buffer.line(y);
+            let position = EditorPosition::new(y, line.beginning());
+
+            self.move_to(position.clone(), position.x, context);
+
+            return;
+        }
+
+        // The new line continues the indentation and list of the current one
+        let text = format!("\n{}", prefix);
+
+        self.replace_range(range.clone(), text.clone(), context);
+
+        let position = self.buffer.position_after(range.start, text.len());
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn split_headline(&mut self, range: Range<EditorPosition>, context: &mut ViewContext<Self>) {
+        let position = self.buffer.split_headline(range);
+
+        self.buffer_changed(context);
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    // Backspace right after the marker of a list item outdents it, or turns it into a plain line
+    // when it isn't indented, instead of joining it with the previous line
+    // Returns whether the marker was handled
+    fn remove_list_marker(
+        &mut self,
+        position: EditorPosition,
+        context: &mut ViewContext<Self>,
+    ) -> bool {
+        let line = self.buffer.line(position.y);
+        let wrap_points = self.buffer.content().wrap_points();
+
+        if !self.buffer.starts_logical_line(position.y, &wrap_points) {
+            return false;
+        }
+
+        let indentation_length = line.text.len() - line.text.trim_start_matches([' ', '\t']).len();
+
+        if list_marker(&line.text[indentation_length..]).is_none() {
+            return false;
+        }
+
+        let (_, prefix_length) = continuation(&line.text);
+
+        if position.x != prefix_length as isize {
+            return false;
+        }
+
+        let length = if indentation_length > 0 {
+            std::cmp::min(INDENTATION.len(), indentation_length)
+        } else {
+            prefix_length
+        };
+        let start = EditorPosition::new(position.y, 0);
+        let end = EditorPosition::new(position.y, length as isize);
+        let x = position.x - length as isize;
+
+        self.replace_range(start..end, "".into(), context);
+        self.move_to(EditorPosition::new(position.y, x), x, context);
+
+        return true;
+    }
+
+    fn indent(&mut self, _: &Indent, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        match self.buffer.edit_location() {
+            EditLocation::Selection(selection) if selection.start.y != selection.end.y => {
+                self.indent_lines(selection, context);
+            }
+            _ => self.replace_text_in_range(None, INDENTATION, context),
+        }
+    }
+
+    fn outdent(&mut self, _: &Outdent, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let selection = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                Selection::new(cursor.position.clone(), cursor.position)
+            }
+            EditLocation::Selection(selection) => selection,
+        };
+
+        self.outdent_lines(selection, context);
+    }
+
+    fn indent_lines(&mut self, selection: Selection, context: &mut ViewContext<Self>) {
+        // Indenting can change the wrapping of the paragraph, so the selection is remembered
+        // in the original text and shifted by the indentation that is added before it
+        let mut start = self.buffer.original_offset(selection.start.clone());
+        let mut end = self.buffer.original_offset(selection.end.clone());
+
+        // Go from the last line to the first so earlier offsets stay valid
+        for y in self.selected_line_starts(&selection).into_iter().rev() {
+            let line = self.buffer.line(y);
+
+            // Indenting a headline would stop it from being a headline
+            if let LineType::HeadlineStart(_) = line.kind {
+                continue;
+            }
+
+            let position = EditorPosition::new(y, line.beginning());
+            let offset = self.buffer.original_offset(position.clone());
+
+            self.replace_range(position.clone()..position, INDENTATION.into(), context);
+
+            if start >= offset {
+                start += INDENTATION.len();
+            }
+
+            if end >= offset {
+                end += INDENTATION.len();
+            }
+        }
+
+        let start = self.buffer.original_position(start);
+        let end = self.buffer.original_position(end);
+
+        self.select(start, end, context);
+    }
+
+    fn outdent_lines(&mut self, selection: Selection, context: &mut ViewContext<Self>) {
+        let mut start = self.buffer.original_offset(selection.start.clone());
+        let mut end = self.buffer.original_offset(selection.end.clone());
+
+        for y in self.selected_line_starts(&selection).into_iter().rev() {
+            let line = self.buffer.line(y);
+            let removable = line
+                .text
+                .chars()
+                .take(INDENTATION.len())
+                .take_while(|character| *character == ' ')
+                .count();
+
+            if removable == 0 {
+                continue;
+            }
+
+            let range = EditorPosition::new(y, line.beginning())
+                ..EditorPosition::new(y, line.beginning() + removable as isize);
+            let offset = self.buffer.original_offset(range.start.clone());
+
+            self.replace_range(range, "".into(), context);
+
+            // Anything inside the removed spaces ends up at the start of the line
+            let shift = |position: usize| {
+                return offset + position.saturating_sub(offset + removable);
+            };
+
+            if start > offset {
+                start = shift(start);
+            }
+
+            if end > offset {
+                end = shift(end);
+            }
+        }
+
+        let start = self.buffer.original_position(start);
+        let end = self.buffer.original_position(end);
+
+        self.select(start, end, context);
+    }
+
+    fn increase_headline_level(
+        &mut self,
+        _: &IncreaseHeadlineLevel,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.change_headline_level(1, context);
+    }
+
+    fn decrease_headline_level(
+        &mut self,
+        _: &DecreaseHeadlineLevel,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.change_headline_level(-1, context);
+    }
+
+    // Adds or removes a `#` at the start of the current line
+    // Levels stop at 6 and decreasing a level 1 headline turns it back into a paragraph
+    fn change_headline_level(&mut self, change: isize, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let position = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+        let (y, _) = self.buffer.logical_line_bounds(position.y);
+        let line = self.buffer.line(y);
+        let level = match line.kind {
+            LineType::HeadlineStart(level) => level as isize,
+            _ => 0,
+        };
+        let new_level = (level + change).clamp(0, MAX_HEADLINE_LEVEL);
+
+        if new_level == level {
+            return;
+        }
+
+        // Column 0 is the start of the text after the markers for both headlines and paragraphs
+        // so remember how far into the text the cursor is and put it back there afterwards
+        let content = self.buffer.content();
+        let text_start = EditorPosition::new(y, 0);
+        let distance = content
+            .original_offset(self.buffer.position_to_offset(position))
+            .saturating_sub(
+                content.original_offset(self.buffer.position_to_offset(text_start.clone())),
+            );
+
+        let markers = EditorPosition::new(y, line.beginning())..text_start.clone();
+
+        self.replace_range(markers, headline_markers(new_level as usize), context);
+
+        let position = self.buffer.position_after(text_start, distance);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn upper_case(&mut self, _: &UpperCase, context: &mut ViewContext<Self>) {
+        self.transform_case(|text| text.to_uppercase(), context);
+    }
+
+    fn lower_case(&mut self, _: &LowerCase, context: &mut ViewContext<Self>) {
+        self.transform_case(|text| text.to_lowercase(), context);
+    }
+
+    fn title_case(&mut self, _: &TitleCase, context: &mut ViewContext<Self>) {
+        self.transform_case(title_case, context);
+    }
+
+    // Transforms the selection, or the word under the cursor when nothing is selected
+    fn transform_case(&mut self, transform: fn(&str) -> String, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let edit_location = self.buffer.edit_location();
+        let range = match &edit_location {
+            EditLocation::Cursor(cursor) => match self.buffer.word_range(cursor.position.clone()) {
+                Some(range) => range,
+                None => return,
             },
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+
+        let text = self.read_range(range.clone());
+        let transformed = transform(&text);
+
+        if transformed == text {
+            return;
+        }
+
+        self.replace_range(range.clone(), transformed.clone(), context);
+
+        match edit_location {
+            EditLocation::Cursor(cursor) => {
+                // Stay at the same spot in the word
+                let distance = std::cmp::min(
+                    (cursor.position.x - range.start.x) as usize,
+                    transformed.len(),
+                );
+                let position = self.buffer.position_after(range.start, distance);
+
+                self.move_to(position.clone(), position.x, context);
+            }
+            EditLocation::Selection(_) => {
+                let end = self
+                    .buffer
+                    .position_after(range.start.clone(), transformed.len());
+
+                self.select(range.start, end, context);
+            }
+        }
+    }
+
+    // Tidies the spacing of the whole document
+    // The cursor stays on the same line and column, as far as they still exist
+    fn format_document(&mut self, _: &FormatDocument, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        if self.buffer.format() {
+            self.buffer_changed(context);
+        }
+    }
+
+    // Removes the emphasis and code markers in and around the selection
+    fn clear_formatting(&mut self, _: &ClearFormatting, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let EditLocation::Selection(selection) = self.buffer.edit_location() else {
+            return;
+        };
+
+        let content = self.buffer.content();
+        let text = content.to_string();
+        let bytes = text.as_bytes();
+        let mut start =
+            content.original_offset(self.buffer.position_to_offset(selection.smallest()));
+        let mut end = content.original_offset(self.buffer.position_to_offset(selection.largest()));
+
+        // Include the markers that enclose the whole selection, like `**` around a bold word
+        while start > 0
+            && end < bytes.len()
+            && bytes[start - 1] == bytes[end]
+            && FORMATTING_MARKERS.contains(&(bytes[end] as char))
+        {
+            start -= 1;
+            end += 1;
+        }
+
+        let unformatted = remove_formatting(&text[start..end]);
+
+        if unformatted == text[start..end] {
+            return;
+        }
+
+        let to_position = |offset: usize| {
+            self.buffer
+                .offset_to_position(content.wrapped_offset(offset))
+        };
+        let range = to_position(start)..to_position(end);
+
+        self.replace_range(range.clone(), unformatted.clone(), context);
+
+        let end = self
+            .buffer
+            .position_after(range.start.clone(), unformatted.len());
+
+        self.select(range.start, end, context);
+    }
+
+    // Checks or unchecks the task on the current line, leaving the cursor where it is
+    fn toggle_task(&mut self, _: &ToggleTask, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let location = self.buffer.edit_location();
+        let position = match location.clone() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
+        };
+        let (y, _) = self.buffer.logical_line_bounds(position.y);
+        let Some(task) = self.buffer.line(y).task else {
+            return;
+        };
+
+        let x = task.checkbox as isize + 1;
+        let state = if task.is_done { " " } else { "x" };
+
+        self.replace_range(
+            EditorPosition::new(y, x)..EditorPosition::new(y, x + 1),
+            state.into(),
+            context,
+        );
+        // The length of the line doesn't change, so the old location is still valid
+        self.buffer.set_location(location);
+    }
+
+    fn toggle_list(&mut self, _: &ToggleList, context: &mut ViewContext<Self>) {
+        self.toggle_list_markers(ListKind::Unordered, context);
+    }
+
+    fn toggle_ordered_list(&mut self, _: &ToggleOrderedList, context: &mut ViewContext<Self>) {
+        self.toggle_list_markers(ListKind::Ordered, context);
+    }
+
+    // Turns the selected lines into a list
+    // or back into regular lines when they already are that kind of list
+    fn toggle_list_markers(&mut self, kind: ListKind, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let selection = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                Selection::new(cursor.position.clone(), cursor.position)
+            }
+            EditLocation::Selection(selection) => selection,
+        };
+        let lines: Vec<(usize, Line)> = self
+            .selected_line_starts(&selection)
+            .into_iter()
+            .map(|y| (y, self.buffer.line(y)))
+            .filter(|(_, line)| {
+                !matches!(line.kind, LineType::HeadlineStart(_)) && !line.text.trim().is_empty()
+            })
+            .collect();
+
+        let (Some((first_y, _)), Some((last_y, _))) = (lines.first(), lines.last()) else {
+            return;
+        };
+
+        let is_list = lines.iter().all(|(_, line)| {
+            list_marker(line.text.trim_start()).map_or(false, |(marker, _)| marker == kind)
+        });
+
+        // Remember the block in the original text so the selection can cover it afterwards
+        let content = self.buffer.content();
+        let start = EditorPosition::new(*first_y, 0);
+        let (_, last_y) = self.buffer.logical_line_bounds(*last_y);
+        let end = EditorPosition::new(last_y, self.buffer.line(last_y).end());
+        let length = content.original_offset(self.buffer.position_to_offset(end))
+            - content.original_offset(self.buffer.position_to_offset(start.clone()));
+        let mut length_change: isize = 0;
+
+        // Go from the last line to the first so earlier offsets stay valid
+        for (index, (y, line)) in lines.iter().enumerate().rev() {
+            let indentation = line.text.len() - line.text.trim_start().len();
+            let existing = list_marker(line.text.trim_start()).map_or(0, |(_, length)| length);
+            let marker = match (is_list, kind) {
+                (true, _) => String::new(),
+                (false, ListKind::Unordered) => "- ".to_string(),
+                (false, ListKind::Ordered) => format!("{}. ", index + 1),
+            };
+            let range = EditorPosition::new(*y, indentation as isize)
+                ..EditorPosition::new(*y, (indentation + existing) as isize);
+
+            length_change += marker.len() as isize - existing as isize;
+
+            self.replace_range(range, marker, context);
+        }
+
+        let end = self
+            .buffer
+            .position_after(start.clone(), (length as isize + length_change) as usize);
+
+        self.select(start, end, context);
+    }
+
+    // Inserts the matching closing character for brackets and markdown markers
+    // Returns whether the insertion has been handled
+    fn auto_close(
+        &mut self,
+        range: Range<EditorPosition>,
+        text: &str,
+        context: &mut ViewContext<Self>,
+    ) -> bool {
+        let mut characters = text.chars();
+        let character = match (characters.next(), characters.next()) {
+            (Some(character), None) => character,
+            _ => return false,
+        };
+
+        // Wrap the selection instead of replacing it
+        if range.start != range.end {
+            let closing = match closing_character(character) {
+                Some(closing) => closing,
+                None => return false,
+            };
+            let selected = self.read_range(range.clone());
+
+            self.replace_range(
+                range.clone(),
+                format!("{}{}{}", character, selected, closing),
+                context,
+            );
+
+            let start = self
+                .buffer
+                .position_after(range.start.clone(), character.len_utf8());
+            let end = self
+                .buffer
+                .position_after(range.start, character.len_utf8() + selected.len());
+
+            self.select(start, end, context);
+
+            return true;
+        }
+
+        let (previous, next) = self.buffer.characters_around(&range.start);
+
+        // Type over the closing character rather than inserting a duplicate
+        if next == Some(character) && is_closing_character(character) {
+            let position = self.buffer.right_position(range.start);
+
+            self.move_to(position.clone(), position.x, context);
+
+            return true;
+        }
+
+        let closing = match closing_character(character) {
+            Some(closing) => closing,
+            None => return false,
+        };
+
+        let is_followed_by_text = match next {
+            Some(next) => !next.is_whitespace() && !is_closing_character(next),
+            None => false,
+        };
+
+        if is_followed_by_text {
+            return false;
+        }
+
+        // Markers that open and close with the same character are also used on their own
+        // E.g. inside words, for list items and for code fences
+        if closing == character {
+            let line = self.buffer.line(range.start.y);
+            let index = (range.start.x - line.beginning()) as usize;
+            let is_start_of_line = line.text.get(..index).unwrap_or("").trim().is_empty();
+            let is_inside_word = match previous {
+                Some(previous) => previous.is_alphanumeric() || previous == character,
+                None => false,
+            };
+
+            if is_inside_word || (is_start_of_line && character != '`') {
+                return false;
+            }
+        }
+
+        self.replace_range(range.clone(), format!("{}{}", character, closing), context);
+
+        let position = self
+            .buffer
+            .position_after(range.start, character.len_utf8());
+
+        self.move_to(position.clone(), position.x, context);
+
+        return true;
+    }
+
+    // Turns a typed character into its typographic counterpart
+    // E.g. `--` becomes an em dash and straight quotes become curly quotes
+    fn substitute_punctuation(
+        &self,
+        range: Range<EditorPosition>,
+        text: &str,
+    ) -> (Range<EditorPosition>, String) {
+        if range.start != range.end || self.is_inside_code(&range.start) {
+            return (range, text.to_string());
+        }
+
+        let (previous, _) = self.buffer.characters_around(&range.start);
+        let is_opening = match previous {
+            Some(character) => character.is_whitespace() || "([{\u{2014}".contains(character),
+            None => true,
+        };
+
+        match (text, previous) {
+            ("-", Some('-')) => {
+                let start = EditorPosition::new(range.start.y, range.start.x - 1);
+
+                (start..range.end, "\u{2014}".into())
+            }
+            ("\"", _) if is_opening => (range, "\u{201C}".into()),
+            ("\"", _) => (range, "\u{201D}".into()),
+            ("'", _) if is_opening => (range, "\u{2018}".into()),
+            ("'", _) => (range, "\u{2019}".into()),
+            _ => (range, text.to_string()),
+        }
+    }
+
+    // Capitalizes a lowercase letter typed at the start of a sentence
+    fn capitalize_sentence_start(&self, range: &Range<EditorPosition>, text: String) -> String {
+        let mut characters = text.chars();
+        let (Some(character), None) = (characters.next(), characters.next()) else {
+            return text;
+        };
+
+        if !character.is_lowercase()
+            || self.is_inside_code(&range.start)
+            || !self.starts_sentence(&range.start)
+        {
+            return text;
+        }
+
+        return character.to_uppercase().collect();
+    }
+
+    // At the start of the document, or after a `.`, `?` or `!` followed by whitespace
+    fn starts_sentence(&self, position: &EditorPosition) -> bool {
+        let lines = self.buffer.lines();
+        let Some(line) = lines.get(position.y) else {
+            return true;
+        };
+        let index = (position.x - line.beginning()) as usize;
+        let before = line.text.get(..index).unwrap_or("");
+        let previous_lines = lines[..position.y]
+            .iter()
+            .rev()
+            .map(|line| line.text.as_str());
+        let mut has_whitespace = false;
+
+        for text in std::iter::once(before).chain(previous_lines) {
+            for character in text.chars().rev() {
+                if !character.is_whitespace() {
+                    return has_whitespace && ".?!".contains(character);
+                }
+
+                has_whitespace = true;
+            }
+
+            // Going back to the previous line crosses a newline or a soft-wrap
+            has_whitespace = true;
+        }
+
+        return true;
+    }
+
+    fn is_inside_code(&self, position: &EditorPosition) -> bool {
+        let lines = self.buffer.lines();
+        let fences = lines
+            .iter()
+            .take(position.y)
+            .filter(|line| line.text.trim_start().starts_with("```"))
+            .count();
+
+        if fences % 2 == 1 {
+            return true;
+        }
+
+        let line = self.buffer.line(position.y);
+        let index = (position.x - line.beginning()) as usize;
+        let backticks = line.text.get(..index).unwrap_or("").matches('`').count();
+
+        return backticks % 2 == 1;
+    }
+
+    // The lines touched by a selection that begin a logical line
+    // Soft-wrapped continuations are left alone
+    fn selected_line_starts(&self, selection: &Selection) -> Vec<usize> {
+        let smallest = selection.smallest();
+        let largest = selection.largest();
+        let wrap_points = self.buffer.content().wrap_points();
+
+        (smallest.y..largest.y + 1)
+            .filter(|y| {
+                let line = self.buffer.line(*y);
+
+                // A selection ending at the very beginning of a line doesn't include it
+                if *y != smallest.y && *y == largest.y && largest.x == line.beginning() {
+                    return false;
+                }
+
+                self.buffer.starts_logical_line(*y, &wrap_points)
+            })
+            .collect()
+    }
+
+    fn save(&mut self, _: &Save, context: &mut ViewContext<Self>) {
+        // Check if the buffer has an associated file that can be written to
+        if !self.buffer.has_file() || self.buffer.is_read_only() {
+            // If there's no such file, prompt for a save location
+            self.prompt_to_save_file(context);
+        } else {
+            // If there is a file, save directly
+            match self.buffer.save() {
+                Ok(_) => {
+                    self.remember_cursor_position();
+                    self.update_changed_lines();
+                    self.show_status_for("Saved", SAVED_STATUS_DURATION, context);
+                }
+                Err(error) => show_save_error(error, context),
+            }
+        }
+    }
+
+    fn save_as(&mut self, _: &SaveAs, context: &mut ViewContext<Self>) {
+        self.prompt_to_save_file(context);
+    }
+
+    // Goes back to the file as it is on disk, asking first when that throws away changes
+    fn revert(&mut self, _: &Revert, context: &mut ViewContext<Self>) {
+        let Some(path) = self.buffer.path() else {
+            self.show_status(
+                "Nothing to revert to, the document isn't saved yet",
+                context,
+            );
+            return;
         };
-        let position = self.beginning_of_line_position(starting_point);
 
-        self.move_to(position.clone(), position.x, context);
+        if !path.exists() {
+            let prompt = context.prompt(
+                PromptLevel::Critical,
+                "The file no longer exists.",
+                Some(
+                    "It was moved or deleted by another program, so there's nothing to revert to.",
+                ),
+                &["OK"],
+            );
+
+            context
+                .foreground_executor()
+                .spawn(async move {
+                    prompt.await.ok();
+                })
+                .detach();
+
+            return;
+        }
+
+        if self.buffer.pristine() {
+            self.reload(context);
+            return;
+        }
+
+        let prompt = context.prompt(
+            PromptLevel::Warning,
+            "Do you want to revert to the saved version?",
+            Some("Your unsaved changes will be lost."),
+            &["Revert", "Cancel"],
+        );
+
+        context
+            .spawn(|editor, mut context| async move {
+                if prompt.await.ok() != Some(0) {
+                    return;
+                }
+
+                editor
+                    .update(&mut context, |editor, context| editor.reload(context))
+                    .ok();
+            })
+            .detach();
     }
 
-    fn move_end_of_line(&mut self, _: &MoveEndOfLine, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
+    // Reads the file again, discarding the unsaved changes
+    fn reload(&mut self, context: &mut ViewContext<Self>) {
+        if let Err(err) = self.buffer.reload() {
+            let error_message = format!("Failed to reload file: {:?}", err);
+            let error_prompt = context.prompt(PromptLevel::Critical, &error_message, None, &["OK"]);
+
+            context
+                .foreground_executor()
+                .spawn(async move {
+                    error_prompt.await.ok();
+                })
+                .detach();
         }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => selection.smallest(),
-                SelectionDirection::Forwards => selection.largest(),
-            },
-        };
-        let position = self.end_of_line_position(starting_point);
-
-        self.move_to(position.clone(), position.x, context);
+        self.buffer_changed(context);
     }
 
-    fn move_beginning_of_word(&mut self, _: &MoveBeginningOfWord, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
+    fn copy(&mut self, _: &Copy, context: &mut ViewContext<Self>) {
+        match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => {
+                let text = self.current_line_text(cursor.position.y);
+
+                self.write_to_clipboard(text, context);
+            }
+            EditLocation::Selection(selection) => {
+                let range = selection.smallest()..selection.largest();
+
+                let text = self.read_range(range.clone());
+
+                self.write_to_clipboard(text, context);
+            }
         }
+    }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.smallest(),
+    // Without a selection the whole document is copied, since a single line is rarely what's wanted
+    fn copy_as_plain_text(&mut self, _: &CopyAsPlainText, context: &mut ViewContext<Self>) {
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(_) => {
+                self.buffer.beginning_of_file_position()..self.buffer.end_of_file_position()
+            }
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
         };
-        let position = self.beginning_of_word_position(starting_point);
+        let start = self.buffer.position_to_offset(range.start);
+        let end = self.buffer.position_to_offset(range.end);
+        let text = self.buffer.content().plain_text(start..end);
 
-        self.move_to(position.clone(), position.x, context);
+        self.write_to_clipboard(text, context);
     }
 
-    fn move_end_of_word(&mut self, _: &MoveEndOfWord, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
-        }
-
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.largest(),
+    // Copies the text as it's displayed, with a newline wherever a line is soft-wrapped
+    fn copy_with_hard_wraps(&mut self, _: &CopyWithHardWraps, context: &mut ViewContext<Self>) {
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(_) => {
+                self.buffer.beginning_of_file_position()..self.buffer.end_of_file_position()
+            }
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
         };
-        let position = self.end_of_word_position(starting_point);
+        let start = self.buffer.position_to_offset(range.start);
+        let end = self.buffer.position_to_offset(range.end);
+        let text = self.buffer.content().read_wrapped_range(start..end);
 
-        self.move_to(position.clone(), position.x, context);
+        self.write_to_clipboard(text, context);
     }
 
-    fn select_left(&mut self, _: &SelectLeft, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn cut(&mut self, _: &Cut, context: &mut ViewContext<Self>) {
+        if self.read_only {
             return;
         }
 
         match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => self.select(
-                cursor.position.clone(),
-                self.left_position(cursor.position),
-                context,
-            ),
+            EditLocation::Cursor(cursor) => {
+                let text = self.current_line_text(cursor.position.y);
+                let (first, last) = self.buffer.logical_line_bounds(cursor.position.y);
+                let last_line = self.buffer.line(last);
+                let end_of_line = EditorPosition::new(last, last_line.end());
+
+                // The final line has no newline of its own to remove
+                // so the newline before it is removed instead
+                let range = if last + 2 < self.buffer.lines().len() {
+                    let next_line = self.buffer.line(last + 1);
+
+                    self.buffer
+                        .beginning_of_line_position(EditorPosition::new(first, 0))
+                        ..EditorPosition::new(last + 1, next_line.beginning())
+                } else if first > 0 {
+                    self.buffer
+                        .end_of_line_position(EditorPosition::new(first - 1, 0))
+                        ..end_of_line
+                } else {
+                    self.buffer
+                        .beginning_of_line_position(EditorPosition::new(first, 0))
+                        ..end_of_line
+                };
+
+                self.write_to_clipboard(text, context);
+                self.replace_range(range, "".into(), context);
+
+                let y = std::cmp::min(first, self.buffer.lines().len() - 1);
+                let position = self
+                    .buffer
+                    .beginning_of_line_position(EditorPosition::new(y, 0));
+
+                self.move_to(position.clone(), position.x, context);
+            }
             EditLocation::Selection(selection) => {
-                self.select(selection.start, self.left_position(selection.end), context)
+                let range = selection.smallest()..selection.largest();
+
+                let text = self.read_range(range.clone());
+
+                self.write_to_clipboard(text, context);
+                self.replace_range(range, "".into(), context);
+                self.move_to(selection.smallest(), selection.smallest().x, context);
             }
         }
     }
 
-    fn select_right(&mut self, _: &SelectRight, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    // Remembers which document the text was copied from, so pasting can fix its links
+    fn write_to_clipboard(&self, text: String, context: &mut ViewContext<Self>) {
+        let item = match self.buffer.path() {
+            Some(path) => ClipboardItem::new_string_with_json_metadata(text, path),
+            None => ClipboardItem::new_string(text),
+        };
+
+        context.write_to_clipboard(item);
+    }
+
+    // The unwrapped text of the logical line including its newline
+    fn current_line_text(&self, y: usize) -> String {
+        let (first, last) = self.buffer.logical_line_bounds(y);
+        let start = self
+            .buffer
+            .beginning_of_line_position(EditorPosition::new(first, 0));
+        let end = self
+            .buffer
+            .end_of_line_position(EditorPosition::new(last, 0));
+
+        return self.read_range(start..end) + "\n";
+    }
+
+    fn paste(&mut self, _: &Paste, context: &mut ViewContext<Self>) {
+        if self.read_only {
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => self.select(
-                cursor.position.clone(),
-                self.right_position(cursor.position),
-                context,
-            ),
-            EditLocation::Selection(selection) => {
-                self.select(selection.start, self.right_position(selection.end), context)
+        let clipboard_item = context
+            .read_from_clipboard()
+            .unwrap_or(ClipboardItem::new_string("".into()));
+
+        let image = clipboard_item
+            .entries()
+            .iter()
+            .find_map(|entry| match entry {
+                gpui::ClipboardEntry::Image(image) => Some(image.clone()),
+                _ => None,
+            });
+
+        if let Some(image) = image {
+            // Images are saved next to the document, so it needs to be somewhere first
+            if self.buffer.has_file() {
+                self.paste_image(image, context);
+            } else {
+                self.prompt_to_save_file_with_callback(
+                    context,
+                    Some(
+                        move |editor: &mut Editor, context: &mut ViewContext<Editor>| {
+                            editor.paste_image(image, context)
+                        },
+                    ),
+                );
+            }
+
+            return;
+        }
+
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+        let mut content = String::new();
+        let mut source: Option<PathBuf> = None;
+
+        for entry in clipboard_item.entries() {
+            if let gpui::ClipboardEntry::String(clipboard_string) = entry {
+                content.push_str(clipboard_string.text());
+                source = source.or_else(|| clipboard_string.metadata_json());
             }
         }
+
+        let mut content = self.buffer.expand_tabs(&normalize_line_endings(content));
+
+        // Relative links keep pointing at the same files when pasted into a document elsewhere
+        if let (Some(source), Some(destination)) = (source, self.buffer.path()) {
+            content = links::rebase(&content, &source, &destination);
+        }
+
+        // Pasting a whole paragraph at the end of a line
+        // shouldn't leave an unexpected blank line behind
+        let (_, next) = self.buffer.characters_around(&range.end);
+
+        if next.is_none() && content.ends_with('\n') {
+            content.pop();
+        }
+
+        // Re-wrapping a lot of text takes a moment, so the status is drawn for a frame first
+        if content.len() >= LARGE_PASTE_LENGTH {
+            self.show_status("Pasting…", context);
+
+            context.on_next_frame(move |editor, context| {
+                editor.insert_pasted_text(range, content, context);
+                editor.status = None;
+                editor.status_task = None;
+            });
+
+            return;
+        }
+
+        self.insert_pasted_text(range, content, context);
     }
 
-    fn select_up(&mut self, _: &SelectUp, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn insert_pasted_text(
+        &mut self,
+        range: Range<EditorPosition>,
+        text: String,
+        context: &mut ViewContext<Self>,
+    ) {
+        // Worked out before the edit, since the wrapping of the line it starts on can change
+        let start = self.buffer.original_offset(range.start.clone());
+
+        self.replace_range(range, text.clone(), context);
+
+        let position = self.buffer.original_position(start + text.len());
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    // Saves the image into the assets folder next to the document and links to it
+    fn paste_image(&mut self, image: Image, context: &mut ViewContext<Self>) {
+        let Some(directory) = self.buffer.path().and_then(|path| {
+            path.parent()
+                .map(|directory| directory.join(ASSETS_DIRECTORY))
+        }) else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis());
+        let name = format!("pasted-{}.{}", timestamp, image_extension(image.format));
+        let result = fs::create_dir_all(&directory)
+            .and_then(|_| fs::write(directory.join(&name), &image.bytes));
+
+        if result.is_err() {
+            self.show_status("Couldn't save the pasted image", context);
+
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => {
-                self.select_to(
-                    self.up_position(cursor.position.clone(), cursor.position.x),
-                    context,
-                );
-            }
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => self.select_to(
-                    self.up_position(selection.end.clone(), selection.start.x),
-                    context,
-                ),
-                SelectionDirection::Forwards => self.select(
-                    selection.start,
-                    self.up_position(selection.end.clone(), selection.end.x),
-                    context,
-                ),
-            },
+        let markdown = format!("![]({}/{})", ASSETS_DIRECTORY, name);
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
         };
+
+        self.replace_range(range.clone(), markdown.clone(), context);
+
+        let position = self.buffer.position_after(range.start, markdown.len());
+
+        self.move_to(position.clone(), position.x, context);
     }
 
-    fn select_down(&mut self, _: &SelectDown, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn insert_link(&mut self, _: &InsertLink, context: &mut ViewContext<Self>) {
+        if self.read_only {
             return;
         }
 
-        match self.buffer.edit_location() {
+        let url = context
+            .read_from_clipboard()
+            .and_then(|item| item.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| is_url(text))
+            .unwrap_or_default();
+
+        let (range, replacement, cursor_offset) = match self.buffer.edit_location() {
             EditLocation::Cursor(cursor) => {
-                self.select_to(
-                    self.down_position(cursor.position.clone(), cursor.position.x),
-                    context,
-                );
+                // Put the cursor between the brackets so the link text can be typed
+                (
+                    cursor.position.clone()..cursor.position,
+                    format!("[]({})", url),
+                    1,
+                )
+            }
+            EditLocation::Selection(selection) => {
+                let range = selection.smallest()..selection.largest();
+                let replacement = format!("[{}]({})", self.read_range(range.clone()), url);
+                // Put the cursor just before the closing parenthesis
+                let cursor_offset = replacement.len() - 1;
+
+                (range, replacement, cursor_offset)
             }
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => self.select(
-                    selection.start,
-                    self.down_position(selection.end.clone(), selection.end.x),
-                    context,
-                ),
-                SelectionDirection::Forwards => self.select_to(
-                    self.down_position(selection.end.clone(), selection.start.x),
-                    context,
-                ),
-            },
         };
+
+        self.replace_range(range.clone(), replacement, context);
+
+        let position = self.buffer.position_after(range.start, cursor_offset);
+
+        self.move_to(position.clone(), position.x, context);
     }
 
-    fn select_beginning_of_file(
+    fn insert_special_character(
         &mut self,
-        _: &SelectBeginningOfFile,
+        _: &InsertSpecialCharacter,
         context: &mut ViewContext<Self>,
     ) {
-        if self.buffer.is_empty() {
+        if self.read_only {
             return;
         }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.start,
-        };
+        let mut answers: Vec<&str> = SPECIAL_CHARACTERS.iter().map(|(name, _)| *name).collect();
+        answers.push("Cancel");
+
+        let prompt = context.prompt(
+            PromptLevel::Info,
+            "Insert Special Character",
+            None,
+            &answers,
+        );
+
+        context
+            .spawn(|editor, mut context| async move {
+                let Some((_, character)) = prompt
+                    .await
+                    .ok()
+                    .and_then(|answer| SPECIAL_CHARACTERS.get(answer))
+                else {
+                    return;
+                };
 
-        self.select(starting_point, self.beginning_of_file_position(), context);
+                editor
+                    .update(&mut context, |editor, context| {
+                        editor.insert_text(&character.to_string(), context)
+                    })
+                    .ok();
+            })
+            .detach();
     }
 
-    fn select_end_of_file(&mut self, _: &SelectEndOfFile, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn insert_character(&mut self, action: &InsertCharacter, context: &mut ViewContext<Self>) {
+        if self.read_only {
             return;
         }
 
-        let starting_point = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.start,
+        self.insert_text(&action.character, context);
+    }
+
+    // Replaces the selection, leaving the cursor after the text
+    // Unlike typing, nothing is auto-closed or substituted
+    fn insert_text(&mut self, text: &str, context: &mut ViewContext<Self>) {
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
         };
 
-        self.select(starting_point, self.end_of_file_position(), context);
+        self.replace_range(range.clone(), text.to_string(), context);
+
+        // The length is in bytes, so the cursor lands after characters like `—` as a whole
+        let position = self.buffer.position_after(range.start, text.len());
+
+        self.move_to(position.clone(), position.x, context);
     }
 
-    fn select_beginning_of_line(
+    // A rule needs a blank line above it, or the line before turns into a headline
+    // Text after the cursor moves below it
+    fn insert_horizontal_rule(
         &mut self,
-        _: &SelectBeginningOfLine,
+        _: &InsertHorizontalRule,
         context: &mut ViewContext<Self>,
     ) {
-        if self.buffer.is_empty() {
+        if self.read_only {
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => self.select(
-                cursor.position.clone(),
-                self.beginning_of_line_position(cursor.position),
-                context,
-            ),
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => {
-                    self.select_to(self.beginning_of_line_position(selection.end), context)
-                }
-                SelectionDirection::Forwards => self.select(
-                    selection.start,
-                    self.beginning_of_line_position(selection.end),
-                    context,
-                ),
-            },
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+        let content = self.buffer.content();
+        let text = content.to_string();
+        let start = content.original_offset(self.buffer.position_to_offset(range.start.clone()));
+        let end = content.original_offset(self.buffer.position_to_offset(range.end.clone()));
+        let before = &text[..start];
+        let after = &text[end..];
+        let line_before = before.rsplit('\n').next().unwrap_or_default();
+        let line_after = after.split('\n').next().unwrap_or_default();
+        let lines_above = &before[..before.len() - line_before.len()];
+
+        let prefix = if !line_before.trim().is_empty() {
+            "\n\n"
+        } else if lines_above.is_empty() || lines_above.ends_with("\n\n") {
+            ""
+        } else {
+            "\n"
+        };
+        let suffix = if line_after.trim().is_empty() {
+            "\n"
+        } else {
+            "\n\n"
+        };
+        let replacement = format!("{}---{}", prefix, suffix);
+        let length = replacement.len();
+
+        self.replace_range(range.clone(), replacement, context);
+
+        let position = self.buffer.position_after(range.start, length);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    // Wraps the selection in a fenced code block, or inserts an empty one
+    // The cursor ends up inside the block, after the selected text
+    fn insert_code_block(&mut self, _: &InsertCodeBlock, context: &mut ViewContext<Self>) {
+        if self.read_only {
+            return;
+        }
+
+        let range = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+        let text = self.buffer.content().to_string();
+        let start = self.buffer.original_offset(range.start.clone());
+        let end = self.buffer.original_offset(range.end.clone());
+        let line_before = text[..start].rsplit('\n').next().unwrap_or_default();
+        let line_after = text[end..].split('\n').next().unwrap_or_default();
+        let code = text[start..end]
+            .strip_suffix('\n')
+            .unwrap_or(&text[start..end]);
+
+        // The fences have to be on lines of their own
+        let prefix = if line_before.is_empty() { "" } else { "\n" };
+        let suffix = if line_after.is_empty() { "" } else { "\n" };
+        let opening = format!("{}```\n", prefix);
+        let replacement = format!("{}{}\n```{}", opening, code, suffix);
+        let cursor_offset = start + opening.len() + code.len();
+
+        self.replace_range(range, replacement, context);
+
+        let position = self.buffer.original_position(cursor_offset);
+
+        self.move_to(position.clone(), position.x, context);
+    }
+
+    fn move_to(
+        &mut self,
+        position: EditorPosition,
+        preferred_x: isize,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.buffer.set_location(EditLocation::Cursor(Cursor {
+            position: position.clone(),
+            preferred_x,
+        }));
+
+        self.ensure_in_viewport(position, context);
+        self.schedule_occurrences(context);
+
+        context.notify();
+    }
+
+    // Remembers where the cursor is before it jumps somewhere else in the document
+    fn record_jump(&mut self) {
+        let position = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.end,
         };
+
+        // Jumping somewhere new forgets the jumps that could be gone forward to
+        self.jumps.truncate(self.jump_index + 1);
+
+        if self.jumps.last() != Some(&position) {
+            self.jumps.push(position);
+        }
+
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+
+        self.jump_index = self.jumps.len();
+    }
+
+    fn clear_jumps(&mut self) {
+        self.jumps.clear();
+        self.jump_index = 0;
     }
 
-    fn select_end_of_line(&mut self, _: &SelectEndOfLine, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn jump_back(&mut self, _: &JumpBack, context: &mut ViewContext<Self>) {
+        if self.jumps.is_empty() {
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => self.select(
-                cursor.position.clone(),
-                self.end_of_line_position(cursor.position),
-                context,
-            ),
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => {
-                    self.select_to(self.end_of_line_position(selection.end), context)
-                }
-                SelectionDirection::Forwards => self.select(
-                    selection.start,
-                    self.end_of_line_position(selection.end),
-                    context,
-                ),
-            },
-        };
-    }
+        // Remember where we came from so jumping forward returns here
+        if self.jump_index == self.jumps.len() {
+            self.record_jump();
+            self.jump_index = self.jumps.len() - 1;
+        }
 
-    fn select_beginning_of_word(
-        &mut self,
-        _: &SelectBeginningOfWord,
-        context: &mut ViewContext<Self>,
-    ) {
-        if self.buffer.is_empty() {
+        if self.jump_index == 0 {
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => {
-                self.select_to(self.beginning_of_word_position(cursor.position), context);
-            }
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => {
-                    self.select_to(self.beginning_of_word_position(selection.end), context)
-                }
-                SelectionDirection::Forwards => self.select(
-                    selection.start,
-                    self.beginning_of_word_position(selection.end),
-                    context,
-                ),
-            },
-        };
+        self.jump_index -= 1;
+        self.jump_to(self.jumps[self.jump_index].clone(), context);
     }
 
-    fn select_end_of_word(&mut self, _: &SelectEndOfWord, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
+    fn jump_forward(&mut self, _: &JumpForward, context: &mut ViewContext<Self>) {
+        if self.jump_index + 1 >= self.jumps.len() {
             return;
         }
 
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => {
-                self.select_to(self.end_of_word_position(cursor.position), context);
-            }
-            EditLocation::Selection(selection) => match selection.direction() {
-                SelectionDirection::Backwards => {
-                    self.select_to(self.end_of_word_position(selection.end), context)
-                }
-                SelectionDirection::Forwards => self.select(
-                    selection.start,
-                    self.end_of_word_position(selection.end),
-                    context,
-                ),
-            },
-        };
+        self.jump_index += 1;
+        self.jump_to(self.jumps[self.jump_index].clone(), context);
     }
 
-    fn select_all(&mut self, _: &SelectAll, context: &mut ViewContext<Self>) {
-        if self.buffer.is_empty() {
-            return;
-        }
+    fn jump_to(&mut self, position: EditorPosition, context: &mut ViewContext<Self>) {
+        self.buffer.set_location(EditLocation::Cursor(Cursor::new(
+            position.y, position.x, position.x,
+        )));
+        // The text might have changed since the position was recorded
+        self.buffer.clamp_edit_location();
 
-        let start = self.beginning_of_file_position();
-        let end = self.end_of_file_position();
+        let EditLocation::Cursor(cursor) = self.buffer.edit_location() else {
+            return;
+        };
 
-        self.select(start, end, context);
+        self.move_to(cursor.position.clone(), cursor.position.x, context);
     }
 
-    fn remove_selection(&mut self, _: &RemoveSelection, context: &mut ViewContext<Self>) {
-        if let EditLocation::Selection(selection) = self.buffer.edit_location() {
-            self.move_to(selection.start.clone(), selection.start.x, context);
+    fn ensure_in_viewport(&mut self, position: EditorPosition, context: &mut ViewContext<Self>) {
+        self.ensure_column_visible(position.x);
+
+        let height = self.scroll_handle.bounds().size.height;
+        let offset = self.scroll_offset().abs();
+        let current_line_offset = px(position.y as f32) * self.metrics.line_height;
+        let viewport = offset..height + offset;
+
+        // Instead of scrolling right when we reach the edge
+        // We'll do it when within the following margin
+        let line_margin = self.metrics.line_height * 3;
+
+        if current_line_offset - line_margin < viewport.start - EDITOR_VERTICAL_MARGIN {
+            self.animate_scroll_to(
+                -(current_line_offset + EDITOR_VERTICAL_MARGIN - line_margin),
+                context,
+            );
+        }
+
+        if current_line_offset + self.metrics.line_height + line_margin
+            > viewport.end - EDITOR_VERTICAL_MARGIN
+        {
+            self.animate_scroll_to(
+                -(current_line_offset - height
+                    + self.metrics.line_height
+                    + EDITOR_VERTICAL_MARGIN
+                    + line_margin),
+                context,
+            );
         }
     }
 
-    fn backspace(&mut self, _: &Backspace, context: &mut ViewContext<Self>) {
-        match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => {
-                if cursor.position == self.beginning_of_file_position() {
-                    return;
-                }
+    // Scrolls sideways so the column stays in view, with some room beside it
+    fn ensure_column_visible(&mut self, x: isize) {
+        if self.wrap {
+            self.horizontal_offset = Pixels::ZERO;
 
-                let line = self.buffer.line(cursor.position.y);
+            return;
+        }
 
-                match (line.clone().kind, cursor.position.x) {
-                    (LineType::HeadlineStart(_level), 0) => {
-                        let position = EditorPosition::new(cursor.position.y, line.beginning());
-                        let range = position.clone()..cursor.position;
+        let width = self.metrics.character_width * CHARACTER_COUNT_PER_LINE as f32;
+        let margin = self.metrics.character_width * HORIZONTAL_SCROLL_MARGIN as f32;
+        let column = self.metrics.character_width * std::cmp::max(0, x) as f32;
 
-                        self.replace_range(range, "".into(), context);
-                    }
-                    (LineType::Normal, 0) => {
-                        let current_cursor_offset =
-                            self.buffer.position_to_offset(cursor.position.clone());
-                        let wrap_points = self.buffer.content().wrap_points();
+        if column - margin < self.horizontal_offset {
+            self.scroll_horizontally_to(column - margin);
+        } else if column + margin > self.horizontal_offset + width {
+            self.scroll_horizontally_to(column + margin - width);
+        }
+    }
 
-                        let mut position = self.left_position(cursor.position.clone());
+    fn scroll_horizontally_to(&mut self, x: Pixels) {
+        let widest_line = self
+            .buffer
+            .lines()
+            .iter()
+            .map(|line| line.end())
+            .max()
+            .unwrap_or(0);
+        let max_offset = self.metrics.character_width
+            * (widest_line as f32 + HORIZONTAL_SCROLL_MARGIN as f32
+                - CHARACTER_COUNT_PER_LINE as f32);
+
+        self.horizontal_offset = if x > max_offset { max_offset } else { x };
+
+        if self.horizontal_offset < Pixels::ZERO {
+            self.horizontal_offset = Pixels::ZERO;
+        }
+    }
 
-                        // Note: It's important we get the cursor offset here
-                        // Before subtracting the soft-wrap point
-                        let cursor_offset = self.buffer.position_to_offset(position.clone());
+    fn scroll_wheel(&mut self, event: &ScrollWheelEvent, context: &mut ViewContext<Self>) {
+        let delta = event.delta.pixel_delta(self.metrics.line_height);
 
-                        if wrap_points.contains(&current_cursor_offset) {
-                            position = self.left_position(position);
-                        }
+        if !self.wrap {
+            // Scrolling right shows what's further along the lines
+            self.scroll_horizontally_to(self.horizontal_offset - delta.x);
+        }
 
-                        let range = position.clone()..cursor.position;
+        let offset = self.scroll_handle.offset().y + delta.y;
 
-                        self.replace_range(range, "".into(), context);
+        // The user takes over, so stop any running animation
+        self.scroll_animation = None;
+        self.scroll_to(offset);
 
-                        let new_cursor_position = self.buffer.offset_to_position(cursor_offset);
+        context.notify();
+    }
 
-                        self.move_to(new_cursor_position, position.x, context);
-                    }
-                    _ => {
-                        let position = self.left_position(cursor.position.clone());
-                        let range = position.clone()..cursor.position;
+    // Where the editor is scrolled to, or will be once the running animation is done
+    fn scroll_offset(&self) -> Pixels {
+        return match &self.scroll_animation {
+            Some(animation) => animation.target,
+            None => self.scroll_handle.offset().y,
+        };
+    }
 
-                        self.replace_range(range, "".into(), context);
+    fn scroll_to(&mut self, y: Pixels) {
+        self.scroll_handle
+            .set_offset(Point::new(Pixels::ZERO, self.clamp_scroll_offset(y)));
+    }
 
-                        if position.x >= 0 {
-                            self.move_to(position.clone(), position.x, context);
-                        }
-                    }
-                };
-            }
-            EditLocation::Selection(selection) => {
-                let smallest = selection.smallest();
-                let range = smallest.clone()..selection.largest();
+    fn animate_scroll_to(&mut self, y: Pixels, context: &mut ViewContext<Self>) {
+        let target = self.clamp_scroll_offset(y);
+        let from = self.scroll_handle.offset().y;
 
-                self.replace_range(range, "".into(), context);
-                let x = std::cmp::max(0, smallest.x);
+        // Small movements snap so moving line by line doesn't lag behind
+        if self.reduce_motion || (target - from).abs() <= self.metrics.line_height * 3 {
+            self.scroll_animation = None;
+            self.scroll_to(target);
 
-                self.move_to(EditorPosition::new(smallest.y, x), x, context);
-            }
+            return;
         }
-    }
 
-    fn enter(&mut self, _: &Enter, context: &mut ViewContext<Self>) {
-        let range = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
-            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
-        };
+        // Replacing the animation drops the previous task, which cancels it
+        let task = context.spawn(|editor, mut context| async move {
+            let start = Instant::now();
 
-        self.replace_range(range.clone(), "\n".into(), context);
+            loop {
+                context.background_executor().timer(SCROLL_FRAME).await;
 
-        let y = range.end.y + 1;
-        let line = self.buffer.line(y);
-        let position = EditorPosition::new(y, line.beginning());
+                let progress = (start.elapsed().as_secs_f32()
+                    / SCROLL_ANIMATION_DURATION.as_secs_f32())
+                .min(1.);
+                // Ease out
+                let eased = 1. - (1. - progress).powi(3);
 
-        self.move_to(position.clone(), position.x, context);
-    }
+                let result = editor.update(&mut context, |editor, context| {
+                    editor.scroll_to(from + (target - from) * eased);
+
+                    if progress >= 1. {
+                        editor.scroll_animation = None;
+                    }
 
-    fn save(&mut self, _: &Save, context: &mut ViewContext<Self>) {
-        // Check if the buffer has an associated file
-        if !self.buffer.has_file() {
-            // If there's no file, prompt for a save location
-            self.prompt_to_save_file(context);
-        } else {
-            // If there is a file, save directly
-            match self.buffer.save() {
-                Ok(_) => {
                     context.notify();
-                }
-                Err(err) => {
-                    // This shouldn't happen since we checked has_file(), but handle it anyway
-                    let error_message = format!("Failed to save file: {:?}", err);
-                    let error_prompt =
-                        context.prompt(PromptLevel::Critical, &error_message, None, &["OK"]);
-
-                    context
-                        .foreground_executor()
-                        .spawn(async move {
-                            error_prompt.await.ok();
-                        })
-                        .detach();
+                });
+
+                if result.is_err() || progress >= 1. {
+                    break;
                 }
             }
-        }
-    }
+        });
 
-    fn save_as(&mut self, _: &SaveAs, context: &mut ViewContext<Self>) {
-        self.prompt_to_save_file(context);
+        self.scroll_animation = Some(ScrollAnimation {
+            target,
+            _task: task,
+        });
     }
 
-    fn copy(&mut self, _: &Copy, context: &mut ViewContext<Self>) {
-        if let EditLocation::Selection(selection) = self.buffer.edit_location() {
-            let range = selection.smallest()..selection.largest();
-
-            let text = self.read_range(range.clone());
+    fn clamp_scroll_offset(&self, y: Pixels) -> Pixels {
+        let max_offset = self.max_scroll_offset();
 
-            context.write_to_clipboard(ClipboardItem::new_string(text));
+        // Offsets are negative when scrolled down
+        if y > Pixels::ZERO {
+            return Pixels::ZERO;
+        } else if y < -max_offset {
+            return -max_offset;
         }
+
+        return y;
     }
 
-    fn cut(&mut self, _: &Cut, context: &mut ViewContext<Self>) {
-        if let EditLocation::Selection(selection) = self.buffer.edit_location() {
-            let range = selection.smallest()..selection.largest();
+    fn content_height(&self) -> Pixels {
+        return px(self.buffer.lines().len() as f32) * self.metrics.line_height
+            + EDITOR_VERTICAL_MARGIN * 2.;
+    }
 
-            let text = self.read_range(range.clone());
+    fn max_scroll_offset(&self) -> Pixels {
+        let height = self.scroll_handle.bounds().size.height;
+        let content_height = self.content_height();
 
-            context.write_to_clipboard(ClipboardItem::new_string(text));
-            self.replace_range(range, "".into(), context);
-            self.move_to(selection.smallest(), selection.smallest().x, context);
+        if content_height < height {
+            return Pixels::ZERO;
         }
+
+        return content_height - height;
     }
 
-    fn paste(&mut self, _: &Paste, context: &mut ViewContext<Self>) {
-        let clipboard_item = context
-            .read_from_clipboard()
-            .unwrap_or(ClipboardItem::new_string("".into()));
-        let range = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position.clone()..cursor.position,
-            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
-        };
-        let mut content = String::new();
+    // The top and height of the scrollbar thumb, or nothing when all the text fits
+    fn scrollbar_thumb(&self) -> Option<(Pixels, Pixels)> {
+        let height = self.scroll_handle.bounds().size.height;
+        let max_offset = self.max_scroll_offset();
 
-        for entry in clipboard_item.entries() {
-            if let gpui::ClipboardEntry::String(clipboard_string) = entry {
-                content.push_str(clipboard_string.text());
-            }
+        if max_offset <= Pixels::ZERO {
+            return None;
         }
 
-        self.replace_range(range.clone(), content.clone(), context);
+        let mut thumb_height = height * (height / self.content_height());
 
-        let mut offset = self.buffer.position_to_offset(range.start);
-        offset += content.len();
-        let position = self.buffer.offset_to_position(offset);
+        if thumb_height < SCROLLBAR_MIN_THUMB_HEIGHT {
+            thumb_height = SCROLLBAR_MIN_THUMB_HEIGHT;
+        }
 
-        self.move_to(position.clone(), position.x, context);
-    }
+        let progress = -self.scroll_handle.offset().y / max_offset;
 
-    fn move_to(
-        &mut self,
-        position: EditorPosition,
-        preferred_x: isize,
-        context: &mut ViewContext<Self>,
-    ) {
-        self.buffer.set_location(EditLocation::Cursor(Cursor {
-            position: position.clone(),
-            preferred_x,
-        }));
+        return Some(((height - thumb_height) * progress, thumb_height));
+    }
 
-        self.ensure_in_viewport(position);
+    fn start_scrollbar_drag(&mut self, event: &MouseDownEvent, context: &mut ViewContext<Self>) {
+        self.scroll_animation = None;
+        self.scrollbar_drag = Some(ScrollbarDrag {
+            mouse_y: event.position.y,
+            scroll_offset: self.scroll_handle.offset().y,
+        });
 
-        context.notify();
+        context.stop_propagation();
     }
 
-    fn ensure_in_viewport(&mut self, position: EditorPosition) {
-        let height = self.scroll_handle.bounds().size.height;
-        let offset = self.scroll_handle.offset().y.abs();
-        let current_line_offset = px(position.y as f32) * LINE_HEIGHT;
-        let viewport = offset..height + offset;
+    fn drag_scrollbar(&mut self, event: &MouseMoveEvent, context: &mut ViewContext<Self>) {
+        let Some(drag) = &self.scrollbar_drag else {
+            return;
+        };
 
-        // Instead of scrolling right when we reach the edge
-        // We'll do it when within the following margin
-        let line_margin = LINE_HEIGHT * 3;
+        // The button was released outside of the window
+        if event.pressed_button != Some(MouseButton::Left) {
+            self.scrollbar_drag = None;
 
-        if current_line_offset - line_margin < viewport.start - EDITOR_VERTICAL_MARGIN {
-            self.scroll_to(-(current_line_offset + EDITOR_VERTICAL_MARGIN - line_margin));
+            return;
         }
 
-        if current_line_offset + LINE_HEIGHT + line_margin > viewport.end - EDITOR_VERTICAL_MARGIN {
-            self.scroll_to(
-                -(current_line_offset - height
-                    + LINE_HEIGHT
-                    + EDITOR_VERTICAL_MARGIN
-                    + line_margin),
-            );
+        let Some((_, thumb_height)) = self.scrollbar_thumb() else {
+            return;
+        };
+
+        // Moving the thumb across the whole track scrolls through the whole document
+        let track = self.scroll_handle.bounds().size.height - thumb_height;
+
+        if track <= Pixels::ZERO {
+            return;
         }
+
+        let ratio = self.max_scroll_offset() / track;
+        let offset = drag.scroll_offset - (event.position.y - drag.mouse_y) * ratio;
+
+        self.scroll_to(offset);
+
+        context.notify();
     }
 
-    fn scroll_to(&mut self, y: Pixels) {
-        self.scroll_handle.set_offset(Point::new(Pixels::ZERO, y));
+    fn stop_scrollbar_drag(&mut self, _: &MouseUpEvent, context: &mut ViewContext<Self>) {
+        if self.scrollbar_drag.take().is_some() {
+            context.notify();
+        }
     }
 
     fn select(
@@ -870,229 +3295,291 @@ impl Editor {
         } else {
             self.buffer
                 .set_location(EditLocation::Selection(Selection::new(start, end)));
+            self.schedule_occurrences(context);
         }
 
         context.notify();
     }
 
-    fn select_to(&mut self, end: EditorPosition, context: &mut ViewContext<Self>) {
-        let start = match self.buffer.edit_location() {
-            EditLocation::Cursor(cursor) => cursor.position,
-            EditLocation::Selection(selection) => selection.start,
-        };
+    // Waits for the cursor to rest so the document isn't searched on every keystroke
+    fn schedule_occurrences(&mut self, context: &mut ViewContext<Self>) {
+        self.occurrences_task = Some(context.spawn(|editor, mut context| async move {
+            context.background_executor().timer(OCCURRENCES_DELAY).await;
 
-        self.ensure_in_viewport(end.clone());
+            editor
+                .update(&mut context, |editor, context| {
+                    editor.occurrences = editor.find_occurrences();
 
-        self.select(start, end, context);
+                    context.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn read_range(&self, range: Range<EditorPosition>) -> String {
-        let start_offset = self.buffer.position_to_offset(range.start);
-        let end_offset = self.buffer.position_to_offset(range.end);
-        let range = start_offset..end_offset;
-
-        return self.buffer.read_range(range);
+    fn update_changed_lines(&mut self) {
+        self.changed_lines = diff::changed_lines(&self.buffer.diff_against_saved());
     }
 
-    fn replace_range(
-        &mut self,
-        range: Range<EditorPosition>,
-        replacement: String,
-        context: &mut ViewContext<Self>,
-    ) {
-        let start_offset = self.buffer.position_to_offset(range.start);
-        let end_offset = self.buffer.position_to_offset(range.end);
+    // Waits for typing to pause so the document isn't diffed on every keystroke
+    fn schedule_changed_lines(&mut self, context: &mut ViewContext<Self>) {
+        self.changed_lines_task = Some(context.spawn(|editor, mut context| async move {
+            context
+                .background_executor()
+                .timer(CHANGED_LINES_DELAY)
+                .await;
 
-        self.buffer.replace(start_offset..end_offset, replacement);
+            editor
+                .update(&mut context, |editor, context| {
+                    editor.update_changed_lines();
 
-        context.notify();
+                    context.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn left_position(&self, point: EditorPosition) -> EditorPosition {
-        let line = self.buffer.line(point.y);
-
-        if point.y == 0 && point.x == line.beginning() {
-            return point;
-        }
-
-        if point.x == line.beginning() {
-            let new_line_index = point.y - 1;
-            let line = self.buffer.line(new_line_index);
+    // The word at the position, without the punctuation around it
+    fn word_at(&self, position: EditorPosition) -> Option<Range<EditorPosition>> {
+        let range = self.buffer.word_range(position)?;
+        let text = self.read_range(range.clone());
+        // Punctuation next to the word, like a trailing comma, isn't part of it
+        let leading = text.len() - text.trim_start_matches(|c| !is_word_character(c)).len();
+        let trailing = text.len() - text.trim_end_matches(|c| !is_word_character(c)).len();
 
-            return EditorPosition::new(new_line_index, line.end());
+        if leading + trailing >= text.len() {
+            return None;
         }
 
-        return EditorPosition::new(point.y, point.x - 1);
+        return Some(
+            EditorPosition::new(range.start.y, range.start.x + leading as isize)
+                ..EditorPosition::new(range.end.y, range.end.x - trailing as isize),
+        );
     }
 
-    fn right_position(&self, point: EditorPosition) -> EditorPosition {
-        let line_length = self.buffer.lines().len();
-        let line = self.buffer.line(point.y);
-
-        if point.y == line_length - 1 && point.x == line.end() {
-            return point;
-        }
+    fn find_occurrences(&self) -> Vec<Range<EditorPosition>> {
+        let current = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => match self.word_at(cursor.position) {
+                Some(range) => range,
+                None => return vec![],
+            },
+            EditLocation::Selection(selection) => selection.smallest()..selection.largest(),
+        };
+        let word = self.read_range(current.clone());
 
-        if point.x == line.end() {
-            return EditorPosition::new(point.y + 1, 0);
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            return vec![];
         }
 
-        return EditorPosition::new(point.y, point.x + 1);
-    }
-
-    fn up_position(&self, point: EditorPosition, preferred_x: isize) -> EditorPosition {
-        if point.y == 0 {
-            let line = self.buffer.line(0);
-            return EditorPosition::new(0, line.beginning());
-        }
+        // Only whole words count, so `art` doesn't match inside of `start`
+        let is_whole_word = |range: &Range<EditorPosition>| {
+            let (before, _) = self.buffer.characters_around(&range.start);
+            let (_, after) = self.buffer.characters_around(&range.end);
 
-        let previous_line = self.buffer.line(point.y - 1);
+            !before.map_or(false, is_word_character) && !after.map_or(false, is_word_character)
+        };
 
-        let x = previous_line.clamp_x(preferred_x);
+        return self
+            .buffer
+            .find_all(&word)
+            .into_iter()
+            .filter(|range| *range != current && is_whole_word(range))
+            .collect();
+    }
 
-        return EditorPosition::new(point.y - 1, x);
+    // Shows a short message at the bottom of the editor that disappears by itself
+    fn show_status(&mut self, status: impl Into<SharedString>, context: &mut ViewContext<Self>) {
+        self.show_status_for(status, STATUS_DURATION, context);
     }
 
-    fn down_position(&self, point: EditorPosition, preferred_x: isize) -> EditorPosition {
-        let line = self.buffer.line(point.y);
+    // Showing another status, or the same one again, starts the duration over
+    fn show_status_for(
+        &mut self,
+        status: impl Into<SharedString>,
+        duration: Duration,
+        context: &mut ViewContext<Self>,
+    ) {
+        // Without motion the status disappears all at once
+        let fade_steps = if self.reduce_motion {
+            0
+        } else {
+            STATUS_FADE_STEPS
+        };
 
-        if point.y == self.buffer.lines().len() - 1 {
-            return EditorPosition::new(point.y, line.end());
-        }
+        self.status = Some(status.into());
+        self.status_opacity = 1.;
+        self.status_task = Some(context.spawn(|editor, mut context| async move {
+            let fade = STATUS_FADE_STEP * fade_steps;
 
-        let next_line = self.buffer.line(point.y + 1);
+            context
+                .background_executor()
+                .timer(duration.saturating_sub(fade))
+                .await;
 
-        let x = next_line.clamp_x(preferred_x);
+            for step in (0..fade_steps).rev() {
+                context.background_executor().timer(STATUS_FADE_STEP).await;
 
-        return EditorPosition::new(point.y + 1, x);
-    }
+                editor
+                    .update(&mut context, |editor, context| {
+                        editor.status_opacity = step as f32 / STATUS_FADE_STEPS as f32;
 
-    fn beginning_of_file_position(&self) -> EditorPosition {
-        let line = self.buffer.line(0);
+                        context.notify();
+                    })
+                    .ok();
+            }
 
-        return EditorPosition::new(0, line.beginning());
-    }
+            editor
+                .update(&mut context, |editor, context| {
+                    editor.status = None;
 
-    fn end_of_file_position(&self) -> EditorPosition {
-        let y = self.buffer.lines().len() - 1;
-        let line = self.buffer.line(y);
+                    context.notify();
+                })
+                .ok();
+        }));
 
-        return EditorPosition::new(y, line.end());
+        context.notify();
     }
 
-    fn beginning_of_line_position(&self, point: EditorPosition) -> EditorPosition {
-        let line = self.buffer.line(point.y);
-
-        return EditorPosition::new(point.y, line.beginning());
-    }
+    // The misspelled byte ranges of each line
+    // Lines inside fenced code blocks are skipped
+    fn misspelled_ranges(&self, lines: &Vec<Line>) -> Vec<Vec<Range<usize>>> {
+        let mut is_inside_code_block = false;
 
-    fn end_of_line_position(&self, point: EditorPosition) -> EditorPosition {
-        let line = self.buffer.line(point.y);
+        return lines
+            .iter()
+            .map(|line| {
+                let is_fence = line.text.trim_start().starts_with("```");
 
-        return EditorPosition::new(point.y, line.end());
-    }
+                if is_fence {
+                    is_inside_code_block = !is_inside_code_block;
+                }
 
-    fn beginning_of_word_position(&self, point: EditorPosition) -> EditorPosition {
-        let line = self.buffer.line(point.y);
-        let line_offset = (point.x - line.beginning()) as usize;
+                if is_fence || is_inside_code_block || matches!(line.kind, LineType::Frontmatter) {
+                    return vec![];
+                }
 
-        // Handle edge case: at beginning of file
-        if point.y == 0 && point.x <= line.beginning() {
-            return self.beginning_of_file_position();
-        }
+                return self.spellchecker.misspelled_ranges(line);
+            })
+            .collect();
+    }
 
-        // First attempt: find previous word boundary in current line
-        if point.x > line.beginning() && line_offset <= line.text.len() {
-            let wrapped_text = WrappedText::new(line.text.clone());
-            let word_boundary = wrapped_text.previous_word_boundary(line_offset);
-            let new_x = line.beginning() + (word_boundary as isize);
+    fn misspellings(&self) -> Vec<Range<EditorPosition>> {
+        let lines = self.buffer.lines();
 
-            // Use this position if it's actually before the current position
-            if new_x < point.x {
-                return EditorPosition::new(point.y, new_x);
-            }
-        }
+        return self
+            .misspelled_ranges(&lines)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(y, ranges)| {
+                let beginning = lines[y].beginning();
 
-        // If we're here, we need to look at previous line
+                ranges.into_iter().map(move |range| {
+                    EditorPosition::new(y, beginning + range.start as isize)
+                        ..EditorPosition::new(y, beginning + range.end as isize)
+                })
+            })
+            .collect();
+    }
 
-        // Handle edge case: already at first line
-        if point.y == 0 {
-            return self.beginning_of_file_position();
-        }
+    fn next_misspelling(&mut self, _: &NextMisspelling, context: &mut ViewContext<Self>) {
+        let current = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.largest(),
+        };
+        let misspellings = self.misspellings();
+        let next = misspellings
+            .iter()
+            .find(|range| (range.start.y, range.start.x) >= (current.y, current.x))
+            // Start over from the top
+            .or(misspellings.first());
+
+        self.select_misspelling(next.cloned(), context);
+    }
 
-        // Get previous line
-        let previous_line = self.buffer.line(point.y - 1);
+    fn previous_misspelling(&mut self, _: &PreviousMisspelling, context: &mut ViewContext<Self>) {
+        let current = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.smallest(),
+        };
+        let misspellings = self.misspellings();
+        let previous = misspellings
+            .iter()
+            .rev()
+            .find(|range| (range.end.y, range.end.x) < (current.y, current.x))
+            // Start over from the bottom
+            .or(misspellings.last());
+
+        self.select_misspelling(previous.cloned(), context);
+    }
 
-        // Handle edge case: previous line is empty
-        if previous_line.text.trim().is_empty() {
-            return EditorPosition::new(point.y - 1, previous_line.beginning());
-        }
+    fn select_misspelling(
+        &mut self,
+        range: Option<Range<EditorPosition>>,
+        context: &mut ViewContext<Self>,
+    ) {
+        let Some(range) = range else {
+            self.show_status("No misspellings", context);
 
-        // Find last word in previous line
-        let wrapped_text = WrappedText::new(previous_line.text.clone());
-        let last_valid_offset = previous_line.text.len();
-        let word_boundary = wrapped_text.previous_word_boundary(last_valid_offset);
-        let new_x = previous_line.beginning() + (word_boundary as isize);
+            return;
+        };
 
-        return EditorPosition::new(point.y - 1, new_x);
+        self.record_jump();
+        self.select(range.start, range.end.clone(), context);
+        self.ensure_in_viewport(range.end, context);
     }
 
-    fn end_of_word_position(&self, point: EditorPosition) -> EditorPosition {
-        let line = self.buffer.line(point.y);
-        let line_offset = (point.x - line.beginning()) as usize;
+    fn select_to(&mut self, end: EditorPosition, context: &mut ViewContext<Self>) {
+        let start = match self.buffer.edit_location() {
+            EditLocation::Cursor(cursor) => cursor.position,
+            EditLocation::Selection(selection) => selection.start,
+        };
 
-        // First attempt: find next word boundary in current line
-        if line_offset < line.text.len() {
-            let wrapped_text = WrappedText::new(line.text.clone());
+        self.ensure_in_viewport(end.clone(), context);
 
-            if let Some(word_boundary) = wrapped_text.next_word_boundary(line_offset) {
-                let new_x = line.beginning() + (word_boundary as isize);
+        self.select(start, end, context);
+    }
 
-                // Use this position if it doesn't exceed the end of the line
-                if new_x <= line.end() {
-                    return EditorPosition::new(point.y, new_x);
-                }
-            }
-        }
+    fn read_range(&self, range: Range<EditorPosition>) -> String {
+        let start_offset = self.buffer.position_to_offset(range.start);
+        let end_offset = self.buffer.position_to_offset(range.end);
+        let range = start_offset..end_offset;
 
-        // If we're here, we need to look at the next line
+        return self.buffer.read_range(range);
+    }
 
-        // Handle edge case: already at last line
-        if point.y >= self.buffer.lines().len() - 1 {
-            return EditorPosition::new(point.y, line.end());
+    fn replace_range(
+        &mut self,
+        range: Range<EditorPosition>,
+        replacement: String,
+        context: &mut ViewContext<Self>,
+    ) {
+        if self.read_only {
+            return;
         }
 
-        // Get next line
-        let next_line = self.buffer.line(point.y + 1);
+        let start_offset = self.buffer.position_to_offset(range.start);
+        let end_offset = self.buffer.position_to_offset(range.end);
 
-        // Handle edge case: next line is empty
-        if next_line.text.trim().is_empty() {
-            return EditorPosition::new(point.y + 1, next_line.beginning());
-        }
+        self.buffer.replace(start_offset..end_offset, replacement);
 
-        // Find first non-whitespace character in next line
-        let mut start_offset = 0;
-        while start_offset < next_line.text.len()
-            && next_line
-                .text
-                .chars()
-                .nth(start_offset)
-                .unwrap_or(' ')
-                .is_whitespace()
-        {
-            start_offset += 1;
-        }
+        self.buffer_changed(context);
+    }
 
-        // If we reached the end of the next line
-        if start_offset >= next_line.text.len() {
-            return EditorPosition::new(point.y + 1, next_line.beginning());
-        }
+    fn buffer_changed(&mut self, context: &mut ViewContext<Self>) {
+        // The positions are stale until the document has been searched again
+        self.occurrences.clear();
+        self.schedule_occurrences(context);
+        self.schedule_changed_lines(context);
+
+        context.emit(BufferChanged {
+            content: self.buffer.content(),
+        });
 
-        // Go to the first word in the next line
-        return EditorPosition::new(point.y + 1, next_line.beginning() + start_offset as isize);
+        context.notify();
     }
 }
 
+impl EventEmitter<BufferChanged> for Editor {}
+
 impl FocusableView for Editor {
     fn focus_handle(&self, _context: &AppContext) -> FocusHandle {
         self.focus_handle.clone()
@@ -1105,10 +3592,16 @@ impl gpui::Render for Editor {
             .track_focus(&self.focus_handle(context))
             .key_context("editor")
             .on_action(context.listener(Self::new_file))
+            .on_action(context.listener(Self::new_from_template))
             .on_action(context.listener(Self::open_file))
+            .on_action(context.listener(Self::open_path))
             .on_action(context.listener(Self::save))
             .on_action(context.listener(Self::save_as))
+            .on_action(context.listener(Self::revert))
             .on_action(context.listener(Self::set_buffer))
+            .on_action(context.listener(Self::next_tab))
+            .on_action(context.listener(Self::previous_tab))
+            .on_action(context.listener(Self::close_tab))
             .on_action(context.listener(Self::move_left))
             .on_action(context.listener(Self::move_right))
             .on_action(context.listener(Self::move_up))
@@ -1130,28 +3623,109 @@ impl gpui::Render for Editor {
             .on_action(context.listener(Self::select_beginning_of_word))
             .on_action(context.listener(Self::select_end_of_word))
             .on_action(context.listener(Self::select_all))
+            .on_action(context.listener(Self::select_paragraph))
+            .on_action(context.listener(Self::select_word))
+            .on_action(context.listener(Self::jump_to_matching_bracket))
+            .on_action(context.listener(Self::select_to_matching_bracket))
+            .on_action(context.listener(Self::jump_back))
+            .on_action(context.listener(Self::jump_forward))
+            .on_action(context.listener(Self::next_misspelling))
+            .on_action(context.listener(Self::previous_misspelling))
             .on_action(context.listener(Self::remove_selection))
             .on_action(context.listener(Self::backspace))
             .on_action(context.listener(Self::enter))
+            .on_action(context.listener(Self::indent))
+            .on_action(context.listener(Self::outdent))
+            .on_action(context.listener(Self::increase_headline_level))
+            .on_action(context.listener(Self::decrease_headline_level))
+            .on_action(context.listener(Self::upper_case))
+            .on_action(context.listener(Self::lower_case))
+            .on_action(context.listener(Self::title_case))
+            .on_action(context.listener(Self::clear_formatting))
+            .on_action(context.listener(Self::format_document))
+            .on_action(context.listener(Self::toggle_list))
+            .on_action(context.listener(Self::toggle_task))
+            .on_action(context.listener(Self::toggle_ordered_list))
+            .on_action(context.listener(Self::toggle_smart_punctuation))
+            .on_action(context.listener(Self::toggle_auto_capitalize))
+            .on_action(context.listener(Self::toggle_trim_trailing_whitespace))
+            .on_action(context.listener(Self::toggle_read_only))
+            .on_action(context.listener(Self::toggle_ruler))
+            .on_action(context.listener(Self::toggle_line_numbers))
+            .on_action(context.listener(Self::toggle_sentence_focus))
+            .on_action(context.listener(Self::toggle_word_wrap))
+            .on_action(context.listener(Self::toggle_vim_mode))
             .on_action(context.listener(Self::copy))
+            .on_action(context.listener(Self::copy_as_plain_text))
+            .on_action(context.listener(Self::copy_with_hard_wraps))
             .on_action(context.listener(Self::cut))
             .on_action(context.listener(Self::paste))
+            .on_action(context.listener(Self::insert_link))
+            .on_action(context.listener(Self::insert_horizontal_rule))
+            .on_action(context.listener(Self::insert_code_block))
+            .on_action(context.listener(Self::insert_special_character))
+            .on_action(context.listener(Self::insert_character))
+            .on_action(context.listener(Self::set_word_goal))
+            .on_mouse_move(context.listener(Self::drag_scrollbar))
+            .on_mouse_up(
+                MouseButton::Left,
+                context.listener(Self::stop_scrollbar_drag),
+            )
             .group("editor-container")
+            .relative()
             .w_full()
             .flex()
             .justify_center()
             .child(
                 div()
                     .id("editor")
-                    .w(CONTAINER_WIDTH)
-                    .line_height(LINE_HEIGHT)
+                    .w(self.metrics.container_width + self.gutter_width())
+                    .text_size(self.metrics.font_size)
+                    .line_height(self.metrics.line_height)
                     .py(EDITOR_VERTICAL_MARGIN)
-                    .overflow_y_scroll()
+                    // Scrolling is handled by the editor itself
+                    // so that both wheel and cursor driven scrolling are clamped the same way
+                    .overflow_hidden()
                     .track_scroll(&self.scroll_handle)
+                    .on_scroll_wheel(context.listener(Self::scroll_wheel))
                     .child(EditorElement {
                         input: context.view().clone(),
                     }),
             )
+            .when_some(self.scrollbar_thumb(), |this, (top, height)| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(top)
+                        .right_1()
+                        .w(SCROLLBAR_WIDTH)
+                        .h(height)
+                        .rounded_full()
+                        .bg(rgb(self.theme.scrollbar))
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            context.listener(Self::start_scrollbar_drag),
+                        ),
+                )
+            })
+            .when(self.tabs.len() > 1, |this| {
+                this.child(self.tab_bar(context))
+            })
+            .when_some(self.status.clone(), |this, status| {
+                this.child(
+                    div()
+                        .absolute()
+                        .bottom_4()
+                        .px_3()
+                        .py_1()
+                        .rounded_md()
+                        .bg(rgb(self.theme.status_background))
+                        .text_color(rgb(self.theme.status_text))
+                        .text_sm()
+                        .opacity(self.status_opacity)
+                        .child(status),
+                )
+            })
     }
 }
 
@@ -1194,6 +3768,22 @@ impl ViewInputHandler for Editor {
         text: &str,
         context: &mut ViewContext<Self>,
     ) {
+        // Typing in Normal mode runs commands instead of inserting the text
+        if self.vim_mode == Some(VimMode::Normal) {
+            for key in text.chars() {
+                self.vim_key(key, context);
+            }
+
+            return;
+        }
+
+        if self.read_only {
+            return;
+        }
+
+        let text = self.buffer.expand_tabs(text);
+        let text = text.as_str();
+
         // If no range is provided, use the current selection or cursor position
         let range = if let Some(range) = range {
             let start = self.buffer.offset_to_position(range.start);
@@ -1207,7 +3797,30 @@ impl ViewInputHandler for Editor {
             }
         };
 
-        self.replace_range(range.clone(), text.to_string(), context);
+        if self.auto_close(range.clone(), text, context) {
+            return;
+        }
+
+        let (range, text) = if self.smart_punctuation {
+            self.substitute_punctuation(range, text)
+        } else {
+            (range, text.to_string())
+        };
+
+        let text = if self.auto_capitalize {
+            self.capitalize_sentence_start(&range, text)
+        } else {
+            text
+        };
+
+        // The wrapping can change anywhere on the lines around the edit
+        // so where the text ends up is worked out in the unwrapped text
+        let start = self
+            .buffer
+            .content()
+            .original_offset(self.buffer.position_to_offset(range.start.clone()));
+
+        self.replace_range(range.clone(), text.clone(), context);
 
         // Handle case where a new headline is being created with ' '
         if let EditLocation::Cursor(cursor) = self.buffer.edit_location() {
@@ -1224,15 +3837,9 @@ impl ViewInputHandler for Editor {
             }
         }
 
-        let mut offset = self.buffer.position_to_offset(range.start.clone()) + text.len();
-        let mut end_position = self.buffer.offset_to_position(offset);
-
-        // If the inserts have caused a soft-wrap
-        // then we need to adjust the offset to account for the extra whitespace characters
-        if end_position.y > range.start.y {
-            offset += end_position.y - range.start.y;
-            end_position = self.buffer.offset_to_position(offset);
-        }
+        let end_position = self
+            .buffer
+            .offset_to_position(self.buffer.content().wrapped_offset(start + text.len()));
 
         self.move_to(end_position.clone(), end_position.x, context);
     }
@@ -1271,20 +3878,28 @@ impl IntoElement for EditorElement {
 struct RenderedLine {
     shaped_line: ShapedLine,
     raw_line: Line,
+    beginning: isize,
 }
 
 impl RenderedLine {
-    pub fn new(raw_line: Line, shaped_line: ShapedLine) -> RenderedLine {
+    pub fn new(raw_line: Line, shaped_line: ShapedLine, beginning: isize) -> RenderedLine {
         return RenderedLine {
             raw_line,
             shaped_line,
+            beginning,
         };
     }
 }
 
 struct PrepaintState {
     lines: Vec<RenderedLine>,
+    // Which line each number goes next to
+    line_numbers: Vec<(usize, ShapedLine)>,
     edit_location_rectangles: Vec<PaintQuad>,
+    task_rectangles: Vec<PaintQuad>,
+    rule_rectangles: Vec<PaintQuad>,
+    occurrence_rectangles: Vec<PaintQuad>,
+    change_rectangles: Vec<PaintQuad>,
 }
 
 impl Element for EditorElement {
@@ -1303,13 +3918,14 @@ impl Element for EditorElement {
         let input = self.input.read(context);
         let content = input.buffer.content();
         let lines = content.lines();
+        let line_height = input.metrics.line_height;
 
         let style = Style::default();
         let new_style = Style {
             size: gpui::Size {
                 width: gpui::Length::Auto,
                 height: gpui::Length::Definite(gpui::DefiniteLength::Absolute(
-                    gpui::AbsoluteLength::Pixels(px(lines.len() as f32) * LINE_HEIGHT),
+                    gpui::AbsoluteLength::Pixels(px(lines.len() as f32) * line_height),
                 )),
             },
             ..style
@@ -1330,69 +3946,261 @@ impl Element for EditorElement {
         let style = context.text_style();
         let font_size = style.font_size.to_pixels(context.rem_size());
         let is_focused = input.focus_handle.is_focused(context);
+        let metrics = input.metrics;
+        let theme = input.theme;
+        let text_left = input.text_left(bounds);
 
         let mut lines: Vec<RenderedLine> = vec![];
         let raw_lines = content.lines();
 
-        for line in &raw_lines {
-            let run = match line.kind {
+        // In Edit mode the markdown markers are hidden
+        // except on the lines being edited
+        let revealed = match input.mode {
+            Mode::Edit => match input.buffer.edit_location() {
+                EditLocation::Cursor(cursor) => cursor.position.y..cursor.position.y + 1,
+                EditLocation::Selection(selection) => {
+                    selection.smallest().y..selection.largest().y + 1
+                }
+            },
+            _ => 0..raw_lines.len(),
+        };
+        let display_map = DisplayMap::new(&raw_lines, revealed);
+        let misspelled_ranges = input.misspelled_ranges(&raw_lines);
+
+        // Zen mode dims everything but the paragraph being written
+        let emphasized = if input.zen {
+            let y = match input.buffer.edit_location() {
+                EditLocation::Cursor(cursor) => cursor.position.y,
+                EditLocation::Selection(selection) => selection.end.y,
+            };
+
+            input.buffer.paragraph_lines(y)
+        } else {
+            0..raw_lines.len()
+        };
+
+        // Sentence focus dims everything but the sentence being written
+        let focused_sentence = if input.sentence_focus && input.mode == Mode::Write {
+            let position = match input.buffer.edit_location() {
+                EditLocation::Cursor(cursor) => cursor.position,
+                EditLocation::Selection(selection) => selection.end,
+            };
+
+            Some(input.buffer.sentence_range(position))
+        } else {
+            None
+        };
+
+        for (index, line) in raw_lines.iter().enumerate() {
+            let text = display_map.text(index, line);
+            let misspelled_ranges: Vec<Range<usize>> = misspelled_ranges[index]
+                .iter()
+                .map(|range| {
+                    display_map.display_offset(index, range.start)
+                        ..display_map.display_offset(index, range.end)
+                })
+                .collect();
+            let mut run = match line.kind {
                 LineType::HeadlineStart(_) => TextRun {
-                    len: line.length(),
+                    len: text.len(),
                     font: Font {
                         weight: FontWeight::EXTRA_BOLD,
                         ..style.font()
                     },
-                    color: Hsla::from(rgb(COLOR_GRAY_800)),
+                    color: Hsla::from(rgb(theme.headline)),
                     background_color: None,
                     underline: None,
                     strikethrough: None,
                 },
                 LineType::HeadlineNotStart => TextRun {
-                    len: line.length(),
+                    len: text.len(),
                     font: Font {
                         weight: FontWeight::EXTRA_BOLD,
                         ..style.font()
                     },
-                    color: Hsla::from(rgb(COLOR_GRAY_800)),
+                    color: Hsla::from(rgb(theme.headline)),
                     background_color: None,
                     underline: None,
                     strikethrough: None,
                 },
                 LineType::Normal => TextRun {
-                    len: line.length(),
+                    len: text.len(),
                     font: style.font(),
-                    color: Hsla::from(rgb(COLOR_GRAY_700)),
+                    color: Hsla::from(rgb(theme.body)),
+                    background_color: None,
+                    underline: None,
+                    strikethrough: None,
+                },
+                LineType::Frontmatter | LineType::HorizontalRule => TextRun {
+                    len: text.len(),
+                    font: style.font(),
+                    color: Hsla::from(rgb(theme.text_muted)),
                     background_color: None,
                     underline: None,
                     strikethrough: None,
                 },
             };
-            let runs = vec![run];
+
+            if !emphasized.contains(&index) {
+                run.color = Hsla::from(rgb(theme.dimmed));
+            }
+
+            let mut runs = underline_ranges(run, &misspelled_ranges, theme.error);
+
+            if let Some(sentence) = &focused_sentence {
+                let focused = if index < sentence.start.y || index > sentence.end.y {
+                    0..0
+                } else {
+                    let start = if index == sentence.start.y {
+                        (sentence.start.x - line.beginning()).max(0) as usize
+                    } else {
+                        0
+                    };
+                    let end = if index == sentence.end.y {
+                        (sentence.end.x - line.beginning()).max(0) as usize
+                    } else {
+                        line.text.len()
+                    };
+
+                    display_map.display_offset(index, start)..display_map.display_offset(index, end)
+                };
+
+                runs = recolor_outside(runs, focused, Hsla::from(rgb(theme.dimmed)));
+            }
 
             let shaped_line = context
                 .text_system()
-                .shape_line(SharedString::from(line.text.clone()), font_size, &runs)
+                .shape_line(SharedString::from(text), font_size, &runs)
                 .unwrap();
+            let beginning = display_map.beginning(index, line);
 
-            lines.push(RenderedLine::new(line.clone(), shaped_line));
+            lines.push(RenderedLine::new(line.clone(), shaped_line, beginning));
         }
 
+        // Soft-wrapped continuations of a line don't get a number of their own
+        let line_numbers = if input.show_line_numbers {
+            let wrap_points = content.wrap_points();
+            let cursor_y = match input.buffer.edit_location() {
+                EditLocation::Cursor(cursor) => cursor.position.y,
+                EditLocation::Selection(selection) => selection.end.y,
+            };
+            let (current, _) = input.buffer.logical_line_bounds(cursor_y);
+            let mut number = 0;
+
+            (0..raw_lines.len())
+                .filter(|index| input.buffer.starts_logical_line(*index, &wrap_points))
+                .map(|index| {
+                    number += 1;
+
+                    let text = format!("{:>1$}", number, LINE_NUMBER_COLUMNS - 1);
+                    let color = if index == current {
+                        theme.body
+                    } else {
+                        theme.text_muted
+                    };
+                    let run = TextRun {
+                        len: text.len(),
+                        font: style.font(),
+                        color: Hsla::from(rgb(color)),
+                        background_color: None,
+                        underline: None,
+                        strikethrough: None,
+                    };
+                    let shaped_line = context
+                        .text_system()
+                        .shape_line(SharedString::from(text), font_size, &[run])
+                        .unwrap();
+
+                    (index, shaped_line)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // Checkboxes of task list items are drawn as a box around the `[ ]`
+        let task_rectangles = raw_lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let task = line.task?;
+                let x = display_map.display_x(index, line, task.checkbox as isize);
+                let left = text_left + px(x as f32) * metrics.character_width - px(1.);
+                let top = bounds.top() + metrics.line_height * index as f32 + px(4.);
+                let box_size = size(
+                    metrics.character_width * 3. + px(2.),
+                    metrics.line_height - px(8.),
+                );
+                let (background, border) = if task.is_done {
+                    (Hsla::from(rgb(theme.accent_background)), rgb(theme.accent))
+                } else {
+                    (transparent_black(), rgb(theme.text_muted))
+                };
+
+                Some(
+                    fill(Bounds::new(point(left, top), box_size), background)
+                        .corner_radii(px(3.))
+                        .border_widths(px(1.))
+                        .border_color(border),
+                )
+            })
+            .collect();
+
+        // Rules are drawn across the text, unless the cursor is close enough to reveal the markers
+        let rule_rectangles = raw_lines
+            .iter()
+            .enumerate()
+            .filter(|(index, line)| {
+                matches!(line.kind, LineType::HorizontalRule)
+                    && display_map.text(*index, line).is_empty()
+            })
+            .map(|(index, _)| {
+                let top = bounds.top() + metrics.line_height * (index as f32 + 0.5);
+
+                fill(
+                    Bounds::new(
+                        point(text_left, top),
+                        size(
+                            metrics.character_width * CHARACTER_COUNT_PER_LINE as f32,
+                            px(1.),
+                        ),
+                    ),
+                    rgb(theme.border),
+                )
+            })
+            .collect();
+
         let edit_location_rectangles = match input.buffer.edit_location() {
             EditLocation::Cursor(cursor) => {
-                let left = bounds.left()
-                    + EDITOR_HORIZONTAL_MARGIN
-                    + px(cursor.position.x as f32) * CHARACTER_WIDTH
-                    - px(1.);
+                let x = display_map.display_x(
+                    cursor.position.y,
+                    &raw_lines[cursor.position.y],
+                    cursor.position.x,
+                );
+                // Normal mode shows a block over the character the commands act on
+                let (left, width) = if input.vim_mode == Some(VimMode::Normal) {
+                    (
+                        text_left + px(x as f32) * metrics.character_width,
+                        metrics.character_width,
+                    )
+                } else {
+                    (
+                        text_left + px(x as f32) * metrics.character_width - px(1.),
+                        px(2.),
+                    )
+                };
                 let top =
                     bounds.top() + context.line_height() * px(cursor.position.y as f32) + px(2.);
 
-                let color = if is_focused {
-                    rgb(COLOR_BLUE_DARK)
+                let color = if !is_focused {
+                    rgb(theme.caret_unfocused)
+                } else if input.read_only {
+                    rgb(theme.caret_read_only)
                 } else {
-                    rgb(COLOR_GRAY_400)
+                    rgb(theme.caret)
                 };
                 let rectangles = vec![fill(
-                    Bounds::new(point(left, top), size(px(2.), px(20.))),
+                    Bounds::new(point(left, top), size(width, metrics.line_height - px(8.))),
                     color,
                 )];
 
@@ -1404,36 +4212,19 @@ impl Element for EditorElement {
                 let smallest = selection.smallest();
                 let largest = selection.largest();
 
-                let line_range = smallest.y..largest.y + 1;
-                let mut line_selection_bounds = Vec::new();
-
-                for index in line_range.clone() {
-                    let start = if index == line_range.start {
-                        smallest.x
-                    } else {
-                        std::cmp::min(0, smallest.x)
-                    };
-                    let end = if index == line_range.end - 1 {
-                        largest.x
-                    } else {
-                        CHARACTER_COUNT_PER_LINE as isize
-                    };
-
-                    line_selection_bounds.push((index, start, end));
-                }
+                let line_selection_bounds =
+                    display_map.selection_columns(&raw_lines, &smallest, &largest);
 
                 for (index, (line_index, start, end)) in line_selection_bounds.iter().enumerate() {
-                    let left = bounds.left()
-                        + EDITOR_HORIZONTAL_MARGIN
-                        + px(start.clone() as f32) * CHARACTER_WIDTH
-                        - px(1.);
+                    let left =
+                        text_left + px(start.clone() as f32) * metrics.character_width - px(1.);
                     let top = bounds.top() + px(line_index.clone() as f32) * context.line_height();
-                    let width = px((end - start) as f32) * CHARACTER_WIDTH + px(2.);
+                    let width = px((end - start) as f32) * metrics.character_width + px(2.);
 
                     let color = if is_focused {
-                        rgb(COLOR_BLUE_MEDIUM)
+                        rgb(theme.selection)
                     } else {
-                        rgb(COLOR_GRAY_300)
+                        rgb(theme.selection_unfocused)
                     };
                     let bounds = Bounds::new(point(left, top), size(width, context.line_height()));
 
@@ -1480,9 +4271,83 @@ impl Element for EditorElement {
             }
         };
 
+        // A word can be split over several lines when it's longer than a line
+        let occurrence_rectangles = input
+            .occurrences
+            .iter()
+            .filter(|range| range.end.y < raw_lines.len())
+            .flat_map(|range| {
+                (range.start.y..range.end.y + 1).map(|index| {
+                    let line = &raw_lines[index];
+                    let start = if index == range.start.y {
+                        display_map.display_x(index, line, range.start.x)
+                    } else {
+                        display_map.display_x(index, line, line.beginning())
+                    };
+                    let end = if index == range.end.y {
+                        display_map.display_x(index, line, range.end.x)
+                    } else {
+                        display_map.display_x(index, line, line.end())
+                    };
+                    let left = text_left + px(start as f32) * metrics.character_width - px(1.);
+                    let top = bounds.top() + px(index as f32) * context.line_height();
+                    let width = px((end - start) as f32) * metrics.character_width + px(2.);
+
+                    fill(
+                        Bounds::new(point(left, top), size(width, context.line_height())),
+                        rgb(theme.occurrence),
+                    )
+                    .corner_radii(px(3.))
+                })
+            })
+            .collect();
+
+        // Changed lines are marked at the start of the margin, next to each of their visual lines
+        let change_rectangles = if input.changed_lines.is_empty() {
+            vec![]
+        } else {
+            let wrap_points = content.wrap_points();
+            let left = bounds.left() + input.gutter_width();
+            let mut logical_index = 0;
+            let mut rectangles = vec![];
+
+            for index in 0..raw_lines.len() {
+                if index > 0 && input.buffer.starts_logical_line(index, &wrap_points) {
+                    logical_index += 1;
+                }
+
+                let Ok(change) = input
+                    .changed_lines
+                    .binary_search_by_key(&logical_index, |(line, _)| *line)
+                else {
+                    continue;
+                };
+                let color = match input.changed_lines[change].1 {
+                    LineChange::Added => theme.line_added,
+                    LineChange::Modified => theme.line_modified,
+                };
+                let top = bounds.top() + px(index as f32) * context.line_height();
+
+                rectangles.push(fill(
+                    Bounds::new(
+                        point(left, top),
+                        size(CHANGE_BAR_WIDTH, context.line_height()),
+                    ),
+                    rgb(color),
+                ));
+            }
+
+            rectangles
+        };
+
         PrepaintState {
             lines,
+            line_numbers,
             edit_location_rectangles,
+            task_rectangles,
+            rule_rectangles,
+            occurrence_rectangles,
+            change_rectangles,
         }
     }
 
@@ -1495,7 +4360,15 @@ impl Element for EditorElement {
         context: &mut gpui::WindowContext,
     ) {
         let focus_handle = self.input.read(context).focus_handle.clone();
+        let show_ruler = self.input.read(context).show_ruler;
+        let metrics = self.input.read(context).metrics;
+        let theme = self.input.read(context).theme;
+        let text_left = self.input.read(context).text_left(bounds);
+        let gutter_width = self.input.read(context).gutter_width();
         let edit_location_rectangles = prepaint.edit_location_rectangles.clone();
+        let task_rectangles = prepaint.task_rectangles.clone();
+        let rule_rectangles = prepaint.rule_rectangles.clone();
+        let occurrence_rectangles = prepaint.occurrence_rectangles.clone();
         let lines = prepaint.lines.clone();
 
         context.handle_input(
@@ -1503,33 +4376,301 @@ impl Element for EditorElement {
             ElementInputHandler::new(bounds, self.input.clone()),
         );
 
-        for rectangle in edit_location_rectangles {
-            context.paint_quad(rectangle);
+        for rectangle in prepaint.change_rectangles.iter() {
+            context.paint_quad(rectangle.clone());
         }
 
-        for (index, line) in lines.iter().enumerate() {
-            let offset = match line.raw_line.kind {
-                LineType::HeadlineStart(level) => {
-                    EDITOR_HORIZONTAL_MARGIN - px(level as f32 + 1 as f32) * CHARACTER_WIDTH
-                }
-                LineType::HeadlineNotStart => EDITOR_HORIZONTAL_MARGIN,
-                LineType::Normal => EDITOR_HORIZONTAL_MARGIN,
-            };
-
+        for (index, line_number) in prepaint.line_numbers.iter() {
             let point = Point::new(
-                bounds.origin.x + offset,
-                bounds.origin.y + (context.line_height() * index),
+                bounds.origin.x,
+                bounds.origin.y + (context.line_height() * *index),
             );
 
-            line.shaped_line
+            line_number
                 .paint(point, context.line_height(), context)
                 .unwrap();
         }
+
+        // Text scrolled to the side disappears before it reaches the line numbers
+        let text_area = Bounds::new(
+            point(bounds.left() + gutter_width, bounds.top()),
+            size(bounds.size.width - gutter_width, bounds.size.height),
+        );
+
+        context.with_content_mask(Some(ContentMask { bounds: text_area }), |context| {
+            // A guide at the column where lines wrap, painted first so it stays behind the text
+            if show_ruler {
+                let x = text_left + metrics.character_width * CHARACTER_COUNT_PER_LINE as f32;
+
+                context.paint_quad(fill(
+                    Bounds::new(point(x, bounds.top()), size(px(1.), bounds.size.height)),
+                    rgb(theme.ruler),
+                ));
+            }
+
+            for rectangle in task_rectangles {
+                context.paint_quad(rectangle);
+            }
+
+            for rectangle in rule_rectangles {
+                context.paint_quad(rectangle);
+            }
+
+            for rectangle in occurrence_rectangles {
+                context.paint_quad(rectangle);
+            }
+
+            for rectangle in edit_location_rectangles {
+                context.paint_quad(rectangle);
+            }
+
+            for (index, line) in lines.iter().enumerate() {
+                // Headline markers are rendered in the margin
+                let offset = px(line.beginning as f32) * metrics.character_width;
+
+                let point = Point::new(
+                    text_left + offset,
+                    bounds.origin.y + (context.line_height() * index),
+                );
+
+                line.shaped_line
+                    .paint(point, context.line_height(), context)
+                    .unwrap();
+            }
+        });
+    }
+}
+
+// Splits the run so the given ranges get a wavy underline
+fn underline_ranges(run: TextRun, ranges: &Vec<Range<usize>>, color: u32) -> Vec<TextRun> {
+    let mut runs = vec![];
+    let mut offset = 0;
+    let underline = UnderlineStyle {
+        thickness: px(1.),
+        color: Some(Hsla::from(rgb(color))),
+        wavy: true,
+    };
+
+    for range in ranges {
+        if range.start > offset {
+            runs.push(TextRun {
+                len: range.start - offset,
+                ..run.clone()
+            });
+        }
+
+        runs.push(TextRun {
+            len: range.len(),
+            underline: Some(underline),
+            ..run.clone()
+        });
+
+        offset = range.end;
+    }
+
+    if offset < run.len || runs.is_empty() {
+        runs.push(TextRun {
+            len: run.len - offset,
+            ..run
+        });
+    }
+
+    return runs;
+}
+
+// Splits the runs so everything outside of the range gets the color
+fn recolor_outside(runs: Vec<TextRun>, range: Range<usize>, color: Hsla) -> Vec<TextRun> {
+    let mut recolored = vec![];
+    let mut offset = 0;
+
+    for run in runs {
+        let run_range = offset..offset + run.len;
+        let mut cuts = vec![run_range.start];
+
+        for edge in [range.start, range.end] {
+            if edge > run_range.start && edge < run_range.end && !cuts.contains(&edge) {
+                cuts.push(edge);
+            }
+        }
+
+        cuts.push(run_range.end);
+
+        for cut in cuts.windows(2) {
+            let (start, end) = (cut[0], cut[1]);
+            let is_inside = start >= range.start && end <= range.end;
+
+            recolored.push(TextRun {
+                len: end - start,
+                color: if is_inside { run.color } else { color },
+                ..run.clone()
+            });
+        }
+
+        offset = run_range.end;
+    }
+
+    return recolored;
+}
+
+// Capitalizes the first letter of every word and lowercases the rest
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut is_start_of_word = true;
+
+    for character in text.chars() {
+        if character.is_whitespace() {
+            is_start_of_word = true;
+            result.push(character);
+        } else if is_start_of_word && character.is_alphanumeric() {
+            is_start_of_word = false;
+            result.extend(character.to_uppercase());
+        } else {
+            result.extend(character.to_lowercase());
+        }
+    }
+
+    return result;
+}
+
+fn headline_markers(level: usize) -> String {
+    if level == 0 {
+        return String::new();
+    }
+
+    return format!("{} ", "#".repeat(level));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListKind {
+    Unordered,
+    Ordered,
+}
+
+// The kind of list marker the text starts with and its length including the space after it
+fn list_marker(text: &str) -> Option<(ListKind, usize)> {
+    if ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| text.starts_with(marker))
+    {
+        return Some((ListKind::Unordered, 2));
+    }
+
+    let digits = text
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+
+    if digits > 0 && text[digits..].starts_with(". ") {
+        return Some((ListKind::Ordered, digits + 2));
+    }
+
+    return None;
+}
+
+// What the next line starts with so the indentation and list item carry over
+// along with how long the same prefix is on this line
+fn continuation(text: &str) -> (String, usize) {
+    let indentation_length = text.len() - text.trim_start_matches([' ', '\t']).len();
+    let (indentation, rest) = text.split_at(indentation_length);
+
+    let Some((kind, length)) = list_marker(rest) else {
+        return (indentation.to_string(), indentation_length);
+    };
+
+    let mut marker = match kind {
+        ListKind::Unordered => rest[..length].to_string(),
+        ListKind::Ordered => {
+            let number: usize = rest[..length - 2].parse().unwrap_or(0);
+
+            format!("{}. ", number + 1)
+        }
+    };
+    let mut prefix_length = indentation_length + length;
+
+    // Task items continue with an unchecked task
+    if ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .any(|checkbox| rest[length..].starts_with(checkbox))
+    {
+        marker.push_str("[ ] ");
+        prefix_length += 4;
     }
+
+    return (format!("{}{}", indentation, marker), prefix_length);
 }
 
-fn get_documents_folder_path() -> PathBuf {
-    let home = env::var("HOME").unwrap_or_else(|_| String::from("/Users/Shared"));
+fn image_extension(format: ImageFormat) -> &'static str {
+    return match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Gif => "gif",
+        ImageFormat::Webp => "webp",
+        ImageFormat::Svg => "svg",
+        _ => "png",
+    };
+}
+
+fn is_url(text: &str) -> bool {
+    let has_scheme = ["http://", "https://", "mailto:", "www."]
+        .iter()
+        .any(|scheme| text.starts_with(scheme));
+
+    return has_scheme && !text.contains(char::is_whitespace);
+}
+
+fn is_word_character(character: char) -> bool {
+    return character.is_alphanumeric() || character == '_';
+}
+
+fn normalize_line_endings(text: String) -> String {
+    return text.replace("\r\n", "\n").replace('\r', "\n");
+}
 
-    return PathBuf::from(home).join("Documents");
+fn closing_character(character: char) -> Option<char> {
+    return match character {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '`' => Some('`'),
+        '*' => Some('*'),
+        '_' => Some('_'),
+        _ => None,
+    };
+}
+
+fn is_closing_character(character: char) -> bool {
+    return matches!(character, ')' | ']' | '`' | '*' | '_');
+}
+
+// Writing can still fail, like when the disk is full or the permissions changed
+fn show_save_error(error: SaveError, context: &mut gpui::WindowContext) {
+    let message = match error {
+        SaveError::NoFileAssociated => "The document isn't saved to a file yet.".to_string(),
+        SaveError::ReadOnly => "The file can only be read, so save it somewhere else.".to_string(),
+        SaveError::InvalidExtension => {
+            "The file must have one of the extensions allowed in the settings, like .md."
+                .to_string()
+        }
+        SaveError::PermissionDenied => "You don't have permission to write there.".to_string(),
+        SaveError::NotFound => "The folder doesn't exist.".to_string(),
+        SaveError::IoError(error) => error.to_string(),
+    };
+    let error_prompt = context.prompt(
+        PromptLevel::Critical,
+        "Failed to save file",
+        Some(&message),
+        &["OK"],
+    );
+
+    context
+        .foreground_executor()
+        .spawn(async move {
+            error_prompt.await.ok();
+        })
+        .detach();
+}
+
+fn remember_save_directory(path: &Path) {
+    if let Some(directory) = path.parent() {
+        storage::write(SAVE_DIRECTORY_FILE, &directory).ok();
+    }
 }
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::buffer::has_allowed_extension;
+
+pub struct FileTree {
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    extensions: Vec<String>,
+    // Read ahead of time so drawing the sidebar doesn't touch the disk
+    entries: Vec<FileTreeEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileTreeEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub is_directory: bool,
+    pub is_expanded: bool,
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf, extensions: Vec<String>) -> FileTree {
+        let mut file_tree = FileTree {
+            root,
+            expanded: HashSet::new(),
+            extensions,
+            entries: vec![],
+        };
+
+        file_tree.refresh();
+
+        return file_tree;
+    }
+
+    pub fn name(&self) -> String {
+        return self
+            .root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.root.to_string_lossy().to_string());
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        return path.starts_with(&self.root);
+    }
+
+    pub fn toggle(&mut self, path: PathBuf) {
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+
+        self.refresh();
+    }
+
+    pub fn set_extensions(&mut self, extensions: Vec<String>) {
+        if self.extensions != extensions {
+            self.extensions = extensions;
+            self.refresh();
+        }
+    }
+
+    pub fn entries(&self) -> &[FileTreeEntry] {
+        return &self.entries;
+    }
+
+    // Reads the folders again, like when files were added or removed by another program
+    // Directories are only read when they are expanded
+    pub fn refresh(&mut self) {
+        let mut entries = vec![];
+
+        self.collect_entries(&self.root, 0, &self.extensions, &mut entries);
+
+        self.entries = entries;
+    }
+
+    fn collect_entries(
+        &self,
+        directory: &Path,
+        depth: usize,
+        extensions: &[String],
+        entries: &mut Vec<FileTreeEntry>,
+    ) {
+        for (path, is_directory) in read_directory(directory, extensions) {
+            let is_expanded = is_directory && self.expanded.contains(&path);
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            entries.push(FileTreeEntry {
+                path: path.clone(),
+                name,
+                depth,
+                is_directory,
+                is_expanded,
+            });
+
+            if is_expanded {
+                self.collect_entries(&path, depth + 1, extensions, entries);
+            }
+        }
+    }
+}
+
+// Lists the subfolders and the files that can be opened in a directory
+// Folders come first and both are sorted by name
+fn read_directory(directory: &Path, extensions: &[String]) -> Vec<(PathBuf, bool)> {
+    let mut children: Vec<(PathBuf, bool)> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let is_hidden = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().starts_with('.'))
+                        .unwrap_or(true);
+
+                    !is_hidden && (path.is_dir() || has_allowed_extension(path, extensions))
+                })
+                .map(|path| {
+                    let is_directory = path.is_dir();
+
+                    (path, is_directory)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    children.sort_by(|(a, a_is_directory), (b, b_is_directory)| {
+        b_is_directory.cmp(a_is_directory).then_with(|| a.cmp(b))
+    });
+
+    return children;
+}
@@ -0,0 +1,94 @@
+use std::path::{Component, Path, PathBuf};
+
+const LINK_TARGET_START: &str = "](";
+
+// Rewrites the relative targets of markdown links and images, like `[text](notes/idea.md)`
+// so they point at the same files after moving the text from one document to another
+// Absolute paths, URLs and anchors are left as they are
+pub fn rebase(text: &str, from_document: &Path, to_document: &Path) -> String {
+    let (Some(from), Some(to)) = (from_document.parent(), to_document.parent()) else {
+        return text.to_string();
+    };
+
+    if from == to {
+        return text.to_string();
+    }
+
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(index) = rest.find(LINK_TARGET_START) {
+        let start = index + LINK_TARGET_START.len();
+
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // A title can follow the target, like `[text](path "title")`
+        let end = rest
+            .find(|character: char| character == ')' || character.is_whitespace())
+            .unwrap_or(rest.len());
+        let target = &rest[..end];
+
+        if is_relative(target) {
+            let path = normalize(&from.join(target));
+
+            output.push_str(&relative_path(&path, to).to_string_lossy());
+        } else {
+            output.push_str(target);
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+
+    return output;
+}
+
+fn is_relative(target: &str) -> bool {
+    if target.is_empty() || target.contains("://") {
+        return false;
+    }
+
+    return !["/", "#", "<", "~", "mailto:", "www."]
+        .iter()
+        .any(|prefix| target.starts_with(prefix));
+}
+
+// Resolves the `.` and `..` in the path without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    return normalized;
+}
+
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path
+        .iter()
+        .zip(base.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+
+    for component in &path[common..] {
+        relative.push(component);
+    }
+
+    return relative;
+}
@@ -1,18 +1,36 @@
 mod buffer;
 mod content;
 mod cursor;
+mod diff;
+mod display_map;
 mod editor;
+mod file_tree;
+mod links;
+mod preview;
+mod settings;
+mod spellcheck;
+mod storage;
+mod templates;
 mod text;
+mod theme;
+mod vim;
 
 use std::{fs, ops::Index, path::PathBuf};
 
 use buffer::Buffer;
-use editor::Editor;
+use content::Statistics;
+use editor::{BufferChanged, Editor};
+use file_tree::{FileTree, FileTreeEntry};
 use gpui::{
-    actions, div, img, impl_actions, prelude::*, px, rems, rgb, size, svg, AppContext, AssetSource,
-    Bounds, FocusHandle, FocusableView, KeyBinding, Menu, MenuItem, MouseButton, PathPromptOptions,
-    PromptLevel, SharedString, View, ViewContext, WindowBounds, WindowOptions,
+    actions, div, img, impl_actions, point, prelude::*, px, relative, rems, rgb, size, svg, Action,
+    AppContext, AssetSource, Bounds, FocusHandle, FocusableView, KeyBinding, Menu, MenuItem,
+    MouseButton, PathPromptOptions, PromptLevel, SharedString, View, ViewContext, WindowBounds,
+    WindowContext, WindowOptions,
 };
+use preview::{JumpToLine, Preview};
+use settings::{Palette, Settings};
+use spellcheck::{Dictionary, Spellchecker};
+use theme::{Appearance, Theme};
 
 const COLOR_WHITE: u32 = 0xffffff;
 const COLOR_BLACK: u32 = 0x000000;
@@ -33,13 +51,26 @@ const COLOR_GRAY_950: u32 = 0x030712;
 const COLOR_BLUE_LIGHT: u32 = 0xe0f2fe;
 const COLOR_BLUE_MEDIUM: u32 = 0x7dd3fc;
 const COLOR_BLUE_DARK: u32 = 0x0ea5e9;
+const COLOR_BLUE_DARKEST: u32 = 0x0c4a6e;
+
+const COLOR_ORANGE_DARK: u32 = 0xf97316;
+
+const COLOR_GREEN: u32 = 0x22c55e;
+
+const COLOR_RED: u32 = 0xef4444;
 
 actions!(
     app,
     [
         // App
         Quit,
+        ReloadSettings,
         ToggleSidebar,
+        ToggleZenMode,
+        ToggleTheme,
+        TogglePreview,
+        SplitRight,
+        FocusNextPane,
         // Editor
         MoveLeft,
         MoveRight,
@@ -62,21 +93,72 @@ actions!(
         SelectBeginningOfWord,
         SelectEndOfWord,
         SelectAll,
+        SelectParagraph,
+        SelectWord,
+        JumpToMatchingBracket,
+        SelectToMatchingBracket,
+        JumpBack,
+        JumpForward,
+        NextMisspelling,
+        PreviousMisspelling,
         RemoveSelection,
         Backspace,
         Enter,
+        Indent,
+        Outdent,
+        InsertLink,
+        InsertHorizontalRule,
+        InsertCodeBlock,
+        InsertSpecialCharacter,
+        IncreaseHeadlineLevel,
+        DecreaseHeadlineLevel,
+        ToggleList,
+        UpperCase,
+        LowerCase,
+        TitleCase,
+        ClearFormatting,
+        FormatDocument,
+        ToggleOrderedList,
+        ToggleTask,
+        ToggleSmartPunctuation,
+        ToggleAutoCapitalize,
+        ToggleTrimTrailingWhitespace,
+        ToggleReadOnly,
+        ShowRuler,
+        ToggleLineNumbers,
+        ToggleSentenceFocus,
+        ToggleWordWrap,
+        ToggleVimMode,
+        SetWordGoal,
         // Clipboard
         Copy,
+        CopyAsPlainText,
+        CopyWithHardWraps,
         Cut,
         Paste,
         // File
         Save,
         SaveAs,
+        Revert,
         NewFile,
+        NewFromTemplate,
         OpenFile,
+        CloseTab,
+        NextTab,
+        PreviousTab,
+        OpenFolder,
+    ]
+);
+impl_actions!(
+    app,
+    [
+        SetMode,
+        SetBuffer,
+        OpenPath,
+        ToggleDirectory,
+        InsertCharacter
     ]
 );
-impl_actions!(app, [SetMode, SetBuffer]);
 
 #[derive(Clone, Default, PartialEq, serde::Deserialize, schemars::JsonSchema)]
 struct SetMode {
@@ -94,6 +176,44 @@ impl SetBuffer {
     }
 }
 
+// Opens a file, asking to save unsaved changes first
+#[derive(Clone, Default, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+struct OpenPath {
+    path: PathBuf,
+}
+
+impl OpenPath {
+    pub fn new(path: PathBuf) -> OpenPath {
+        return OpenPath { path };
+    }
+}
+
+// Inserts text that's hard to type, like a non-breaking space
+// Can be bound in the settings, like `{ "character": "→" }`
+#[derive(Clone, Default, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+struct InsertCharacter {
+    character: String,
+}
+
+impl InsertCharacter {
+    pub fn new(character: char) -> InsertCharacter {
+        return InsertCharacter {
+            character: character.to_string(),
+        };
+    }
+}
+
+#[derive(Clone, Default, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+struct ToggleDirectory {
+    path: PathBuf,
+}
+
+impl ToggleDirectory {
+    pub fn new(path: PathBuf) -> ToggleDirectory {
+        return ToggleDirectory { path };
+    }
+}
+
 impl SetMode {
     pub fn mode(mode: Mode) -> SetMode {
         SetMode { mode }
@@ -127,61 +247,87 @@ fn main() {
             base: resources_path,
         })
         .run(|context: &mut AppContext| {
-            let bounds = Bounds::centered(None, size(px(1024.), px(768.)), context);
-
-            context.bind_keys([
-                KeyBinding::new("cmd-q", Quit, None),
-                KeyBinding::new("cmd-b", ToggleSidebar, None),
-                // KeyBinding::new("cmd-1", SetMode::mode(Mode::Outline), None),
-                KeyBinding::new("cmd-2", SetMode::mode(Mode::Write), None),
-                // KeyBinding::new("cmd-3", SetMode::mode(Mode::Edit), None),
-                KeyBinding::new("left", MoveLeft, "editor".into()),
-                KeyBinding::new("right", MoveRight, "editor".into()),
-                KeyBinding::new("up", MoveUp, "editor".into()),
-                KeyBinding::new("down", MoveDown, "editor".into()),
-                KeyBinding::new("cmd-up", MoveBeginningOfFile, "editor".into()),
-                KeyBinding::new("cmd-down", MoveEndOfFile, "editor".into()),
-                KeyBinding::new("cmd-left", MoveBeginningOfLine, "editor".into()),
-                KeyBinding::new("cmd-right", MoveEndOfLine, "editor".into()),
-                KeyBinding::new("alt-left", MoveBeginningOfWord, "editor".into()),
-                KeyBinding::new("alt-right", MoveEndOfWord, "editor".into()),
-                KeyBinding::new("shift-left", SelectLeft, "editor".into()),
-                KeyBinding::new("shift-right", SelectRight, "editor".into()),
-                KeyBinding::new("shift-up", SelectUp, "editor".into()),
-                KeyBinding::new("shift-down", SelectDown, "editor".into()),
-                KeyBinding::new("cmd-shift-up", SelectBeginningOfFile, "editor".into()),
-                KeyBinding::new("cmd-shift-down", SelectEndOfFile, "editor".into()),
-                KeyBinding::new("cmd-shift-left", SelectBeginningOfLine, "editor".into()),
-                KeyBinding::new("cmd-shift-right", SelectEndOfLine, "editor".into()),
-                KeyBinding::new("alt-shift-left", SelectBeginningOfWord, "editor".into()),
-                KeyBinding::new("alt-shift-right", SelectEndOfWord, "editor".into()),
-                KeyBinding::new("cmd-a", SelectAll, "editor".into()),
-                KeyBinding::new("escape", RemoveSelection, "editor".into()),
-                KeyBinding::new("backspace", Backspace, "editor".into()),
-                KeyBinding::new("enter", Enter, "editor".into()),
-                KeyBinding::new("cmd-c", Copy, "editor".into()),
-                KeyBinding::new("cmd-x", Cut, "editor".into()),
-                KeyBinding::new("cmd-v", Paste, "editor".into()),
-                KeyBinding::new("cmd-s", Save, None),
-                KeyBinding::new("cmd-shift-s", SaveAs, None),
-                KeyBinding::new("cmd-n", NewFile, None),
-                KeyBinding::new("cmd-o", OpenFile, None),
-            ]);
+            let window_state: WindowState = storage::read(WINDOW_STATE_FILE).unwrap_or_default();
+            let window_bounds = window_state.window_bounds(context).unwrap_or_else(|| {
+                WindowBounds::Windowed(Bounds::centered(None, size(px(1024.), px(768.)), context))
+            });
+
+            context.set_global(Settings::load(context));
+            bind_keys(context);
 
             context.on_action(quit);
+            context.on_action(reload_settings);
 
             context.set_menus(vec![
                 Menu {
                     name: "Wordsmith".into(),
-                    items: vec![MenuItem::action("Quit", Quit)],
+                    items: vec![
+                        MenuItem::action("Toggle Dark Mode", ToggleTheme),
+                        MenuItem::action("Reload Settings", ReloadSettings),
+                        MenuItem::separator(),
+                        MenuItem::action("Quit", Quit),
+                    ],
                 },
                 Menu {
                     name: "File".into(),
                     items: vec![
                         MenuItem::action("New", NewFile),
-                        MenuItem::action("Save", Save),
-                        MenuItem::action("Save as...", SaveAs),
+                        MenuItem::action("New from Template...", NewFromTemplate),
                         MenuItem::action("Open...", OpenFile),
+                        MenuItem::action("Open Folder...", OpenFolder),
+                        MenuItem::separator(),
+                        MenuItem::action("Save", Save),
+                        MenuItem::action("Save As...", SaveAs),
+                        MenuItem::action("Revert to Saved", Revert),
+                        MenuItem::separator(),
+                        MenuItem::action("Next Tab", NextTab),
+                        MenuItem::action("Previous Tab", PreviousTab),
+                        MenuItem::action("Close Tab", CloseTab),
+                        MenuItem::separator(),
+                        MenuItem::action("Split Right", SplitRight),
+                        MenuItem::action("Focus Next Pane", FocusNextPane),
+                    ],
+                },
+                Menu {
+                    name: "Edit".into(),
+                    items: vec![
+                        MenuItem::action("Smart Punctuation", ToggleSmartPunctuation),
+                        MenuItem::action("Capitalize Sentences", ToggleAutoCapitalize),
+                        MenuItem::action(
+                            "Trim Trailing Whitespace on Save",
+                            ToggleTrimTrailingWhitespace,
+                        ),
+                        MenuItem::action("Read Only", ToggleReadOnly),
+                        MenuItem::action("Show Ruler", ShowRuler),
+                        MenuItem::action("Show Line Numbers", ToggleLineNumbers),
+                        MenuItem::action("Focus on Sentence", ToggleSentenceFocus),
+                        MenuItem::action("Wrap Lines", ToggleWordWrap),
+                        MenuItem::action("Vim Mode", ToggleVimMode),
+                        MenuItem::action("Show Preview", TogglePreview),
+                        MenuItem::action("Set Word Goal...", SetWordGoal),
+                        MenuItem::separator(),
+                        MenuItem::action("Copy as Plain Text", CopyAsPlainText),
+                        MenuItem::action("Copy with Hard Wraps", CopyWithHardWraps),
+                        MenuItem::separator(),
+                        MenuItem::action("Insert Link", InsertLink),
+                        MenuItem::action("Insert Horizontal Rule", InsertHorizontalRule),
+                        MenuItem::action("Insert Code Block", InsertCodeBlock),
+                        MenuItem::action("Insert Special Character...", InsertSpecialCharacter),
+                        MenuItem::action("Toggle List", ToggleList),
+                        MenuItem::action("Toggle Numbered List", ToggleOrderedList),
+                        MenuItem::action("Toggle Task", ToggleTask),
+                        MenuItem::separator(),
+                        MenuItem::action("Make Upper Case", UpperCase),
+                        MenuItem::action("Make Lower Case", LowerCase),
+                        MenuItem::action("Make Title Case", TitleCase),
+                        MenuItem::action("Clear Formatting", ClearFormatting),
+                        MenuItem::action("Format Document", FormatDocument),
+                        MenuItem::separator(),
+                        MenuItem::action("Next Misspelling", NextMisspelling),
+                        MenuItem::action("Previous Misspelling", PreviousMisspelling),
+                        MenuItem::separator(),
+                        MenuItem::action("Jump Back", JumpBack),
+                        MenuItem::action("Jump Forward", JumpForward),
                     ],
                 },
             ]);
@@ -189,15 +335,120 @@ fn main() {
             let window = context
                 .open_window(
                     WindowOptions {
-                        window_bounds: Some(WindowBounds::Windowed(bounds)),
+                        window_bounds: Some(window_bounds),
                         ..Default::default()
                     },
                     |context| {
-                        let editor = context.new_view(|context| {
-                            Editor::new(Buffer::empty(), context.focus_handle())
+                        let spellchecker =
+                            Spellchecker::new(Dictionary::load(context.asset_source().as_ref()));
+                        let editor =
+                            new_editor(spellchecker.clone(), window_state.mode.clone(), context);
+
+                        let wordsmith = context.new_view(|context| {
+                            let preview = context.new_view(|_| Preview::new());
+                            let focus_handle = context.focus_handle();
+
+                            Wordsmith::observe_editor(&editor, context);
+                            // Clicking outside the text, like on the sidebar, hands the keyboard
+                            // back to the editor with the cursor or selection where it was left
+                            context
+                                .on_focus(&focus_handle, |wordsmith: &mut Wordsmith, context| {
+                                    context.focus_view(&wordsmith.active_editor().clone());
+                                })
+                                .detach();
+                            context
+                                .observe_window_bounds(|wordsmith: &mut Wordsmith, context| {
+                                    wordsmith.window_bounds = Some(context.window_bounds());
+                                })
+                                .detach();
+                            context
+                                .subscribe(
+                                    &preview,
+                                    |wordsmith: &mut Wordsmith, _, event: &JumpToLine, context| {
+                                        wordsmith.jump_to_line(event.0, context)
+                                    },
+                                )
+                                .detach();
+                            // The palette the theme is built from can change with the settings
+                            context
+                                .observe_global::<Settings>(|wordsmith: &mut Wordsmith, context| {
+                                    wordsmith.set_appearance(wordsmith.theme.appearance, context);
+                                    wordsmith.show_settings_errors(context);
+
+                                    if let Some(file_tree) = wordsmith.file_tree.as_mut() {
+                                        file_tree.set_extensions(
+                                            Settings::get(context).file_extensions.clone(),
+                                        );
+                                    }
+                                })
+                                .detach();
+                            // Files might have been added or removed while the window was in the background
+                            context
+                                .observe_window_activation(|wordsmith: &mut Wordsmith, context| {
+                                    if !context.is_window_active() {
+                                        return;
+                                    }
+
+                                    if let Some(file_tree) = wordsmith.file_tree.as_mut() {
+                                        file_tree.refresh();
+                                    }
+
+                                    context.notify();
+                                })
+                                .detach();
+                            context
+                                .on_app_quit(|wordsmith: &mut Wordsmith, _| {
+                                    wordsmith.save_window_state();
+
+                                    async {}
+                                })
+                                .detach();
+                            context
+                                .on_release(|wordsmith: &mut Wordsmith, _| {
+                                    wordsmith.save_window_state();
+                                })
+                                .detach();
+
+                            let statistics = editor.read(context).statistics();
+                            let mut wordsmith = Wordsmith::new(
+                                focus_handle,
+                                editor,
+                                spellchecker,
+                                preview,
+                                statistics,
+                            );
+                            wordsmith.show_sidebar = window_state.show_sidebar;
+                            wordsmith.mode = window_state.mode.clone();
+                            wordsmith.window_bounds = Some(context.window_bounds());
+                            wordsmith.set_appearance(window_state.appearance, context);
+                            // The window has to be open before it can show a prompt
+                            context.defer(|wordsmith, context| {
+                                wordsmith.show_settings_errors(context)
+                            });
+
+                            wordsmith
+                        });
+
+                        // Closing the window asks about unsaved changes just like quitting does
+                        context.on_window_should_close({
+                            let wordsmith = wordsmith.clone();
+
+                            move |context| {
+                                wordsmith.update(context, |wordsmith, context| {
+                                    if wordsmith.pristine(context) {
+                                        return true;
+                                    }
+
+                                    wordsmith.prompt_to_save_before_closing(context, |context| {
+                                        context.remove_window()
+                                    });
+
+                                    return false;
+                                })
+                            }
                         });
 
-                        context.new_view(|context| Wordsmith::new(context.focus_handle(), editor))
+                        wordsmith
                     },
                 )
                 .unwrap();
@@ -212,7 +463,7 @@ fn main() {
 
             window
                 .update(context, |view, context| {
-                    context.focus_view(&view.editor);
+                    context.focus_view(view.active_editor());
                     context.activate(true);
                 })
                 .unwrap();
@@ -223,6 +474,95 @@ struct Assets {
     base: PathBuf,
 }
 
+const WINDOW_STATE_FILE: &str = "window.json";
+
+// What the window looked like when the app was last closed
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct WindowState {
+    bounds: Option<SavedBounds>,
+    maximized: bool,
+    show_sidebar: bool,
+    mode: Mode,
+    appearance: Appearance,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct SavedBounds {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        WindowState {
+            bounds: None,
+            maximized: false,
+            show_sidebar: true,
+            mode: Mode::default(),
+            appearance: Appearance::default(),
+        }
+    }
+}
+
+impl WindowState {
+    fn new(
+        window_bounds: Option<WindowBounds>,
+        show_sidebar: bool,
+        mode: Mode,
+        appearance: Appearance,
+    ) -> WindowState {
+        let (bounds, maximized) = match window_bounds {
+            Some(WindowBounds::Windowed(bounds)) => (Some(bounds), false),
+            Some(WindowBounds::Maximized(bounds)) => (Some(bounds), true),
+            // Fullscreen is only remembered as the size it returns to
+            Some(WindowBounds::Fullscreen(bounds)) => (Some(bounds), false),
+            None => (None, false),
+        };
+
+        WindowState {
+            bounds: bounds.map(|bounds| SavedBounds {
+                x: f32::from(bounds.origin.x),
+                y: f32::from(bounds.origin.y),
+                width: f32::from(bounds.size.width),
+                height: f32::from(bounds.size.height),
+            }),
+            maximized,
+            show_sidebar,
+            mode,
+            appearance,
+        }
+    }
+
+    // The saved bounds, unless they would put the window somewhere that can't be seen
+    // like on a display that has been disconnected since
+    fn window_bounds(&self, context: &AppContext) -> Option<WindowBounds> {
+        let saved = self.bounds.clone()?;
+        let bounds = Bounds::new(
+            point(px(saved.x), px(saved.y)),
+            size(px(saved.width), px(saved.height)),
+        );
+
+        let is_visible = context.displays().iter().any(|display| {
+            let visible = display.bounds().intersect(&bounds);
+
+            visible.size.width >= px(100.) && visible.size.height >= px(100.)
+        });
+
+        if !is_visible {
+            return None;
+        }
+
+        if self.maximized {
+            return Some(WindowBounds::Maximized(bounds));
+        }
+
+        return Some(WindowBounds::Windowed(bounds));
+    }
+}
+
 impl AssetSource for Assets {
     fn load(&self, path: &str) -> gpui::Result<Option<std::borrow::Cow<'static, [u8]>>> {
         fs::read(self.base.join(path))
@@ -250,17 +590,409 @@ struct Wordsmith {
     focus_handle: FocusHandle,
     show_sidebar: bool,
     mode: Mode,
-    editor: View<Editor>,
+    // One editor per pane, from left to right
+    editors: Vec<View<Editor>>,
+    // The file each pane had open when it last changed, to notice when it opens another one
+    editor_paths: Vec<Option<PathBuf>>,
+    active_editor: usize,
+    spellchecker: Spellchecker,
+    file_tree: Option<FileTree>,
+    window_bounds: Option<WindowBounds>,
+    zen: bool,
+    // Whether entering zen mode also made the window fullscreen
+    // so leaving it only exits fullscreen when we were the ones entering it
+    zen_entered_fullscreen: bool,
+    theme: Theme,
+    statistics: Statistics,
+    preview: View<Preview>,
+    show_preview: bool,
 }
 
 impl Wordsmith {
-    pub fn new(focus_handle: FocusHandle, editor: View<Editor>) -> Wordsmith {
+    pub fn new(
+        focus_handle: FocusHandle,
+        editor: View<Editor>,
+        spellchecker: Spellchecker,
+        preview: View<Preview>,
+        statistics: Statistics,
+    ) -> Wordsmith {
         Wordsmith {
             focus_handle,
             show_sidebar: true,
             mode: Mode::Write,
-            editor,
+            editors: vec![editor],
+            editor_paths: vec![None],
+            active_editor: 0,
+            spellchecker,
+            file_tree: None,
+            window_bounds: None,
+            zen: false,
+            zen_entered_fullscreen: false,
+            theme: Theme::light(&Palette::default()),
+            statistics,
+            preview,
+            show_preview: false,
+        }
+    }
+
+    fn save_window_state(&self) {
+        let state = WindowState::new(
+            self.window_bounds.clone(),
+            self.show_sidebar,
+            self.mode.clone(),
+            self.theme.appearance,
+        );
+
+        storage::write(WINDOW_STATE_FILE, &state).ok();
+    }
+
+    fn observe_editor(editor: &View<Editor>, context: &mut ViewContext<Self>) {
+        // Re-render when the editor changes so the sidebar stays up to date
+        context
+            .observe(editor, |wordsmith: &mut Wordsmith, editor, context| {
+                wordsmith.editor_changed(editor, context)
+            })
+            .detach();
+        // Keep the word count up to date as the text changes
+        context
+            .subscribe(
+                editor,
+                |wordsmith: &mut Wordsmith, editor, event, context| {
+                    wordsmith.buffer_changed(editor, event, context)
+                },
+            )
+            .detach();
+        // The sidebar and preview follow the pane being typed in
+        context
+            .on_focus(
+                &editor.focus_handle(context),
+                |wordsmith: &mut Wordsmith, context| wordsmith.pane_focused(context),
+            )
+            .detach();
+    }
+
+    fn active_editor(&self) -> &View<Editor> {
+        return &self.editors[self.active_editor];
+    }
+
+    fn editor_changed(&mut self, editor: View<Editor>, context: &mut ViewContext<Self>) {
+        self.sync_panes(&editor, context);
+
+        if &editor != self.active_editor() {
+            return;
+        }
+
+        // Show the folder of the opened file unless it's already part of the tree
+        if let Some(path) = editor.read(context).path() {
+            let is_in_tree = self
+                .file_tree
+                .as_ref()
+                .map_or(false, |file_tree| file_tree.contains(&path));
+
+            if !is_in_tree {
+                if let Some(directory) = path.parent() {
+                    self.file_tree = Some(FileTree::new(
+                        directory.to_path_buf(),
+                        Settings::get(context).file_extensions.clone(),
+                    ));
+                }
+            }
+        }
+
+        self.sync_preview_scroll(context);
+
+        context.notify();
+    }
+
+    fn buffer_changed(
+        &mut self,
+        editor: View<Editor>,
+        event: &BufferChanged,
+        context: &mut ViewContext<Self>,
+    ) {
+        self.sync_panes(&editor, context);
+
+        if &editor != self.active_editor() {
+            return;
+        }
+
+        self.statistics = event.content.statistics();
+
+        if self.show_preview {
+            self.preview.update(context, |preview, context| {
+                preview.set_content(&event.content, context);
+            });
+        }
+
+        context.notify();
+    }
+
+    // Panes with the same file open edit the same text, as if they shared one document
+    // A pane that just opened the file takes the text from the other one, so unsaved changes aren't lost
+    fn sync_panes(&mut self, editor: &View<Editor>, context: &mut ViewContext<Self>) {
+        let Some(index) = self.editors.iter().position(|pane| pane == editor) else {
+            return;
+        };
+        let path = editor.read(context).path();
+        let opened = path != self.editor_paths[index];
+
+        self.editor_paths[index] = path.clone();
+
+        if path.is_none() {
+            return;
+        }
+
+        let others: Vec<View<Editor>> = self
+            .editors
+            .iter()
+            .filter(|pane| *pane != editor && pane.read(context).path() == path)
+            .cloned()
+            .collect();
+
+        for other in others {
+            let (source, target) = if opened {
+                (other, editor.clone())
+            } else {
+                (editor.clone(), other)
+            };
+            let (content, is_saved) = {
+                let source = source.read(context);
+
+                (source.content(), source.is_saved())
+            };
+
+            target.update(context, |target, context| {
+                target.mirror(content, is_saved, context);
+            });
+        }
+    }
+
+    fn pane_focused(&mut self, context: &mut ViewContext<Self>) {
+        let focused = self
+            .editors
+            .iter()
+            .position(|editor| editor.focus_handle(context).is_focused(context));
+
+        if let Some(index) = focused {
+            self.activate_pane(index, context);
+        }
+    }
+
+    fn activate_pane(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        if index == self.active_editor {
+            return;
+        }
+
+        self.active_editor = index;
+        self.active_pane_changed(context);
+    }
+
+    // The sidebar and preview show the document in the active pane
+    fn active_pane_changed(&mut self, context: &mut ViewContext<Self>) {
+        self.statistics = self.active_editor().read(context).statistics();
+
+        if self.show_preview {
+            let content = self.active_editor().read(context).content();
+
+            self.preview.update(context, |preview, context| {
+                preview.set_content(&content, context);
+            });
+        }
+
+        context.notify();
+    }
+
+    // Opens a second pane to the right with the same file, or closes it again
+    fn split_right(&mut self, _: &SplitRight, context: &mut ViewContext<Self>) {
+        if self.editors.len() > 1 {
+            self.close_pane(self.editors.len() - 1, context);
+
+            return;
+        }
+
+        let editor = new_editor(self.spellchecker.clone(), self.mode.clone(), context);
+        let path = self.active_editor().read(context).path();
+        let theme = self.theme;
+        let zen = self.zen;
+
+        Wordsmith::observe_editor(&editor, context);
+
+        editor.update(context, |editor, context| {
+            editor.set_theme(theme, context);
+            editor.set_zen(zen, context);
+
+            if let Some(path) = path {
+                editor.open(path, context);
+            }
+        });
+
+        self.editors.push(editor.clone());
+        self.editor_paths.push(None);
+
+        context.focus_view(&editor);
+        context.notify();
+    }
+
+    fn focus_next_pane(&mut self, _: &FocusNextPane, context: &mut ViewContext<Self>) {
+        let index = (self.active_editor + 1) % self.editors.len();
+
+        context.focus_view(&self.editors[index].clone());
+        self.activate_pane(index, context);
+    }
+
+    // Asks about the unsaved changes in the pane before closing it
+    fn close_pane(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        let editor = self.editors[index].clone();
+
+        if editor.read(context).pristine() {
+            self.remove_pane(index, context);
+
+            return;
+        }
+
+        let wordsmith = context.view().downgrade();
+        let entity_id = editor.entity_id();
+
+        editor.update(context, |editor, context| {
+            editor.prompt_to_save_before_closing(context, move |_, context| {
+                wordsmith
+                    .update(context, |wordsmith, context| {
+                        // The other pane could have been closed in the meantime
+                        let index = wordsmith
+                            .editors
+                            .iter()
+                            .position(|pane| pane.entity_id() == entity_id);
+
+                        if let Some(index) = index {
+                            wordsmith.remove_pane(index, context);
+                        }
+                    })
+                    .ok();
+            });
+        });
+    }
+
+    fn remove_pane(&mut self, index: usize, context: &mut ViewContext<Self>) {
+        self.editors.remove(index);
+        self.editor_paths.remove(index);
+        self.active_editor = 0;
+        self.active_pane_changed(context);
+
+        context.focus_view(&self.active_editor().clone());
+    }
+
+    fn pristine(&self, context: &AppContext) -> bool {
+        return self
+            .editors
+            .iter()
+            .all(|editor| editor.read(context).pristine());
+    }
+
+    // Asks about the unsaved changes in one pane after the other
+    // The continuation runs once every pane has been dealt with
+    fn prompt_to_save_before_closing<F>(&mut self, context: &mut ViewContext<Self>, continuation: F)
+    where
+        F: FnOnce(&mut WindowContext) + Send + 'static,
+    {
+        let unsaved = self
+            .editors
+            .iter()
+            .find(|editor| !editor.read(context).pristine())
+            .cloned();
+        let Some(editor) = unsaved else {
+            continuation(context);
+
+            return;
+        };
+        let wordsmith = context.view().downgrade();
+
+        editor.update(context, |editor, context| {
+            editor.prompt_to_save_before_closing(context, move |_, context| {
+                wordsmith
+                    .update(context, |wordsmith, context| {
+                        wordsmith.prompt_to_save_before_closing(context, continuation);
+                    })
+                    .ok();
+            });
+        });
+    }
+
+    fn toggle_preview(&mut self, _: &TogglePreview, context: &mut ViewContext<Self>) {
+        self.show_preview = !self.show_preview;
+
+        // The preview isn't kept up to date while it's hidden
+        if self.show_preview {
+            let content = self.active_editor().read(context).content();
+
+            self.preview.update(context, |preview, context| {
+                preview.set_content(&content, context);
+            });
+        }
+
+        context.notify();
+    }
+
+    // Keeps the top of the preview at the same place in the document as the top of the editor
+    fn sync_preview_scroll(&mut self, context: &mut ViewContext<Self>) {
+        if !self.show_preview {
+            return;
+        }
+
+        let y = self.active_editor().read(context).top_visible_line();
+
+        self.preview.update(context, |preview, context| {
+            preview.scroll_to_line(y, context);
+        });
+    }
+
+    fn jump_to_line(&mut self, y: usize, context: &mut ViewContext<Self>) {
+        let editor = self.active_editor().clone();
+
+        editor.update(context, |editor, context| {
+            editor.jump_to_line(y, context);
+        });
+
+        context.focus_view(&editor);
+    }
+
+    fn open_folder(&mut self, _: &OpenFolder, context: &mut ViewContext<Self>) {
+        let paths = context.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+
+        context
+            .spawn(|wordsmith, mut context| async move {
+                if let Ok(Ok(Some(paths))) = paths.await {
+                    if let Some(path) = paths.first() {
+                        wordsmith
+                            .update(&mut context, |wordsmith, context| {
+                                wordsmith.file_tree = Some(FileTree::new(
+                                    path.clone(),
+                                    Settings::get(context).file_extensions.clone(),
+                                ));
+
+                                context.notify();
+                            })
+                            .ok();
+                    }
+                }
+            })
+            .detach();
+    }
+
+    fn toggle_directory(&mut self, action: &ToggleDirectory, context: &mut ViewContext<Self>) {
+        if let Some(file_tree) = self.file_tree.as_mut() {
+            file_tree.toggle(action.path.clone());
         }
+
+        context.notify();
+    }
+
+    // Quitting waits for the unsaved changes to be saved or discarded
+    fn quit(&mut self, _: &Quit, context: &mut ViewContext<Self>) {
+        self.prompt_to_save_before_closing(context, |context| context.quit());
     }
 
     fn toggle_sidebar(&mut self, _: &ToggleSidebar, context: &mut ViewContext<Self>) {
@@ -269,9 +1001,88 @@ impl Wordsmith {
         context.notify();
     }
 
+    // The sidebar is hidden while in zen mode without touching show_sidebar
+    // so it comes back the way it was when leaving
+    fn toggle_zen_mode(&mut self, _: &ToggleZenMode, context: &mut ViewContext<Self>) {
+        self.zen = !self.zen;
+
+        if self.zen {
+            self.zen_entered_fullscreen = !context.is_fullscreen();
+
+            if self.zen_entered_fullscreen {
+                context.toggle_fullscreen();
+            }
+        } else if self.zen_entered_fullscreen {
+            if context.is_fullscreen() {
+                context.toggle_fullscreen();
+            }
+
+            self.zen_entered_fullscreen = false;
+        }
+
+        let zen = self.zen;
+
+        for editor in self.editors.clone() {
+            editor.update(context, |editor, context| {
+                editor.set_zen(zen, context);
+            });
+        }
+
+        context.notify();
+    }
+
+    fn toggle_theme(&mut self, _: &ToggleTheme, context: &mut ViewContext<Self>) {
+        self.set_appearance(self.theme.appearance.toggled(), context);
+    }
+
+    fn show_settings_errors(&mut self, context: &mut ViewContext<Self>) {
+        let errors = &Settings::get(context).errors;
+
+        if errors.is_empty() {
+            return;
+        }
+
+        let detail = errors.join("\n");
+        let prompt = context.prompt(
+            PromptLevel::Warning,
+            "Some settings couldn't be applied",
+            Some(&detail),
+            &["OK"],
+        );
+
+        context
+            .foreground_executor()
+            .spawn(async {
+                prompt.await.ok();
+            })
+            .detach();
+    }
+
+    fn set_appearance(&mut self, appearance: Appearance, context: &mut ViewContext<Self>) {
+        let theme = Theme::new(appearance, &Settings::get(context).palette);
+
+        self.theme = theme;
+        for editor in self.editors.clone() {
+            editor.update(context, |editor, context| {
+                editor.set_theme(theme, context);
+            });
+        }
+        self.preview.update(context, |preview, context| {
+            preview.set_theme(theme, context);
+        });
+
+        context.notify();
+    }
+
     fn set_mode(&mut self, event: &SetMode, context: &mut ViewContext<Self>) {
         self.mode = event.mode.clone();
 
+        for editor in self.editors.clone() {
+            editor.update(context, |editor, context| {
+                editor.set_mode(event.mode.clone(), context);
+            });
+        }
+
         context.notify();
     }
 }
@@ -284,31 +1095,72 @@ impl FocusableView for Wordsmith {
 
 impl Render for Wordsmith {
     fn render(&mut self, context: &mut gpui::ViewContext<Self>) -> impl gpui::IntoElement {
-        let children = if self.show_sidebar {
-            vec![
-                main_content(self.editor.clone()),
-                sidebar(self.mode.clone()),
-            ]
-        } else {
-            vec![main_content(self.editor.clone())]
-        };
+        let editor = self.active_editor().read(context);
+        let read_only = editor.read_only();
+        let file_read_only = editor.file_read_only();
+        let active_path = editor.path();
+        let word_goal = editor.word_goal();
+        let selection_counts = editor.selection_counts();
+        let theme = self.theme;
+        let font_family = Settings::get(context).font_family.clone();
+        let file_tree = self
+            .file_tree
+            .as_ref()
+            .map(|file_tree| (file_tree.name(), file_tree.entries().to_vec()));
+        let mut children: Vec<gpui::Div> = self
+            .editors
+            .iter()
+            .enumerate()
+            .map(|(index, editor)| {
+                // A line between the panes, like the one beside the sidebar
+                main_content(editor.clone()).when(index > 0, |this| {
+                    this.border_l_1().border_color(rgb(theme.border))
+                })
+            })
+            .collect();
+
+        if self.show_preview && !self.zen {
+            children.push(preview_pane(self.preview.clone()));
+        }
+
+        if self.show_sidebar && !self.zen {
+            children.push(sidebar(
+                theme,
+                self.mode.clone(),
+                read_only,
+                file_read_only,
+                self.statistics.clone(),
+                word_goal,
+                selection_counts,
+                file_tree,
+                active_path,
+            ));
+        }
 
         div()
             .flex()
             .flex_row()
             .track_focus(&self.focus_handle(context))
+            .on_action(context.listener(Self::quit))
             .on_action(context.listener(Self::toggle_sidebar))
+            .on_action(context.listener(Self::toggle_zen_mode))
+            .on_action(context.listener(Self::toggle_theme))
+            .on_action(context.listener(Self::toggle_preview))
+            .on_action(context.listener(Self::split_right))
+            .on_action(context.listener(Self::focus_next_pane))
             .on_action(context.listener(Self::set_mode))
-            .bg(rgb(COLOR_WHITE))
+            .on_action(context.listener(Self::open_folder))
+            .on_action(context.listener(Self::toggle_directory))
+            .bg(rgb(theme.background))
             .size_full()
-            .font_family("MonoLisa")
-            .text_color(rgb(COLOR_BLACK))
+            .font_family(font_family)
+            .text_color(rgb(theme.text))
             .children(children)
     }
 }
 
-#[derive(Clone, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
-enum Mode {
+#[derive(Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum Mode {
     Outline,
     Write,
     Edit,
@@ -320,22 +1172,249 @@ impl Default for Mode {
     }
 }
 
+fn new_editor(spellchecker: Spellchecker, mode: Mode, context: &mut WindowContext) -> View<Editor> {
+    return context.new_view(|context| {
+        // Check for changes made by other programs when coming back to the window
+        context
+            .observe_window_activation(|editor: &mut Editor, context| {
+                editor.window_activation_changed(context)
+            })
+            .detach();
+        context
+            .on_app_quit(|editor: &mut Editor, _| {
+                editor.remember_cursor_position();
+
+                async {}
+            })
+            .detach();
+        context
+            .observe_global::<Settings>(|editor: &mut Editor, context| {
+                let settings = Settings::get(context).clone();
+
+                editor.apply_settings(&settings, context);
+            })
+            .detach();
+
+        let settings = Settings::get(context).clone();
+        let mut editor = Editor::new(Buffer::empty(), context.focus_handle(), spellchecker);
+        editor.set_mode(mode, context);
+        editor.apply_settings(&settings, context);
+
+        editor
+    });
+}
+
 fn main_content(editor: View<Editor>) -> gpui::Div {
     div().flex().justify_center().flex_1().child(editor)
 }
 
-fn sidebar(mode: Mode) -> gpui::Div {
+fn preview_pane(preview: View<Preview>) -> gpui::Div {
+    div().flex().flex_1().child(preview)
+}
+
+fn sidebar(
+    theme: Theme,
+    mode: Mode,
+    read_only: bool,
+    file_read_only: bool,
+    statistics: Statistics,
+    word_goal: Option<usize>,
+    selection_counts: Option<(usize, usize)>,
+    file_tree: Option<(String, Vec<FileTreeEntry>)>,
+    active_path: Option<PathBuf>,
+) -> gpui::Div {
     div()
+        .flex()
+        .flex_col()
+        .gap_4()
         .w(rems(15.))
         .border_l_1()
-        .border_color(rgb(COLOR_GRAY_100))
+        .border_color(rgb(theme.border))
         .p(rems(1.))
-        .children(vec![mode_selector(mode)])
+        .child(mode_selector(theme, mode))
+        .when(read_only, |this| {
+            this.child(read_only_indicator(theme, "Read only"))
+        })
+        .when(file_read_only, |this| {
+            this.child(read_only_indicator(theme, "Read-only file"))
+        })
+        .child(statistics_view(
+            theme,
+            statistics,
+            word_goal,
+            selection_counts,
+        ))
+        .when_some(file_tree, |this, (name, entries)| {
+            this.child(file_tree_view(theme, name, entries, active_path))
+        })
+}
+
+fn file_tree_view(
+    theme: Theme,
+    name: String,
+    entries: Vec<FileTreeEntry>,
+    active_path: Option<PathBuf>,
+) -> gpui::Div {
+    div()
+        .flex()
+        .flex_col()
+        .text_size(px(12.))
+        .child(
+            div()
+                .pb_1()
+                .text_size(px(10.))
+                .text_color(rgb(theme.text_muted))
+                .child(name.to_uppercase()),
+        )
+        .children(entries.into_iter().map(|entry| {
+            let is_active = active_path.as_ref() == Some(&entry.path);
+            let icon = match (entry.is_directory, entry.is_expanded) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            let path = entry.path.clone();
+            let is_directory = entry.is_directory;
+
+            div()
+                .flex()
+                .flex_row()
+                .py(px(2.))
+                .pl(px(12. * entry.depth as f32))
+                .rounded(px(3.))
+                .text_color(rgb(theme.body))
+                .when(is_active, |this| {
+                    this.bg(rgb(theme.accent_background))
+                        .text_color(rgb(theme.accent))
+                })
+                .when(!is_active, |this| {
+                    this.hover(|this| this.bg(rgb(theme.surface)))
+                })
+                .child(format!("{}{}", icon, entry.name))
+                .on_mouse_up(MouseButton::Left, move |_event, context| {
+                    if is_directory {
+                        context.dispatch_action(Box::new(ToggleDirectory::new(path.clone())));
+                    } else {
+                        context.dispatch_action(Box::new(OpenPath::new(path.clone())));
+                    }
+                })
+        }))
+}
+
+fn statistics_view(
+    theme: Theme,
+    statistics: Statistics,
+    word_goal: Option<usize>,
+    selection_counts: Option<(usize, usize)>,
+) -> gpui::Div {
+    let words = match (statistics.words, word_goal) {
+        (words, Some(goal)) => format!("{} / {} words", words, goal),
+        (1, None) => "1 word".to_string(),
+        (words, None) => format!("{} words", words),
+    };
+    let lines = match statistics.lines {
+        1 => "1 line".to_string(),
+        lines => format!("{} lines", lines),
+    };
+    let reading_time = match statistics.reading_minutes {
+        0 => "< 1 min read".to_string(),
+        minutes => format!("{} min read", minutes),
+    };
+    // Documents without a task list don't need to mention tasks
+    let tasks = match statistics.tasks {
+        0 => None,
+        1 => Some(format!("{}/1 task done", statistics.completed_tasks)),
+        tasks => Some(format!(
+            "{}/{} tasks done",
+            statistics.completed_tasks, tasks
+        )),
+    };
+    let selected = selection_counts.map(|(words, characters)| {
+        format!(
+            "{} {}, {} {} selected",
+            words,
+            if words == 1 { "word" } else { "words" },
+            characters,
+            if characters == 1 {
+                "character"
+            } else {
+                "characters"
+            }
+        )
+    });
+
+    div()
+        .flex()
+        .flex_col()
+        .text_size(px(10.))
+        .text_color(rgb(theme.text_muted))
+        .child(words)
+        .when_some(word_goal, |this, goal| {
+            this.child(word_goal_progress(theme, statistics.words, goal))
+        })
+        .child(reading_time)
+        .child(lines)
+        .children(tasks)
+        .when_some(selected, |this, selected| {
+            this.child(div().text_color(rgb(theme.accent)).child(selected))
+        })
+}
+
+fn word_goal_progress(theme: Theme, words: usize, goal: usize) -> gpui::Div {
+    let reached = words >= goal;
+    let progress = (words as f32 / goal.max(1) as f32).min(1.);
+    let color = if reached {
+        theme.accent
+    } else {
+        theme.text_muted
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .py_1()
+        .child(
+            div()
+                .h(px(4.))
+                .w_full()
+                .rounded_full()
+                .bg(rgb(theme.surface))
+                .child(
+                    div()
+                        .h_full()
+                        .w(relative(progress))
+                        .rounded_full()
+                        .bg(rgb(color)),
+                ),
+        )
+        .when(reached, |this| {
+            this.child(div().text_color(rgb(theme.accent)).child("🎉 Goal reached"))
+        })
+}
+
+fn read_only_indicator(theme: Theme, label: &'static str) -> gpui::Div {
+    div()
+        .flex()
+        .flex_row()
+        .items_center()
+        .gap_1()
+        .text_color(rgb(theme.warning))
+        .text_size(px(10.))
+        .children(vec![
+            svg()
+                .path("icons/lock.svg")
+                .size_4()
+                .text_color(rgb(theme.warning))
+                .into_any_element(),
+            div().child(label).into_any_element(),
+        ])
 }
 
-fn mode_selector(mode: Mode) -> gpui::Div {
+fn mode_selector(theme: Theme, mode: Mode) -> gpui::Div {
     div().flex().flex_row().gap_2().children(vec![
         radio_button(
+            theme,
             "Outline",
             "icons/outline.svg",
             mode == Mode::Outline,
@@ -343,6 +1422,7 @@ fn mode_selector(mode: Mode) -> gpui::Div {
             Mode::Outline,
         ),
         radio_button(
+            theme,
             "Write",
             "icons/write.svg",
             mode == Mode::Write,
@@ -350,16 +1430,18 @@ fn mode_selector(mode: Mode) -> gpui::Div {
             Mode::Write,
         ),
         radio_button(
+            theme,
             "Edit",
             "icons/edit.svg",
             mode == Mode::Edit,
-            true,
+            false,
             Mode::Edit,
         ),
     ])
 }
 
 fn radio_button(
+    theme: Theme,
     label: &'static str,
     icon: &'static str,
     active: bool,
@@ -379,19 +1461,19 @@ fn radio_button(
                 .py_1()
                 .border_1()
                 .when(disabled, |this| {
-                    this.border_color(rgb(COLOR_GRAY_100))
-                        .bg(rgb(COLOR_GRAY_50))
+                    this.border_color(rgb(theme.border))
+                        .bg(rgb(theme.surface_disabled))
                 })
                 .when(!disabled, |this| {
                     this.when(active, |this| {
-                        this.border_color(rgb(COLOR_BLUE_MEDIUM))
-                            .bg(rgb(COLOR_BLUE_LIGHT))
+                        this.border_color(rgb(theme.accent_border))
+                            .bg(rgb(theme.accent_background))
                             .group("active-button")
                     })
                     .when(!active, |this| {
-                        this.border_color(rgb(COLOR_GRAY_200))
-                            .bg(rgb(COLOR_GRAY_100))
-                            .hover(|this| this.bg(rgb(COLOR_GRAY_200)))
+                        this.border_color(rgb(theme.surface_hover))
+                            .bg(rgb(theme.surface))
+                            .hover(|this| this.bg(rgb(theme.surface_hover)))
                     })
                 })
                 .group("button")
@@ -400,13 +1482,13 @@ fn radio_button(
                     svg()
                         .path(icon)
                         .size_6()
-                        .when(disabled, |this| this.text_color(rgb(COLOR_GRAY_300)))
+                        .when(disabled, |this| this.text_color(rgb(theme.text_disabled)))
                         .when(!disabled, |this| {
-                            this.when(active, |this| this.text_color(rgb(COLOR_BLUE_DARK)))
+                            this.when(active, |this| this.text_color(rgb(theme.accent)))
                                 .when(!active, |this| {
-                                    this.text_color(rgb(COLOR_GRAY_500))
+                                    this.text_color(rgb(theme.text_muted))
                                         .group_hover("button", |this| {
-                                            this.text_color(rgb(COLOR_GRAY_600))
+                                            this.text_color(rgb(theme.text_secondary))
                                         })
                                 })
                         }),
@@ -419,10 +1501,10 @@ fn radio_button(
             div()
                 .flex()
                 .justify_center()
-                .when(disabled, |this| this.text_color(rgb(COLOR_GRAY_300)))
+                .when(disabled, |this| this.text_color(rgb(theme.text_disabled)))
                 .when(!disabled, |this| {
-                    this.when(active, |this| this.text_color(rgb(COLOR_BLUE_DARK)))
-                        .when(!active, |this| this.text_color(rgb(COLOR_GRAY_600)))
+                    this.when(active, |this| this.text_color(rgb(theme.accent)))
+                        .when(!active, |this| this.text_color(rgb(theme.text_secondary)))
                 })
                 .text_size(px(8.))
                 .child(label),
@@ -438,3 +1520,114 @@ fn radio_button(
 fn quit(_: &Quit, context: &mut AppContext) {
     context.quit();
 }
+
+fn reload_settings(_: &ReloadSettings, context: &mut AppContext) {
+    context.set_global(Settings::load(context));
+    bind_keys(context);
+    context.refresh();
+}
+
+// The bindings from the settings file are bound last so they take precedence
+fn bind_keys(context: &mut AppContext) {
+    let key_bindings = Settings::get(context).key_bindings(context);
+
+    context.clear_key_bindings();
+    context.bind_keys(default_key_bindings());
+    context.bind_keys(key_bindings);
+}
+
+// The shortcuts are written for macOS, other platforms use ctrl where macOS uses cmd
+fn key_binding<A: Action>(keystrokes: &str, action: A, context: Option<&str>) -> KeyBinding {
+    if cfg!(target_os = "macos") {
+        return KeyBinding::new(keystrokes, action, context);
+    }
+
+    return KeyBinding::new(&keystrokes.replace("cmd-", "ctrl-"), action, context);
+}
+
+fn default_key_bindings() -> Vec<KeyBinding> {
+    return vec![
+        key_binding("cmd-q", Quit, None),
+        key_binding("cmd-b", ToggleSidebar, None),
+        key_binding("cmd-shift-f", ToggleZenMode, None),
+        key_binding("cmd-shift-p", TogglePreview, None),
+        key_binding("cmd-alt-\\", SplitRight, None),
+        key_binding("cmd-alt-o", FocusNextPane, None),
+        // key_binding("cmd-1", SetMode::mode(Mode::Outline), None),
+        key_binding("cmd-2", SetMode::mode(Mode::Write), None),
+        key_binding("cmd-3", SetMode::mode(Mode::Edit), None),
+        key_binding("left", MoveLeft, "editor".into()),
+        key_binding("right", MoveRight, "editor".into()),
+        key_binding("up", MoveUp, "editor".into()),
+        key_binding("down", MoveDown, "editor".into()),
+        key_binding("cmd-up", MoveBeginningOfFile, "editor".into()),
+        key_binding("cmd-down", MoveEndOfFile, "editor".into()),
+        key_binding("cmd-left", MoveBeginningOfLine, "editor".into()),
+        key_binding("cmd-right", MoveEndOfLine, "editor".into()),
+        key_binding("alt-left", MoveBeginningOfWord, "editor".into()),
+        key_binding("alt-right", MoveEndOfWord, "editor".into()),
+        key_binding("shift-left", SelectLeft, "editor".into()),
+        key_binding("shift-right", SelectRight, "editor".into()),
+        key_binding("shift-up", SelectUp, "editor".into()),
+        key_binding("shift-down", SelectDown, "editor".into()),
+        key_binding("cmd-shift-up", SelectBeginningOfFile, "editor".into()),
+        key_binding("cmd-shift-down", SelectEndOfFile, "editor".into()),
+        key_binding("cmd-shift-left", SelectBeginningOfLine, "editor".into()),
+        key_binding("cmd-shift-right", SelectEndOfLine, "editor".into()),
+        key_binding("alt-shift-left", SelectBeginningOfWord, "editor".into()),
+        key_binding("alt-shift-right", SelectEndOfWord, "editor".into()),
+        key_binding("cmd-a", SelectAll, "editor".into()),
+        key_binding("cmd-alt-p", SelectParagraph, "editor".into()),
+        key_binding("cmd-d", SelectWord, "editor".into()),
+        key_binding("cmd-m", JumpToMatchingBracket, "editor".into()),
+        key_binding("cmd-shift-m", SelectToMatchingBracket, "editor".into()),
+        key_binding("cmd-alt-left", JumpBack, "editor".into()),
+        key_binding("cmd-alt-right", JumpForward, "editor".into()),
+        key_binding("cmd-;", NextMisspelling, "editor".into()),
+        key_binding("cmd-shift-;", PreviousMisspelling, "editor".into()),
+        // Escape leaves vim's Insert mode first, then cancels a pending vim command,
+        // then collapses the selection, and otherwise does nothing
+        key_binding("escape", RemoveSelection, None),
+        key_binding("backspace", Backspace, "editor".into()),
+        key_binding("enter", Enter, "editor".into()),
+        key_binding("tab", Indent, "editor".into()),
+        key_binding("shift-tab", Outdent, "editor".into()),
+        key_binding("cmd-k", InsertLink, "editor".into()),
+        key_binding("cmd-alt-h", InsertHorizontalRule, "editor".into()),
+        key_binding("cmd-alt-k", InsertCodeBlock, "editor".into()),
+        key_binding("cmd-alt-i", InsertSpecialCharacter, "editor".into()),
+        // The same shortcuts macOS uses for these characters
+        key_binding(
+            "alt-shift-space",
+            InsertCharacter::new('\u{a0}'),
+            "editor".into(),
+        ),
+        key_binding("alt-shift--", InsertCharacter::new('—'), "editor".into()),
+        key_binding("alt--", InsertCharacter::new('–'), "editor".into()),
+        key_binding("alt-;", InsertCharacter::new('…'), "editor".into()),
+        key_binding("cmd-]", IncreaseHeadlineLevel, "editor".into()),
+        key_binding("cmd-[", DecreaseHeadlineLevel, "editor".into()),
+        key_binding("cmd-shift-8", ToggleList, "editor".into()),
+        key_binding("cmd-shift-7", ToggleOrderedList, "editor".into()),
+        key_binding("cmd-enter", ToggleTask, "editor".into()),
+        key_binding("cmd-\\", ClearFormatting, "editor".into()),
+        key_binding("cmd-alt-f", FormatDocument, "editor".into()),
+        key_binding("alt-z", ToggleWordWrap, "editor".into()),
+        key_binding("cmd-c", Copy, "editor".into()),
+        key_binding("cmd-alt-c", CopyAsPlainText, "editor".into()),
+        key_binding("cmd-alt-shift-c", CopyWithHardWraps, "editor".into()),
+        key_binding("cmd-x", Cut, "editor".into()),
+        key_binding("cmd-v", Paste, "editor".into()),
+        key_binding("cmd-s", Save, None),
+        key_binding("cmd-shift-s", SaveAs, None),
+        key_binding("cmd-n", NewFile, None),
+        key_binding("cmd-w", CloseTab, "editor".into()),
+        key_binding("cmd-shift-]", NextTab, "editor".into()),
+        key_binding("cmd-shift-[", PreviousTab, "editor".into()),
+        key_binding("ctrl-tab", NextTab, "editor".into()),
+        key_binding("ctrl-shift-tab", PreviousTab, "editor".into()),
+        key_binding("cmd-alt-n", NewFromTemplate, None),
+        key_binding("cmd-o", OpenFile, None),
+        key_binding("cmd-shift-o", OpenFolder, None),
+    ];
+}
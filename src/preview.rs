@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+use gpui::{
+    div, prelude::*, px, rems, rgb, FontWeight, MouseButton, Pixels, Point, ScrollHandle,
+    ViewContext,
+};
+
+use crate::{
+    content::{is_horizontal_rule, Content, LineType},
+    display_map::DisplayMap,
+    settings::Palette,
+    theme::Theme,
+};
+
+const CODE_FENCE: &str = "```";
+
+// Emitted when a headline in the preview is clicked
+// Holds the line of the headline in the editor
+pub struct JumpToLine(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+enum BlockKind {
+    Headline(usize),
+    Paragraph,
+    // Holds the bullet or number of the item
+    ListItem(String),
+    Code,
+    Rule,
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    kind: BlockKind,
+    text: String,
+    // The lines of the editor the block was made from
+    lines: Range<usize>,
+}
+
+// Renders the document as formatted markdown next to the editor
+pub struct Preview {
+    blocks: Vec<Block>,
+    scroll_handle: ScrollHandle,
+    theme: Theme,
+}
+
+impl Preview {
+    pub fn new() -> Preview {
+        return Preview {
+            blocks: vec![],
+            scroll_handle: ScrollHandle::new(),
+            theme: Theme::light(&Palette::default()),
+        };
+    }
+
+    pub fn set_content(&mut self, content: &Content, context: &mut ViewContext<Self>) {
+        self.blocks = blocks(content);
+
+        context.notify();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme, context: &mut ViewContext<Self>) {
+        self.theme = theme;
+
+        context.notify();
+    }
+
+    // Lines up the block that contains the given editor line with the top of the preview
+    pub fn scroll_to_line(&mut self, y: usize, context: &mut ViewContext<Self>) {
+        let Some(index) = self
+            .blocks
+            .iter()
+            .position(|block| block.lines.end > y)
+            .or(self.blocks.len().checked_sub(1))
+        else {
+            return;
+        };
+
+        // Item bounds move along with the scroll offset, so measure from the first block
+        let (Some(first), Some(block)) = (
+            self.scroll_handle.bounds_for_item(0),
+            self.scroll_handle.bounds_for_item(index),
+        ) else {
+            return;
+        };
+
+        let offset = block.top() - first.top();
+
+        if self.scroll_handle.offset().y != -offset {
+            self.scroll_handle
+                .set_offset(Point::new(Pixels::ZERO, -offset));
+
+            context.notify();
+        }
+    }
+
+    fn render_block(&self, block: &Block, context: &mut ViewContext<Self>) -> gpui::AnyElement {
+        let theme = self.theme;
+
+        return match block.kind {
+            BlockKind::Headline(level) => {
+                let y = block.lines.start;
+                let size = match level {
+                    1 => rems(1.75),
+                    2 => rems(1.5),
+                    3 => rems(1.25),
+                    _ => rems(1.),
+                };
+
+                div()
+                    .text_size(size)
+                    .font_weight(FontWeight::EXTRA_BOLD)
+                    .text_color(rgb(theme.headline))
+                    .cursor_pointer()
+                    .hover(|this| this.text_color(rgb(theme.accent)))
+                    .child(block.text.clone())
+                    .on_mouse_up(
+                        MouseButton::Left,
+                        context.listener(move |_, _, context| {
+                            context.emit(JumpToLine(y));
+                        }),
+                    )
+                    .into_any_element()
+            }
+            BlockKind::Paragraph => div()
+                .text_color(rgb(theme.body))
+                .child(block.text.clone())
+                .into_any_element(),
+            BlockKind::ListItem(ref marker) => div()
+                .flex()
+                .flex_row()
+                .gap_2()
+                .text_color(rgb(theme.body))
+                .child(
+                    div()
+                        .text_color(rgb(theme.text_muted))
+                        .child(marker.clone()),
+                )
+                .child(block.text.clone())
+                .into_any_element(),
+            BlockKind::Code => div()
+                .p_2()
+                .rounded(px(3.))
+                .bg(rgb(theme.surface))
+                .text_color(rgb(theme.body))
+                .text_size(rems(0.875))
+                .child(block.text.clone())
+                .into_any_element(),
+            BlockKind::Rule => div().h(px(1.)).bg(rgb(theme.border)).into_any_element(),
+        };
+    }
+}
+
+impl EventEmitter<JumpToLine> for Preview {}
+
+impl Render for Preview {
+    fn render(&mut self, context: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .id("preview")
+            .flex()
+            .flex_col()
+            .gap_3()
+            .size_full()
+            .px(rems(2.))
+            .py(rems(2.))
+            .border_l_1()
+            .border_color(rgb(self.theme.border))
+            .bg(rgb(self.theme.background))
+            .overflow_y_scroll()
+            .track_scroll(&self.scroll_handle)
+            .children(
+                self.blocks
+                    .iter()
+                    .map(|block| self.render_block(block, context)),
+            )
+    }
+}
+
+// Groups the lines of the document into the blocks that are rendered
+// Soft-wrapped lines keep the space they were wrapped at, so they can be joined back together as is
+fn blocks(content: &Content) -> Vec<Block> {
+    let lines = content.lines();
+    let wrap_points = content.wrap_points();
+    // Nothing is revealed, so all the markdown markers are hidden
+    let display_map = DisplayMap::new(&lines, 0..0);
+    let mut blocks: Vec<Block> = vec![];
+    let mut is_inside_code = false;
+    let mut offset = 0;
+
+    for (y, line) in lines.iter().enumerate() {
+        let starts_logical_line = !wrap_points.contains(&offset);
+        offset += line.length() + 1;
+
+        let trimmed = line.text.trim();
+
+        if starts_logical_line && trimmed.starts_with(CODE_FENCE) {
+            if !is_inside_code {
+                blocks.push(Block {
+                    kind: BlockKind::Code,
+                    text: String::new(),
+                    lines: y..y + 1,
+                });
+            } else if let Some(block) = blocks.last_mut() {
+                block.lines.end = y + 1;
+            }
+
+            is_inside_code = !is_inside_code;
+
+            continue;
+        }
+
+        if is_inside_code {
+            if let Some(block) = blocks.last_mut() {
+                if !block.text.is_empty() && starts_logical_line {
+                    block.text.push('\n');
+                }
+
+                block.text.push_str(&line.text);
+                block.lines.end = y + 1;
+            }
+
+            continue;
+        }
+
+        // Frontmatter is metadata, so it isn't part of the rendered document
+        if trimmed.is_empty() || matches!(line.kind, LineType::Frontmatter) {
+            continue;
+        }
+
+        let text = display_map.text(y, line);
+        let continues_block = !starts_logical_line
+            || matches!(line.kind, LineType::HeadlineNotStart)
+            || (is_paragraph_line(trimmed)
+                && blocks.last().map_or(false, |block| {
+                    block.kind == BlockKind::Paragraph && block.lines.end == y
+                }));
+
+        if continues_block {
+            if let Some(block) = blocks.last_mut() {
+                if starts_logical_line && !block.text.ends_with(' ') {
+                    block.text.push(' ');
+                }
+
+                block.text.push_str(&text);
+                block.lines.end = y + 1;
+
+                continue;
+            }
+        }
+
+        let (kind, text) = match line.kind {
+            LineType::HeadlineStart(level) => (BlockKind::Headline(level), text),
+            LineType::HorizontalRule => (BlockKind::Rule, String::new()),
+            _ => match list_item(&text) {
+                Some((marker, item)) => (BlockKind::ListItem(marker), item.to_string()),
+                None => (BlockKind::Paragraph, text.trim_start().to_string()),
+            },
+        };
+
+        blocks.push(Block {
+            kind,
+            text,
+            lines: y..y + 1,
+        });
+    }
+
+    return blocks;
+}
+
+fn is_paragraph_line(text: &str) -> bool {
+    return list_item(text).is_none() && !is_horizontal_rule(text);
+}
+
+// The marker to show and the text of the item
+fn list_item(text: &str) -> Option<(String, &str)> {
+    let trimmed = text.trim_start();
+
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(marker) {
+            return Some(("•".to_string(), item));
+        }
+    }
+
+    let digits = trimmed
+        .chars()
+        .take_while(|character| character.is_ascii_digit())
+        .count();
+
+    if digits == 0 {
+        return None;
+    }
+
+    let item = trimmed[digits..].strip_prefix(". ")?;
+
+    return Some((format!("{}.", &trimmed[..digits]), item));
+}
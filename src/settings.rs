@@ -0,0 +1,327 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use gpui::{px, AppContext, Global, KeyBinding, KeyBindingContextPredicate, Pixels};
+use serde::Deserialize;
+
+use crate::{
+    buffer::{DEFAULT_FILE_EXTENSIONS, DEFAULT_TAB_WIDTH},
+    editor::CHARACTER_COUNT_PER_LINE,
+    storage::{self, ReadError},
+    text::WordBoundaries,
+    COLOR_BLACK, COLOR_BLUE_DARK, COLOR_BLUE_DARKEST, COLOR_BLUE_LIGHT, COLOR_BLUE_MEDIUM,
+    COLOR_GRAY_100, COLOR_GRAY_200, COLOR_GRAY_300, COLOR_GRAY_400, COLOR_GRAY_50, COLOR_GRAY_500,
+    COLOR_GRAY_600, COLOR_GRAY_700, COLOR_GRAY_800, COLOR_GRAY_900, COLOR_GRAY_950, COLOR_GREEN,
+    COLOR_ORANGE_DARK, COLOR_PINK, COLOR_RED, COLOR_WHITE,
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+// How wide and tall a character of the editor font is compared to its size
+const CHARACTER_WIDTH_RATIO: f32 = 0.64;
+const LINE_HEIGHT_RATIO: f32 = 1.75;
+
+// User settings, read from settings.json in the application support directory
+// Anything that's left out falls back to the defaults
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub font_family: String,
+    pub font_size: f32,
+    pub key_bindings: Vec<KeyBindingSetting>,
+    // Either "whitespace" or "punctuation"
+    pub word_boundaries: WordBoundaries,
+    // How many spaces tabs are replaced with
+    pub tab_width: usize,
+    // Where Save As starts out for new documents, instead of the documents folder
+    pub save_directory: Option<PathBuf>,
+    // Which files can be opened and saved, like `["md", "txt"]`
+    // An empty extension allows files without one
+    pub file_extensions: Vec<String>,
+    // Turns animations like smooth scrolling into instant changes
+    // Follows the system setting when left out
+    pub reduce_motion: Option<bool>,
+    // Overrides for the palette, like `"gray_700": "#333333"`
+    colors: HashMap<String, String>,
+    #[serde(skip)]
+    pub palette: Palette,
+    // What was wrong with the settings file, so it can be shown instead of silently ignored
+    #[serde(skip)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct KeyBindingSetting {
+    pub keystrokes: String,
+    pub action: String,
+    pub context: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            font_family: "MonoLisa".into(),
+            font_size: 16.,
+            key_bindings: vec![],
+            word_boundaries: WordBoundaries::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            save_directory: None,
+            file_extensions: DEFAULT_FILE_EXTENSIONS.map(String::from).to_vec(),
+            reduce_motion: None,
+            colors: HashMap::new(),
+            palette: Palette::default(),
+            errors: vec![],
+        }
+    }
+}
+
+impl Global for Settings {}
+
+impl Settings {
+    pub fn load(context: &AppContext) -> Settings {
+        let mut settings: Settings = match storage::read(SETTINGS_FILE) {
+            Ok(settings) => settings,
+            // Without a settings file everything is left at the defaults
+            Err(ReadError::IoError(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                Settings::default()
+            }
+            Err(ReadError::IoError(error)) => Settings {
+                errors: vec![format!("Couldn't read {}: {}", SETTINGS_FILE, error)],
+                ..Settings::default()
+            },
+            Err(ReadError::InvalidJson(error)) => Settings {
+                errors: vec![format!("{} is invalid: {}", SETTINGS_FILE, error)],
+                ..Settings::default()
+            },
+        };
+
+        if settings.reduce_motion.is_none() {
+            settings.reduce_motion = system_reduces_motion();
+        }
+
+        for (name, value) in settings.colors.iter() {
+            match parse_color(value) {
+                Some(color) if settings.palette.set(name, color) => {}
+                _ => settings
+                    .errors
+                    .push(format!("Ignoring invalid color {}: {}", name, value)),
+            }
+        }
+
+        for binding in settings.key_bindings.iter() {
+            if let Err(error) = binding.load(context) {
+                settings.errors.push(format!(
+                    "Ignoring key binding {}: {}",
+                    binding.keystrokes, error
+                ));
+            }
+        }
+
+        return settings;
+    }
+
+    pub fn get(context: &AppContext) -> &Settings {
+        return context.global::<Settings>();
+    }
+
+    pub fn reduce_motion(&self) -> bool {
+        return self.reduce_motion.unwrap_or(false);
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        return Metrics::new(self.font_size);
+    }
+
+    // The bindings from the settings file, which are bound after the defaults so they win
+    // The ones that can't be bound are already in `errors`
+    pub fn key_bindings(&self, context: &AppContext) -> Vec<KeyBinding> {
+        return self
+            .key_bindings
+            .iter()
+            .filter_map(|binding| binding.load(context).ok())
+            .collect();
+    }
+}
+
+impl KeyBindingSetting {
+    fn load(&self, context: &AppContext) -> gpui::Result<KeyBinding> {
+        // Actions can be written without their namespace
+        let name = if self.action.contains("::") {
+            self.action.clone()
+        } else {
+            format!("app::{}", self.action)
+        };
+        let action = context.build_action(&name, None)?;
+        let predicate = match &self.context {
+            Some(context) => Some(KeyBindingContextPredicate::parse(context)?),
+            None => None,
+        };
+
+        return KeyBinding::load(&self.keystrokes, action, predicate.map(Into::into));
+    }
+}
+
+// The editor lays text out on a grid of monospace characters
+// Everything is derived from the font size so it all scales together
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub font_size: Pixels,
+    pub character_width: Pixels,
+    pub line_height: Pixels,
+    pub horizontal_margin: Pixels,
+    pub container_width: Pixels,
+}
+
+impl Metrics {
+    pub fn new(font_size: f32) -> Metrics {
+        let character_width = px(font_size * CHARACTER_WIDTH_RATIO);
+        // 6 headline markers + 1 space
+        let horizontal_margin = character_width * 7.;
+
+        return Metrics {
+            font_size: px(font_size),
+            character_width,
+            line_height: px(font_size * LINE_HEIGHT_RATIO),
+            horizontal_margin,
+            container_width: character_width * CHARACTER_COUNT_PER_LINE as f32
+                + horizontal_margin * 2.,
+        };
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        return Settings::default().metrics();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub white: u32,
+    pub black: u32,
+    pub pink: u32,
+    pub gray_50: u32,
+    pub gray_100: u32,
+    pub gray_200: u32,
+    pub gray_300: u32,
+    pub gray_400: u32,
+    pub gray_500: u32,
+    pub gray_600: u32,
+    pub gray_700: u32,
+    pub gray_800: u32,
+    pub gray_900: u32,
+    pub gray_950: u32,
+    pub blue_light: u32,
+    pub blue_medium: u32,
+    pub blue_dark: u32,
+    pub blue_darkest: u32,
+    pub orange_dark: u32,
+    pub green: u32,
+    pub red: u32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            white: COLOR_WHITE,
+            black: COLOR_BLACK,
+            pink: COLOR_PINK,
+            gray_50: COLOR_GRAY_50,
+            gray_100: COLOR_GRAY_100,
+            gray_200: COLOR_GRAY_200,
+            gray_300: COLOR_GRAY_300,
+            gray_400: COLOR_GRAY_400,
+            gray_500: COLOR_GRAY_500,
+            gray_600: COLOR_GRAY_600,
+            gray_700: COLOR_GRAY_700,
+            gray_800: COLOR_GRAY_800,
+            gray_900: COLOR_GRAY_900,
+            gray_950: COLOR_GRAY_950,
+            blue_light: COLOR_BLUE_LIGHT,
+            blue_medium: COLOR_BLUE_MEDIUM,
+            blue_dark: COLOR_BLUE_DARK,
+            blue_darkest: COLOR_BLUE_DARKEST,
+            orange_dark: COLOR_ORANGE_DARK,
+            green: COLOR_GREEN,
+            red: COLOR_RED,
+        }
+    }
+}
+
+impl Palette {
+    // Returns whether there is a color with that name
+    fn set(&mut self, name: &str, color: u32) -> bool {
+        let field = match name {
+            "white" => &mut self.white,
+            "black" => &mut self.black,
+            "pink" => &mut self.pink,
+            "gray_50" => &mut self.gray_50,
+            "gray_100" => &mut self.gray_100,
+            "gray_200" => &mut self.gray_200,
+            "gray_300" => &mut self.gray_300,
+            "gray_400" => &mut self.gray_400,
+            "gray_500" => &mut self.gray_500,
+            "gray_600" => &mut self.gray_600,
+            "gray_700" => &mut self.gray_700,
+            "gray_800" => &mut self.gray_800,
+            "gray_900" => &mut self.gray_900,
+            "gray_950" => &mut self.gray_950,
+            "blue_light" => &mut self.blue_light,
+            "blue_medium" => &mut self.blue_medium,
+            "blue_dark" => &mut self.blue_dark,
+            "blue_darkest" => &mut self.blue_darkest,
+            "orange_dark" => &mut self.orange_dark,
+            "green" => &mut self.green,
+            "red" => &mut self.red,
+            _ => return false,
+        };
+
+        *field = color;
+
+        return true;
+    }
+}
+
+// Asks the system whether animations should be avoided
+// Nothing when it can't be found out, like on a system without the setting
+#[cfg(target_os = "macos")]
+fn system_reduces_motion() -> Option<bool> {
+    let output = std::process::Command::new("defaults")
+        .args(["read", "com.apple.universalaccess", "reduceMotion"])
+        .output()
+        .ok()?;
+
+    return match String::from_utf8_lossy(&output.stdout).trim() {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    };
+}
+
+#[cfg(target_os = "linux")]
+fn system_reduces_motion() -> Option<bool> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "enable-animations"])
+        .output()
+        .ok()?;
+
+    return match String::from_utf8_lossy(&output.stdout).trim() {
+        "false" => Some(true),
+        "true" => Some(false),
+        _ => None,
+    };
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn system_reduces_motion() -> Option<bool> {
+    return None;
+}
+
+// Parses colors written as `#rrggbb`
+fn parse_color(value: &str) -> Option<u32> {
+    let hex = value.strip_prefix('#')?;
+
+    if hex.len() != 6 {
+        return None;
+    }
+
+    return u32::from_str_radix(hex, 16).ok();
+}
@@ -0,0 +1,317 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+    rc::Rc,
+};
+
+use gpui::AssetSource;
+
+use crate::content::Line;
+
+const DICTIONARY_ASSET: &str = "dictionary/en.txt";
+// Ships with macOS and is a lot more complete than the bundled list
+const SYSTEM_DICTIONARY_PATH: &str = "/usr/share/dict/words";
+const MAX_CACHED_LINES: usize = 10_000;
+
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    pub fn new(words: &str) -> Dictionary {
+        let mut dictionary = Dictionary {
+            words: HashSet::new(),
+        };
+
+        dictionary.extend(words);
+
+        return dictionary;
+    }
+
+    pub fn load(assets: &dyn AssetSource) -> Dictionary {
+        let bundled = assets
+            .load(DICTIONARY_ASSET)
+            .ok()
+            .flatten()
+            .map(|data| String::from_utf8_lossy(&data).to_string())
+            .unwrap_or_default();
+        let mut dictionary = Dictionary::new(&bundled);
+
+        if let Ok(words) = fs::read_to_string(SYSTEM_DICTIONARY_PATH) {
+            dictionary.extend(&words);
+        }
+
+        return dictionary;
+    }
+
+    pub fn extend(&mut self, words: &str) {
+        self.words.extend(
+            words
+                .lines()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.words.is_empty();
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        let word = word.to_lowercase().replace('’', "'");
+
+        if self.words.contains(&word) {
+            return true;
+        }
+
+        let word = word
+            .strip_suffix("'s")
+            .or_else(|| word.strip_suffix('\''))
+            .unwrap_or(&word);
+
+        return self.words.contains(word) || self.contains_stem(word);
+    }
+
+    // Most word lists don't contain every inflection
+    // So also accept words that are a known word plus a common suffix
+    fn contains_stem(&self, word: &str) -> bool {
+        const SUFFIXES: [(&str, &str); 14] = [
+            ("ies", "y"),
+            ("ied", "y"),
+            ("ier", "y"),
+            ("iest", "y"),
+            ("ily", "y"),
+            ("es", ""),
+            ("s", ""),
+            ("ed", ""),
+            ("ed", "e"),
+            ("ing", ""),
+            ("ing", "e"),
+            ("er", ""),
+            ("est", ""),
+            ("ly", ""),
+        ];
+
+        return SUFFIXES.iter().any(|(suffix, replacement)| {
+            let Some(stem) = word.strip_suffix(suffix) else {
+                return false;
+            };
+
+            if stem.len() < 2 {
+                return false;
+            }
+
+            if self.words.contains(&format!("{}{}", stem, replacement)) {
+                return true;
+            }
+
+            // Running, stopped, bigger
+            let mut characters = stem.chars().rev();
+            // The last letter can take more than one byte, like `é`
+            let without_last = stem
+                .char_indices()
+                .next_back()
+                .map_or(stem, |(index, _)| &stem[..index]);
+
+            return replacement.is_empty()
+                && characters.next() == characters.next()
+                && self.words.contains(without_last);
+        });
+    }
+}
+
+// Checks lines against the dictionary and remembers the result per line
+// so typing only re-checks the line that changed
+#[derive(Clone)]
+pub struct Spellchecker {
+    dictionary: Rc<Dictionary>,
+    cache: Rc<RefCell<HashMap<String, Vec<Range<usize>>>>>,
+}
+
+impl Spellchecker {
+    pub fn new(dictionary: Dictionary) -> Spellchecker {
+        return Spellchecker {
+            dictionary: Rc::new(dictionary),
+            cache: Rc::new(RefCell::new(HashMap::new())),
+        };
+    }
+
+    // The byte ranges of the misspelled words in the text of the line
+    pub fn misspelled_ranges(&self, line: &Line) -> Vec<Range<usize>> {
+        // Without a dictionary every word would be marked
+        if self.dictionary.is_empty() {
+            return vec![];
+        }
+
+        let mut cache = self.cache.borrow_mut();
+
+        if let Some(ranges) = cache.get(&line.text) {
+            return ranges.clone();
+        }
+
+        if cache.len() > MAX_CACHED_LINES {
+            cache.clear();
+        }
+
+        let ranges: Vec<Range<usize>> = words(&line.text)
+            .into_iter()
+            .filter(|range| !self.dictionary.contains(&line.text[range.clone()]))
+            .collect();
+
+        cache.insert(line.text.clone(), ranges.clone());
+
+        return ranges;
+    }
+}
+
+// Splits the text into the words worth checking
+// Code spans, links, numbers and acronyms are left out
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut is_inside_code = false;
+    let mut chunk_start = 0;
+
+    for (index, character) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        if character == '`' {
+            is_inside_code = !is_inside_code;
+        }
+
+        if !character.is_whitespace() {
+            continue;
+        }
+
+        let chunk = &text[chunk_start..index];
+
+        if !is_inside_code && !chunk.contains('`') && !is_link(chunk) {
+            words.extend(
+                words_in_chunk(chunk)
+                    .into_iter()
+                    .map(|range| chunk_start + range.start..chunk_start + range.end),
+            );
+        }
+
+        chunk_start = index + character.len_utf8();
+    }
+
+    return words;
+}
+
+fn words_in_chunk(chunk: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start: Option<usize> = None;
+    let characters: Vec<(usize, char)> = chunk.char_indices().collect();
+
+    for (position, (index, character)) in characters.iter().enumerate() {
+        let is_apostrophe = *character == '\'' || *character == '’';
+        let next_is_letter = characters
+            .get(position + 1)
+            .map_or(false, |(_, next)| next.is_alphabetic());
+        // Apostrophes only belong to a word when they're inside it, like in "don't"
+        let is_part_of_word =
+            character.is_alphanumeric() || (is_apostrophe && start.is_some() && next_is_letter);
+
+        match (is_part_of_word, start) {
+            (true, None) => start = Some(*index),
+            (false, Some(word_start)) => {
+                words.push(word_start..*index);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(word_start) = start {
+        words.push(word_start..chunk.len());
+    }
+
+    return words
+        .into_iter()
+        .filter(|range| should_check(&chunk[range.clone()]))
+        .collect();
+}
+
+fn should_check(word: &str) -> bool {
+    let has_digits = word.chars().any(|character| character.is_numeric());
+    let is_acronym = word
+        .chars()
+        .filter(|character| character.is_uppercase())
+        .count()
+        > 1;
+
+    return word.chars().count() > 1 && !has_digits && !is_acronym;
+}
+
+fn is_link(chunk: &str) -> bool {
+    return chunk.contains("://")
+        || chunk.contains("www.")
+        || chunk.contains('@')
+        || chunk.contains("](");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        return Dictionary::new("run\nstop\nbig\nhappy\nbake\ndon't\ncafé\n");
+    }
+
+    fn checked_words(text: &str) -> Vec<&str> {
+        return words(text).into_iter().map(|range| &text[range]).collect();
+    }
+
+    #[test]
+    fn contains_words_in_any_case() {
+        assert!(dictionary().contains("Run"));
+        assert!(dictionary().contains("CAFÉ"));
+        assert!(!dictionary().contains("walk"));
+    }
+
+    #[test]
+    fn contains_inflections_of_words() {
+        let dictionary = dictionary();
+
+        assert!(dictionary.contains("runs"));
+        assert!(dictionary.contains("running"));
+        assert!(dictionary.contains("stopped"));
+        assert!(dictionary.contains("bigger"));
+        assert!(dictionary.contains("happiest"));
+        assert!(dictionary.contains("baking"));
+    }
+
+    #[test]
+    fn contains_possessives_and_curly_apostrophes() {
+        let dictionary = dictionary();
+
+        assert!(dictionary.contains("run's"));
+        assert!(dictionary.contains("don’t"));
+    }
+
+    #[test]
+    fn stems_ending_in_a_repeated_multi_byte_letter_dont_panic() {
+        assert!(!dictionary().contains("xééing"));
+        assert!(!dictionary().contains("caféé"));
+    }
+
+    #[test]
+    fn words_leave_out_code_links_numbers_and_acronyms() {
+        let text = "Check `code here` and https://example.com or 42nd NASA words";
+
+        assert_eq!(checked_words(text), vec!["Check", "and", "or", "words"]);
+    }
+
+    #[test]
+    fn words_keep_apostrophes_inside_them() {
+        assert_eq!(checked_words("'don't' stop’s"), vec!["don't", "stop’s"]);
+    }
+
+    #[test]
+    fn words_are_byte_ranges_in_multi_byte_text() {
+        assert_eq!(checked_words("— café “naïve”"), vec!["café", "naïve"]);
+    }
+}
@@ -0,0 +1,54 @@
+use std::{env, fs, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+// Keeps small pieces of state between launches as JSON files
+// in the application support directory, or wherever the platform keeps app data
+fn directory() -> PathBuf {
+    return dirs::data_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("Wordsmith");
+}
+
+// Where documents go unless the user picks another folder
+// It might not exist, like on Linux desktops without one
+pub fn documents_directory() -> PathBuf {
+    return dirs::document_dir()
+        .filter(|directory| directory.is_dir())
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(env::temp_dir);
+}
+
+pub fn read<T: DeserializeOwned>(name: &str) -> Result<T, ReadError> {
+    let contents = fs::read_to_string(directory().join(name))?;
+
+    return Ok(serde_json::from_str(&contents)?);
+}
+
+pub fn write<T: Serialize>(name: &str, value: &T) -> std::io::Result<()> {
+    let directory = directory();
+    let contents = serde_json::to_string_pretty(value)?;
+
+    fs::create_dir_all(&directory)?;
+    fs::write(directory.join(name), contents)?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    IoError(std::io::Error),
+    InvalidJson(serde_json::Error),
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(error: std::io::Error) -> Self {
+        ReadError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for ReadError {
+    fn from(error: serde_json::Error) -> Self {
+        ReadError::InvalidJson(error)
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gpui::AssetSource;
+
+const TEMPLATES_DIRECTORY: &str = "templates";
+const DATE_PLACEHOLDER: &str = "{{date}}";
+const CURSOR_PLACEHOLDER: &str = "{{cursor}}";
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub text: String,
+}
+
+// The bundled templates, sorted by name
+pub fn list(assets: &dyn AssetSource) -> Vec<Template> {
+    let mut templates: Vec<Template> = assets
+        .list(TEMPLATES_DIRECTORY)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|file_name| {
+            let name = file_name.strip_suffix(".md")?.to_string();
+            let data = assets
+                .load(&format!("{}/{}", TEMPLATES_DIRECTORY, file_name))
+                .ok()
+                .flatten()?;
+
+            Some(Template {
+                name,
+                text: String::from_utf8_lossy(&data).to_string(),
+            })
+        })
+        .collect();
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    return templates;
+}
+
+// Fills in the placeholders of the template
+// Returns the text and where the cursor marker was, if there was one
+pub fn expand(template: &str) -> (String, Option<usize>) {
+    let text = template.replace(DATE_PLACEHOLDER, &today());
+    let cursor = text.find(CURSOR_PLACEHOLDER);
+
+    return (text.replacen(CURSOR_PLACEHOLDER, "", 1), cursor);
+}
+
+// Today's date, like 2024-03-09
+// Worked out from the days since 1970, in UTC
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0) as i64;
+
+    // Counts from March, so the leap day is the last day of the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!("{:04}-{:02}-{:02}", year, month, day);
+}
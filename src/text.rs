@@ -2,64 +2,398 @@ use std::ops::Range;
 
 use crate::editor::CHARACTER_COUNT_PER_LINE;
 
+// Where a line can be wrapped when it has no whitespace to wrap at
+const BREAK_CHARACTERS: [char; 2] = ['-', '/'];
+
+// The minimum free space that is left for insertions when the buffer grows
+const GAP_SIZE: usize = 4096;
+
+// The original text is kept in a gap buffer
+// The gap is moved to wherever an edit happens, so typing only has to copy the bytes between
+// the previous and the current edit instead of the whole tail of the document
 #[derive(Debug, Clone)]
 pub struct RawText {
-    text: String,
+    bytes: Vec<u8>,
+    gap: Range<usize>,
 }
 
 impl RawText {
     pub fn new(text: String) -> RawText {
-        return RawText { text };
-    }
+        let mut bytes = text.into_bytes();
+        let length = bytes.len();
+
+        bytes.resize(length + GAP_SIZE, 0);
 
-    pub fn empty() -> RawText {
         return RawText {
-            text: String::new(),
+            bytes,
+            gap: length..length + GAP_SIZE,
         };
     }
 
+    pub fn empty() -> RawText {
+        return RawText::new(String::new());
+    }
+
+    pub fn len(&self) -> usize {
+        return self.bytes.len() - self.gap.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
     pub fn to_string(&self) -> String {
-        return self.text.clone();
+        return self.read_range(0..self.len());
     }
 
     pub fn replace(&mut self, range: Range<usize>, replacement: String) {
-        self.text.replace_range(range, &replacement);
+        self.move_gap(range.start);
+
+        // The replaced text becomes part of the gap
+        self.gap.end += range.len();
+
+        if replacement.len() > self.gap.len() {
+            self.grow(replacement.len());
+        }
+
+        let start = self.gap.start;
+
+        self.bytes[start..start + replacement.len()].copy_from_slice(replacement.as_bytes());
+        self.gap.start += replacement.len();
     }
 
     pub fn read_range(&self, range: Range<usize>) -> String {
-        let substring = &self.text[range.start..range.end];
+        let mut bytes = Vec::with_capacity(range.len());
+
+        if range.start < self.gap.start {
+            bytes.extend_from_slice(&self.bytes[range.start..range.end.min(self.gap.start)]);
+        }
+
+        if range.end > self.gap.start {
+            let start = range.start.max(self.gap.start) + self.gap.len();
+
+            bytes.extend_from_slice(&self.bytes[start..range.end + self.gap.len()]);
+        }
+
+        return String::from_utf8(bytes)
+            .expect("Ranges of the text should be on character boundaries");
+    }
+
+    // The character that ends at the offset, read without copying the rest of the text
+    pub fn previous_character(&self, offset: usize) -> Option<char> {
+        let mut start = offset.checked_sub(1)?;
+
+        while !self.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        return self.read_range(start..offset).chars().next();
+    }
+
+    // The character that starts at the offset
+    pub fn next_character(&self, offset: usize) -> Option<char> {
+        if offset >= self.len() {
+            return None;
+        }
+
+        let mut end = offset + 1;
+
+        while !self.is_char_boundary(end) {
+            end += 1;
+        }
+
+        return self.read_range(offset..end).chars().next();
+    }
+
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        if offset == 0 || offset >= self.len() {
+            return offset <= self.len();
+        }
+
+        // Every byte of a character except the first looks like 0b10xxxxxx
+        return self.byte(offset) & 0b1100_0000 != 0b1000_0000;
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        if offset < self.gap.start {
+            return self.bytes[offset];
+        }
+
+        return self.bytes[offset + self.gap.len()];
+    }
+
+    fn move_gap(&mut self, offset: usize) {
+        if offset < self.gap.start {
+            let length = self.gap.start - offset;
+
+            self.bytes
+                .copy_within(offset..self.gap.start, self.gap.end - length);
+            self.gap = offset..self.gap.end - length;
+        } else if offset > self.gap.start {
+            let length = offset - self.gap.start;
+
+            self.bytes
+                .copy_within(self.gap.end..self.gap.end + length, self.gap.start);
+            self.gap = offset..self.gap.end + length;
+        }
+    }
+
+    fn grow(&mut self, minimum: usize) {
+        let additional = minimum - self.gap.len() + GAP_SIZE.max(self.len() / 2);
+        let tail = self.gap.end..self.bytes.len();
+
+        self.bytes.resize(self.bytes.len() + additional, 0);
+        self.bytes.copy_within(tail, self.gap.end + additional);
+        self.gap.end += additional;
+    }
+}
+
+// What counts as a word when moving the cursor word by word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordBoundaries {
+    // Everything between whitespace is one word, so `foo.bar` is a single word
+    #[default]
+    Whitespace,
+    // Words also end where letters and digits meet punctuation, so `foo.bar` is three words
+    Punctuation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharacterClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl WordBoundaries {
+    fn class(&self, character: char) -> CharacterClass {
+        if character.is_whitespace() {
+            return CharacterClass::Whitespace;
+        }
+
+        if *self == WordBoundaries::Punctuation && !character.is_alphanumeric() {
+            return CharacterClass::Punctuation;
+        }
 
-        return substring.to_string();
+        return CharacterClass::Word;
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct WrappedText {
     text: RawText,
+    // Every line of the original text is wrapped on its own
+    // so an edit only has to re-wrap the lines it touches
+    lines: Vec<WrappedLine>,
+    wrapped: String,
+    wrap_points: Vec<usize>,
+    // Without wrapping every line stays as long as it is, for tables and code
+    wrap: bool,
+}
+
+// The part of the wrapped text that was wrapped again after an edit
+// It always covers whole lines, so everything before and after it is unchanged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewrap {
+    // The offset in the wrapped text where the lines start
+    pub start: usize,
+    // How long the lines were in the wrapped text before and after the edit
+    pub old_length: usize,
+    pub new_length: usize,
+}
+
+#[derive(Debug, Clone)]
+struct WrappedLine {
+    // The length of the line in the original text, including the newline
+    length: usize,
+    has_newline: bool,
+    text: String,
+    // Relative to the start of the wrapped line
+    wrap_points: Vec<usize>,
+}
+
+impl WrappedLine {
+    fn new(line: &str, wrap: bool) -> WrappedLine {
+        let has_newline = line.ends_with('\n');
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        // Matches `str::lines`, which the wrapping used to be based on
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        let (text, wrap_points) = if wrap {
+            wrap_line(content)
+        } else {
+            (format!("{}\n", content), vec![])
+        };
+
+        return WrappedLine {
+            length: line.len(),
+            has_newline,
+            text,
+            wrap_points,
+        };
+    }
+
+    // Whether an edit at the offset, relative to the start of the line, changes this line
+    fn contains(&self, offset: usize) -> bool {
+        return offset < self.length || (offset == self.length && !self.has_newline);
+    }
 }
 
 impl WrappedText {
     pub fn new(text: String) -> WrappedText {
-        return WrappedText {
+        let lines = text
+            .split_inclusive('\n')
+            .map(|line| WrappedLine::new(line, true))
+            .collect();
+        let mut wrapped_text = WrappedText {
             text: RawText::new(text),
+            lines,
+            wrapped: String::new(),
+            wrap_points: vec![],
+            wrap: true,
         };
+
+        wrapped_text.join_lines();
+
+        return wrapped_text;
     }
 
     pub fn empty() -> WrappedText {
         return WrappedText {
             text: RawText::empty(),
+            lines: vec![],
+            wrapped: String::new(),
+            wrap_points: vec![],
+            wrap: true,
         };
     }
 
-    pub fn original(&self) -> RawText {
-        return self.text.clone();
+    // Every line of the original text, including a last one without a newline
+    pub fn line_count(&self) -> usize {
+        return self.lines.len();
     }
 
-    pub fn replace(&mut self, range: Range<usize>, replacement: String) {
+    pub fn wraps(&self) -> bool {
+        return self.wrap;
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        if self.wrap == wrap {
+            return;
+        }
+
+        self.wrap = wrap;
+        self.lines = self
+            .text
+            .to_string()
+            .split_inclusive('\n')
+            .map(|line| WrappedLine::new(line, wrap))
+            .collect();
+        self.join_lines();
+    }
+
+    pub fn original(&self) -> &RawText {
+        return &self.text;
+    }
+
+    pub fn replace(&mut self, range: Range<usize>, replacement: String) -> Rewrap {
         let start_offset = self.resolve_offset(range.start);
         let end_offset = self.resolve_offset(range.end);
+        let replacement_length = replacement.len();
 
         self.text.replace(start_offset..end_offset, replacement);
+
+        return self.rewrap(start_offset..end_offset, replacement_length);
+    }
+
+    // Re-wraps the lines that were touched by replacing the range of the original text
+    // Only those lines are spliced into the wrapped text, and the wrap points after them are shifted
+    fn rewrap(&mut self, range: Range<usize>, replacement_length: usize) -> Rewrap {
+        let mut line_start = 0;
+        let mut wrapped_start = 0;
+        let mut first: Option<(usize, usize, usize)> = None;
+        let mut last = self.lines.len();
+        let mut region_end = 0;
+
+        for (index, line) in self.lines.iter().enumerate() {
+            if first.is_none() && line.contains(range.start - line_start) {
+                first = Some((index, line_start, wrapped_start));
+            }
+
+            if first.is_some() {
+                last = index + 1;
+                region_end = line_start + line.length;
+
+                if line.contains(range.end.saturating_sub(line_start)) && range.end >= line_start {
+                    break;
+                }
+            }
+
+            line_start += line.length;
+            wrapped_start += line.text.len();
+        }
+
+        // Typing after the final newline starts a new line
+        let (first, region_start, wrapped_start) =
+            first.unwrap_or((self.lines.len(), line_start, wrapped_start));
+        let last = std::cmp::max(first, last);
+        let region_end =
+            std::cmp::max(region_end, range.end) + replacement_length - (range.end - range.start);
+        let text = self.text.read_range(region_start..region_end);
+        let lines: Vec<WrappedLine> = text
+            .split_inclusive('\n')
+            .map(|line| WrappedLine::new(line, self.wrap))
+            .collect();
+
+        let old_length: usize = self.lines[first..last]
+            .iter()
+            .map(|line| line.text.len())
+            .sum();
+        let mut wrapped = String::new();
+        let mut wrap_points = vec![];
+
+        for line in lines.iter() {
+            wrap_points.extend(
+                line.wrap_points
+                    .iter()
+                    .map(|point| wrapped_start + wrapped.len() + point),
+            );
+            wrapped += line.text.as_str();
+        }
+
+        let old_end = wrapped_start + old_length;
+        let first_point = self
+            .wrap_points
+            .partition_point(|point| *point < wrapped_start);
+        let last_point = self.wrap_points.partition_point(|point| *point < old_end);
+
+        for point in self.wrap_points[last_point..].iter_mut() {
+            *point = *point + wrapped.len() - old_length;
+        }
+
+        self.wrap_points
+            .splice(first_point..last_point, wrap_points);
+        self.wrapped.replace_range(wrapped_start..old_end, &wrapped);
+        self.lines.splice(first..last, lines);
+
+        return Rewrap {
+            start: wrapped_start,
+            old_length,
+            new_length: wrapped.len(),
+        };
+    }
+
+    fn join_lines(&mut self) {
+        let mut wrapped = String::new();
+        let mut wrap_points = vec![];
+
+        for line in self.lines.iter() {
+            wrap_points.extend(line.wrap_points.iter().map(|point| wrapped.len() + point));
+            wrapped += line.text.as_str();
+        }
+
+        self.wrapped = wrapped;
+        self.wrap_points = wrap_points;
     }
 
     pub fn read_range(&self, range: Range<usize>) -> String {
@@ -71,96 +405,115 @@ impl WrappedText {
         return self.text.read_range(range);
     }
 
-    fn resolve_offset(&self, offset: usize) -> usize {
-        let (_, wrap_points) = self.to_string_with_wrap_points();
-        let wrap_points_before_offset =
-            wrap_points.iter().filter(|point| **point <= offset).count();
+    // Unlike `read_range` the newlines inserted at the wrap points are kept
+    pub fn read_wrapped_range(&self, range: Range<usize>) -> String {
+        let end = std::cmp::min(range.end, self.wrapped.len());
+        let start = std::cmp::min(range.start, end);
 
-        return offset - wrap_points_before_offset;
+        return self.wrapped[start..end].to_string();
     }
 
-    pub fn previous_word_boundary(&self, offset: usize) -> usize {
-        let content = self.text.to_string();
-        let chars: Vec<char> = content.chars().collect();
+    pub fn original_offset(&self, offset: usize) -> usize {
+        return self.resolve_offset(offset);
+    }
 
-        // Handle edge cases
-        if offset == 0 {
-            return 0;
-        }
+    pub fn wrapped_offset(&self, offset: usize) -> usize {
+        // Each wrap point has shifted the text after it by the inserted newline
+        // So the position of the wrap point in the original text is its index lower
+        // Those positions only grow, so the wrap points before the offset are found by halving
+        let mut low = 0;
+        let mut high = self.wrap_points.len();
 
-        if offset >= chars.len() {
-            return self.previous_word_boundary(chars.len() - 1);
+        while low < high {
+            let middle = (low + high) / 2;
+
+            if self.wrap_points[middle] - (middle + 1) <= offset {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
         }
 
-        let mut cursor = offset;
+        return offset + low;
+    }
 
-        // Case 1: We're at the beginning of a word already
-        let at_word_beginning = offset < chars.len()
-            && !chars[offset].is_whitespace()
-            && (offset == 0 || chars[offset - 1].is_whitespace());
+    fn resolve_offset(&self, offset: usize) -> usize {
+        let wrap_points_before_offset = self.wrap_points.partition_point(|point| *point <= offset);
 
-        if at_word_beginning {
-            // Skip back through current word
-            while cursor > 0 && !chars[cursor - 1].is_whitespace() {
-                cursor -= 1;
-            }
+        return offset - wrap_points_before_offset;
+    }
 
-            if cursor == 0 {
-                return 0;
-            }
+    pub fn is_wrap_point(&self, offset: usize) -> bool {
+        return self.wrap_points.binary_search(&offset).is_ok();
+    }
 
-            // Skip back through whitespace
-            while cursor > 0 && chars[cursor - 1].is_whitespace() {
-                cursor -= 1;
-            }
+    // Offsets are in bytes, and an offset past the end of the text counts as the end
+    // so an empty text or line never has a character to index into
+    pub fn previous_word_boundary(&self, offset: usize, word_boundaries: WordBoundaries) -> usize {
+        let text = &self.text;
+        let class = |character: char| word_boundaries.class(character);
+        let previous = |cursor: usize| text.previous_character(cursor);
 
-            // Skip back through previous word
-            while cursor > 0 && !chars[cursor - 1].is_whitespace() {
-                cursor -= 1;
-            }
+        let mut cursor = std::cmp::min(offset, text.len());
 
-            return cursor;
+        while !text.is_char_boundary(cursor) {
+            cursor -= 1;
         }
 
-        // Case 2: We're in the middle of a word or at whitespace
-
         // Skip back through whitespace
-        while cursor > 0 && chars[cursor - 1].is_whitespace() {
-            cursor -= 1;
+        while let Some(character) = previous(cursor).filter(|character| character.is_whitespace()) {
+            cursor -= character.len_utf8();
         }
 
-        // Find the beginning of the current word
-        while cursor > 0 && !chars[cursor - 1].is_whitespace() {
-            cursor -= 1;
+        // Find the beginning of the word before the cursor
+        if let Some(character) = previous(cursor) {
+            let word = class(character);
+
+            while let Some(character) =
+                previous(cursor).filter(|character| class(*character) == word)
+            {
+                cursor -= character.len_utf8();
+            }
         }
 
         return cursor;
     }
 
-    pub fn next_word_boundary(&self, offset: usize) -> Option<usize> {
-        let content = self.text.to_string();
-        let chars: Vec<char> = content.chars().collect();
+    pub fn next_word_boundary(
+        &self,
+        offset: usize,
+        word_boundaries: WordBoundaries,
+    ) -> Option<usize> {
+        let text = &self.text;
+        let class = |character: char| word_boundaries.class(character);
+        let next = |cursor: usize| text.next_character(cursor);
 
         // Handle edge case
-        if offset >= chars.len() {
+        if offset >= text.len() {
             return None;
         }
 
         let mut cursor = offset;
 
-        // Skip any whitespace after current position
-        while cursor < chars.len() && chars[cursor].is_whitespace() {
+        while !text.is_char_boundary(cursor) {
             cursor += 1;
         }
 
+        // Skip any whitespace after current position
+        while let Some(character) = next(cursor).filter(|character| character.is_whitespace()) {
+            cursor += character.len_utf8();
+        }
+
         // If we reached the end after skipping whitespace
-        if cursor >= chars.len() {
+        let Some(character) = next(cursor) else {
             return None;
-        }
+        };
 
         // Find end of current word
-        while cursor < chars.len() && !chars[cursor].is_whitespace() {
-            cursor += 1;
+        let word = class(character);
+
+        while let Some(character) = next(cursor).filter(|character| class(*character) == word) {
+            cursor += character.len_utf8();
         }
 
         if cursor == offset {
@@ -171,84 +524,429 @@ impl WrappedText {
     }
 
     pub fn wrap_points(&self) -> Vec<usize> {
-        let (_, wrap_points) = self.to_string_with_wrap_points();
-
-        return wrap_points;
+        return self.wrap_points.clone();
     }
 
-    fn to_string_with_wrap_points(&self) -> (String, Vec<usize>) {
-        let content = self.text.to_string();
+    pub fn to_string(&self) -> String {
+        return self.wrapped.clone();
+    }
 
-        let mut wrap_points: Vec<usize> = vec![];
-        let mut output = String::new();
-        let mut offset = 0;
+    // The wrapped text, without copying it
+    pub fn as_str(&self) -> &str {
+        return self.wrapped.as_str();
+    }
+}
 
-        for line in content.lines() {
-            let mut cloned_line = line.to_string();
+// Soft-wraps a single line of the original text, without its newline
+// The wrapped line always ends with a newline and the wrap points are relative to its start
+fn wrap_line(line: &str) -> (String, Vec<usize>) {
+    let mut wrap_points: Vec<usize> = vec![];
+    let mut output = String::new();
+    let mut offset = 0;
 
-            loop {
-                if cloned_line.len() <= CHARACTER_COUNT_PER_LINE {
-                    output += cloned_line.as_str();
-                    output += "\n";
+    let mut cloned_line = line.to_string();
 
-                    offset += cloned_line.len();
+    loop {
+        // The wrap width counts characters, while slicing the line takes byte indices
+        if cloned_line.chars().count() <= CHARACTER_COUNT_PER_LINE {
+            output += cloned_line.as_str();
+            output += "\n";
 
-                    break;
-                }
+            offset += cloned_line.len();
 
-                // Take all the characters that would result in a full line
-                let soft_wrapped_line_without_wordbreak: String =
-                    cloned_line.chars().take(CHARACTER_COUNT_PER_LINE).collect();
+            break;
+        }
 
-                // Find the reverse index (from the back of the line) of the first word break
-                let word_break_index_from_back = soft_wrapped_line_without_wordbreak
+        // Take all the characters that would result in a full line
+        let soft_wrapped_line_without_wordbreak: String =
+            cloned_line.chars().take(CHARACTER_COUNT_PER_LINE).collect();
+
+        // Find the reverse index (from the back of the line) of the first word break
+        // Without whitespace, compound words and paths are broken after a hyphen or slash
+        let word_break_index_from_back = soft_wrapped_line_without_wordbreak
+            .chars()
+            .rev()
+            .position(|character| character.is_whitespace())
+            .or_else(|| {
+                soft_wrapped_line_without_wordbreak
                     .chars()
                     .rev()
-                    .position(|character| character.is_whitespace());
+                    .position(|character| BREAK_CHARACTERS.contains(&character))
+            });
+
+        // Find the word break index from the front instead
+        // If there is no word break in the entire line
+        // then the word is broken at the edge of the line
+        let word_break_index = match word_break_index_from_back {
+            Some(index) => CHARACTER_COUNT_PER_LINE - index,
+            None => CHARACTER_COUNT_PER_LINE,
+        };
 
-                // If there is no word break in the entire line
-                if let None = word_break_index_from_back {
-                    panic!("Properly handle 1 word that is too long for a single line");
-                }
+        let word_break_byte_index = cloned_line
+            .char_indices()
+            .nth(word_break_index)
+            .map_or(cloned_line.len(), |(index, _)| index);
 
-                // Find the word break index from the front instead
-                let word_break_index =
-                    CHARACTER_COUNT_PER_LINE - word_break_index_from_back.unwrap();
+        // Find the actual content we want to be on this line
+        // I.e. all the content up to the last word break before the line is full
+        // Remove the taken content from the full, non-wrapped line content
+        let rest = cloned_line.split_off(word_break_byte_index);
+        let soft_wrapped_line = std::mem::replace(&mut cloned_line, rest);
 
-                // Find the actual content we want to be on this line
-                // I.e. all the content up to the last word break before the line is full
-                let soft_wrapped_line: String = soft_wrapped_line_without_wordbreak
-                    .chars()
-                    .take(word_break_index)
-                    .collect();
+        output += soft_wrapped_line.as_str();
+        output += "\n";
+
+        offset += soft_wrapped_line.len();
+
+        // To add the soft-wrap newline character as well
+        // It not then the wrap points will point to offsets in the original text
+        // We want them to point to offsets in the wrapped text
+        offset += 1;
+
+        wrap_points.push(offset);
+
+        continue;
+    }
+
+    return (output, wrap_points);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    // The wrapped lines, without the newlines inserted at the wrap points
+    fn wrapped_lines(text: &str) -> Vec<String> {
+        let (wrapped, _) = wrap_line(text);
 
-                // Remove the taken content from the full, non-wrapped line content
-                cloned_line = cloned_line.split_off(word_break_index);
+        return wrapped.lines().map(String::from).collect();
+    }
+
+    #[test]
+    fn multi_byte_text_within_the_width_is_not_wrapped() {
+        let line = format!("a{}", "—".repeat(20));
+
+        assert_eq!(wrapped_lines(&line), vec![line]);
+    }
 
-                output += soft_wrapped_line.as_str();
-                output += "\n";
+    #[test]
+    fn multi_byte_text_is_wrapped_by_characters() {
+        let line = "é".repeat(70);
+        let lines = wrapped_lines(&line);
 
-                offset += soft_wrapped_line.len();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].chars().count(), CHARACTER_COUNT_PER_LINE);
+        assert_eq!(lines.concat(), line);
+    }
+
+    #[test]
+    fn curly_quotes_are_not_duplicated_when_wrapping() {
+        let line = "“Quoted” text about “things” and ‘more’ words that go on and on, about things here and there";
+        let lines = wrapped_lines(line);
+
+        assert!(lines.len() > 1);
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() <= CHARACTER_COUNT_PER_LINE));
+        assert_eq!(lines.concat(), line);
+    }
 
-                // To add the soft-wrap newline character as well
-                // It not then the wrap points will point to offsets in the original text
-                // We want them to point to offsets in the wrapped text
-                offset += 1;
+    #[test]
+    fn wrap_points_are_offsets_in_the_wrapped_text() {
+        let text = format!("{} end\n", "—".repeat(70));
+        let wrapped = WrappedText::new(text.clone());
+        let wrapped_text = wrapped.to_string();
 
-                wrap_points.push(offset);
+        for point in wrapped.wrap_points() {
+            assert!(wrapped_text.is_char_boundary(point));
+            assert_eq!(&wrapped_text[point - 1..point], "\n");
+        }
+
+        assert_eq!(wrapped.original().to_string(), text);
+    }
+
+    #[test]
+    fn long_hyphenated_words_are_wrapped_after_a_hyphen() {
+        let line = "context-sensitive-configuration-of-the-editor-and-everything-around-it";
+        let lines = wrapped_lines(line);
+
+        assert_eq!(
+            lines,
+            vec![
+                "context-sensitive-configuration-of-the-editor-and-",
+                "everything-around-it"
+            ]
+        );
+    }
+
+    #[test]
+    fn urls_are_wrapped_after_a_slash() {
+        let line = "https://example.com/documentation/getting-started/installation/linux";
+        let lines = wrapped_lines(line);
+
+        assert_eq!(
+            lines,
+            vec![
+                "https://example.com/documentation/getting-started/",
+                "installation/linux"
+            ]
+        );
+    }
 
-                continue;
+    #[test]
+    fn whitespace_is_preferred_over_hyphens() {
+        let line = format!("{}well-known", "word ".repeat(11));
+
+        assert_eq!(
+            wrapped_lines(&line),
+            vec!["word ".repeat(11), "well-known".to_string()]
+        );
+    }
+
+    #[test]
+    fn words_without_break_opportunities_are_broken_at_the_width() {
+        let line = "a".repeat(70);
+        let (wrapped, wrap_points) = wrap_line(&line);
+
+        assert_eq!(wrapped, format!("{}\n{}\n", "a".repeat(60), "a".repeat(10)));
+        assert_eq!(wrap_points, vec![61]);
+    }
+
+    // The offsets moving word by word stops at, going forwards and then backwards
+    fn word_stops(text: &str, word_boundaries: WordBoundaries) -> (Vec<usize>, Vec<usize>) {
+        let text = WrappedText::new(text.to_string());
+        let length = text.original().to_string().len();
+        let mut forwards = vec![];
+        let mut offset = 0;
+
+        while let Some(next) = text.next_word_boundary(offset, word_boundaries) {
+            if next <= offset {
+                break;
             }
 
-            offset += 1;
+            forwards.push(next);
+            offset = next;
         }
 
-        return (output, wrap_points);
+        let mut backwards = vec![];
+        let mut offset = length;
+
+        while offset > 0 {
+            offset = text.previous_word_boundary(offset, word_boundaries);
+            backwards.push(offset);
+        }
+
+        return (forwards, backwards);
     }
 
-    pub fn to_string(&self) -> String {
-        let (output, _) = self.to_string_with_wrap_points();
+    #[test]
+    fn words_end_at_whitespace() {
+        assert_eq!(
+            word_stops("foo.bar(baz)", WordBoundaries::Whitespace),
+            (vec![12], vec![0])
+        );
+        assert_eq!(
+            word_stops("say foo.bar(baz) now", WordBoundaries::Whitespace),
+            (vec![3, 16, 20], vec![17, 4, 0])
+        );
+    }
+
+    #[test]
+    fn words_end_at_punctuation() {
+        assert_eq!(
+            word_stops("foo.bar(baz)", WordBoundaries::Punctuation),
+            (vec![3, 4, 7, 8, 11, 12], vec![11, 8, 7, 4, 3, 0])
+        );
+        assert_eq!(
+            word_stops("say foo.bar(baz) now", WordBoundaries::Punctuation),
+            (
+                vec![3, 7, 8, 11, 12, 15, 16, 20],
+                vec![17, 15, 12, 11, 8, 7, 4, 0]
+            )
+        );
+    }
+
+    #[test]
+    fn word_boundaries_fall_between_multi_byte_characters() {
+        let text = "café — “naïve” über";
+
+        assert_eq!(
+            word_stops(text, WordBoundaries::Whitespace),
+            (vec![5, 9, 22, 28], vec![23, 10, 6, 0])
+        );
+        assert_eq!(
+            word_stops(text, WordBoundaries::Punctuation),
+            (vec![5, 9, 13, 19, 22, 28], vec![23, 19, 13, 10, 6, 0])
+        );
+    }
+
+    #[test]
+    fn word_boundaries_of_an_empty_text() {
+        let text = WrappedText::new(String::new());
+
+        for word_boundaries in [WordBoundaries::Whitespace, WordBoundaries::Punctuation] {
+            assert_eq!(text.previous_word_boundary(0, word_boundaries), 0);
+            assert_eq!(text.previous_word_boundary(5, word_boundaries), 0);
+            assert_eq!(text.next_word_boundary(0, word_boundaries), None);
+        }
+    }
+
+    // Text that is typed or pasted in the edits below, with multi-byte characters and newlines
+    const INSERTIONS: [&str; 10] = [
+        "a",
+        "word ",
+        "\n",
+        "—",
+        "“quoted” ",
+        "café ",
+        "\r\n",
+        "- item\n",
+        "## Headline ",
+        "A sentence that is long enough to be wrapped on its own, with “curly” quotes — and dashes.",
+    ];
+
+    // A small deterministic generator, so failures can be reproduced
+    fn random(seed: &mut u64, below: usize) -> usize {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+
+        return (*seed >> 33) as usize % below;
+    }
+
+    // A random range of whole characters and what replaces it
+    fn random_edit(text: &str, seed: &mut u64) -> (Range<usize>, String) {
+        let boundaries: Vec<usize> = text
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([text.len()])
+            .collect();
+        let start = random(seed, boundaries.len());
+        // Mostly typing, sometimes deleting or replacing a few characters
+        let length = match random(seed, 3) {
+            0 => 0,
+            _ => random(seed, std::cmp::min(boundaries.len() - start, 80)),
+        };
+        let replacement = match random(seed, 4) {
+            0 => String::new(),
+            _ => INSERTIONS[random(seed, INSERTIONS.len())].to_string(),
+        };
+
+        return (boundaries[start]..boundaries[start + length], replacement);
+    }
+
+    #[test]
+    fn the_gap_buffer_matches_a_string() {
+        let mut seed = 1;
+        let mut expected = "Some “text” — to start with\n".repeat(20);
+        let mut text = RawText::new(expected.clone());
+
+        for _ in 0..2000 {
+            let (range, replacement) = random_edit(&expected, &mut seed);
 
-        return output;
+            expected.replace_range(range.clone(), &replacement);
+            text.replace(range, replacement);
+
+            let (range, _) = random_edit(&expected, &mut seed);
+
+            assert_eq!(text.to_string(), expected);
+            assert_eq!(text.read_range(range.clone()), expected[range]);
+        }
+    }
+
+    #[test]
+    fn the_gap_buffer_grows_for_large_insertions() {
+        let mut text = RawText::new("start end".into());
+        let insertion = "é".repeat(GAP_SIZE * 2);
+
+        text.replace(6..6, insertion.clone());
+        text.replace(0..0, insertion.clone());
+
+        assert_eq!(
+            text.to_string(),
+            format!("{}start {}end", insertion, insertion)
+        );
+    }
+
+    // A micro-benchmark against a String, which copies the whole tail of the text on every edit
+    #[test]
+    fn typing_near_the_top_of_a_large_document_is_faster_than_with_a_string() {
+        let document = "Some “text” — to start with\n".repeat(40_000);
+        let mut expected = document.clone();
+        let mut text = RawText::new(document);
+
+        let start = Instant::now();
+
+        for offset in 10..2010 {
+            expected.replace_range(offset..offset, "a");
+        }
+
+        let string_time = start.elapsed();
+        let start = Instant::now();
+
+        for offset in 10..2010 {
+            text.replace(offset..offset, "a".into());
+        }
+
+        let gap_buffer_time = start.elapsed();
+
+        assert_eq!(text.to_string(), expected);
+        assert!(
+            gap_buffer_time * 10 < string_time,
+            "{:?} with the gap buffer, {:?} with a String",
+            gap_buffer_time,
+            string_time
+        );
+    }
+
+    #[test]
+    fn reading_characters_around_the_gap() {
+        let mut text = RawText::new("café über".into());
+
+        // The gap now sits between the two words
+        text.replace(5..6, " — ".into());
+
+        assert_eq!(text.to_string(), "café — über");
+        assert_eq!(text.previous_character(5), Some('é'));
+        assert_eq!(text.next_character(5), Some(' '));
+        assert_eq!(text.next_character(6), Some('—'));
+        assert_eq!(text.previous_character(9), Some('—'));
+        assert_eq!(text.previous_character(0), None);
+        assert_eq!(text.next_character(text.len()), None);
+        assert!(!text.is_char_boundary(4));
+        assert!(!text.is_char_boundary(7));
+        assert!(text.is_char_boundary(text.len()));
+    }
+
+    #[test]
+    fn rewrapping_edits_matches_wrapping_from_scratch() {
+        for wrap in [true, false] {
+            let mut seed = 2;
+            let mut expected = "A line that is long enough to be wrapped, with “quotes” — and dashes, and café.\n\nShort\n".repeat(10);
+            let mut text = WrappedText::new(expected.clone());
+
+            text.set_wrap(wrap);
+
+            for _ in 0..500 {
+                let (range, replacement) = random_edit(&expected, &mut seed);
+                let start = text.wrapped_offset(range.start);
+                let end = text.wrapped_offset(range.end);
+
+                assert_eq!(text.original_offset(start), range.start);
+                assert_eq!(text.original_offset(end), range.end);
+
+                expected.replace_range(range, &replacement);
+                text.replace(start..end, replacement);
+
+                let mut fresh = WrappedText::new(expected.clone());
+                fresh.set_wrap(wrap);
+
+                assert_eq!(text.original().to_string(), expected);
+                assert_eq!(text.to_string(), fresh.to_string());
+                assert_eq!(text.wrap_points(), fresh.wrap_points());
+            }
+        }
     }
 }
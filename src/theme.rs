@@ -0,0 +1,133 @@
+use crate::settings::Palette;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Appearance {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    pub fn toggled(&self) -> Appearance {
+        return match self {
+            Appearance::Light => Appearance::Dark,
+            Appearance::Dark => Appearance::Light,
+        };
+    }
+}
+
+// What each part of the UI is colored with
+// Both themes pick their colors from the same palette
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub appearance: Appearance,
+    pub background: u32,
+    pub text: u32,
+    pub border: u32,
+    pub surface: u32,
+    pub surface_hover: u32,
+    pub surface_disabled: u32,
+    pub text_muted: u32,
+    pub text_secondary: u32,
+    pub text_disabled: u32,
+    pub accent: u32,
+    pub accent_background: u32,
+    pub accent_border: u32,
+    pub warning: u32,
+    pub error: u32,
+    // Editor
+    pub headline: u32,
+    pub body: u32,
+    pub dimmed: u32,
+    pub caret: u32,
+    pub caret_unfocused: u32,
+    pub caret_read_only: u32,
+    pub selection: u32,
+    pub selection_unfocused: u32,
+    pub ruler: u32,
+    pub scrollbar: u32,
+    pub occurrence: u32,
+    pub line_added: u32,
+    pub line_modified: u32,
+    pub status_background: u32,
+    pub status_text: u32,
+}
+
+impl Theme {
+    pub fn new(appearance: Appearance, palette: &Palette) -> Theme {
+        return match appearance {
+            Appearance::Light => Theme::light(palette),
+            Appearance::Dark => Theme::dark(palette),
+        };
+    }
+
+    pub fn light(palette: &Palette) -> Theme {
+        return Theme {
+            appearance: Appearance::Light,
+            background: palette.white,
+            text: palette.black,
+            border: palette.gray_100,
+            surface: palette.gray_100,
+            surface_hover: palette.gray_200,
+            surface_disabled: palette.gray_50,
+            text_muted: palette.gray_500,
+            text_secondary: palette.gray_600,
+            text_disabled: palette.gray_300,
+            accent: palette.blue_dark,
+            accent_background: palette.blue_light,
+            accent_border: palette.blue_medium,
+            warning: palette.orange_dark,
+            error: palette.red,
+            headline: palette.gray_800,
+            body: palette.gray_700,
+            dimmed: palette.gray_300,
+            caret: palette.blue_dark,
+            caret_unfocused: palette.gray_400,
+            caret_read_only: palette.orange_dark,
+            selection: palette.blue_medium,
+            selection_unfocused: palette.gray_300,
+            ruler: palette.gray_200,
+            scrollbar: palette.gray_300,
+            occurrence: palette.gray_200,
+            line_added: palette.green,
+            line_modified: palette.blue_dark,
+            status_background: palette.gray_800,
+            status_text: palette.gray_50,
+        };
+    }
+
+    pub fn dark(palette: &Palette) -> Theme {
+        return Theme {
+            appearance: Appearance::Dark,
+            background: palette.gray_950,
+            text: palette.gray_50,
+            border: palette.gray_800,
+            surface: palette.gray_900,
+            surface_hover: palette.gray_800,
+            surface_disabled: palette.gray_900,
+            text_muted: palette.gray_400,
+            text_secondary: palette.gray_300,
+            text_disabled: palette.gray_700,
+            accent: palette.blue_medium,
+            accent_background: palette.blue_darkest,
+            accent_border: palette.blue_dark,
+            warning: palette.orange_dark,
+            error: palette.red,
+            headline: palette.gray_100,
+            body: palette.gray_300,
+            dimmed: palette.gray_700,
+            caret: palette.blue_medium,
+            caret_unfocused: palette.gray_600,
+            caret_read_only: palette.orange_dark,
+            selection: palette.blue_darkest,
+            selection_unfocused: palette.gray_700,
+            ruler: palette.gray_800,
+            scrollbar: palette.gray_700,
+            occurrence: palette.gray_800,
+            line_added: palette.green,
+            line_modified: palette.blue_medium,
+            status_background: palette.gray_100,
+            status_text: palette.gray_900,
+        };
+    }
+}
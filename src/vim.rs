@@ -0,0 +1,37 @@
+// Modal editing, where keys move around and edit in Normal mode and type text in Insert mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VimMode {
+    Normal,
+    Insert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VimCommand {
+    Left,
+    Down,
+    Up,
+    Right,
+    Insert,
+    Append,
+    DeleteCharacter,
+    DeleteLine,
+}
+
+// What a key typed in Normal mode does
+// Commands like `dd` take two keys, so the first one is returned as pending until the next arrives
+pub fn command(pending: Option<char>, key: char) -> (Option<VimCommand>, Option<char>) {
+    return match (pending, key) {
+        (Some('d'), 'd') => (Some(VimCommand::DeleteLine), None),
+        // A key that doesn't finish the command cancels it, like in vim
+        (Some(_), _) => (None, None),
+        (None, 'd') => (None, Some('d')),
+        (None, 'h') => (Some(VimCommand::Left), None),
+        (None, 'j') => (Some(VimCommand::Down), None),
+        (None, 'k') => (Some(VimCommand::Up), None),
+        (None, 'l') => (Some(VimCommand::Right), None),
+        (None, 'i') => (Some(VimCommand::Insert), None),
+        (None, 'a') => (Some(VimCommand::Append), None),
+        (None, 'x') => (Some(VimCommand::DeleteCharacter), None),
+        (None, _) => (None, None),
+    };
+}